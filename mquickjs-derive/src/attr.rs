@@ -0,0 +1,97 @@
+//! Parsing of `#[js(...)]` attributes.
+
+use syn::{Attribute, Field, Ident, LitStr, Variant};
+
+/// Options accepted on struct fields.
+pub(crate) struct FieldAttrs {
+    pub(crate) rename: Option<String>,
+    pub(crate) default: bool,
+    pub(crate) skip: bool,
+}
+
+/// Options accepted on enum variants.
+pub(crate) struct VariantAttrs {
+    pub(crate) rename: Option<String>,
+}
+
+impl FieldAttrs {
+    pub(crate) fn parse(field: &Field) -> syn::Result<Self> {
+        let mut attrs = Self {
+            rename: None,
+            default: false,
+            skip: false,
+        };
+        for_each_js_attr(&field.attrs, |meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                attrs.rename = Some(value.value());
+            } else if meta.path.is_ident("default") {
+                attrs.default = true;
+            } else if meta.path.is_ident("skip") {
+                attrs.skip = true;
+            } else {
+                return Err(meta.error("unsupported field attribute, expected `rename`, `default` or `skip`"));
+            }
+            Ok(())
+        })?;
+        Ok(attrs)
+    }
+
+    /// Parse the options of a tuple field, which has no property that could
+    /// be missing, so `default` is rejected.
+    pub(crate) fn parse_unnamed(field: &Field) -> syn::Result<Self> {
+        let attrs = Self::parse(field)?;
+        if attrs.default {
+            return Err(syn::Error::new_spanned(field, "`#[js(default)]` is only supported on named fields"));
+        }
+        Ok(attrs)
+    }
+
+    /// The JS property name for a named field.
+    pub(crate) fn js_name(&self, ident: &Ident) -> String {
+        self.rename.clone().unwrap_or_else(|| unraw(ident))
+    }
+}
+
+impl VariantAttrs {
+    pub(crate) fn parse(variant: &Variant) -> syn::Result<Self> {
+        let mut attrs = Self { rename: None };
+        for_each_js_attr(&variant.attrs, |meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                attrs.rename = Some(value.value());
+            } else {
+                return Err(meta.error("unsupported variant attribute, expected `rename`"));
+            }
+            Ok(())
+        })?;
+        Ok(attrs)
+    }
+
+    /// The JS tag for the variant.
+    pub(crate) fn js_name(&self, ident: &Ident) -> String {
+        self.rename.clone().unwrap_or_else(|| unraw(ident))
+    }
+}
+
+/// Reject `#[js(...)]` on the container itself, which has no options yet.
+pub(crate) fn reject_container_attrs(attrs: &[Attribute]) -> syn::Result<()> {
+    for_each_js_attr(attrs, |meta| Err(meta.error("unsupported container attribute")))
+}
+
+fn for_each_js_attr(
+    attrs: &[Attribute],
+    mut f: impl FnMut(syn::meta::ParseNestedMeta<'_>) -> syn::Result<()>,
+) -> syn::Result<()> {
+    for attr in attrs {
+        if attr.path().is_ident("js") {
+            attr.parse_nested_meta(&mut f)?;
+        }
+    }
+    Ok(())
+}
+
+fn unraw(ident: &Ident) -> String {
+    let name = ident.to_string();
+    name.strip_prefix("r#").map(str::to_string).unwrap_or(name)
+}
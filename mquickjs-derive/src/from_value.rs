@@ -0,0 +1,193 @@
+//! Code generation for `#[derive(FromValue)]`.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attr::{FieldAttrs, VariantAttrs, reject_container_attrs};
+use crate::ctx_generics;
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    reject_container_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let type_name = name.to_string();
+    let generics = ctx_generics(&input.generics, quote!(::mquickjs_rs::FromValue<'ctx>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let construct = construct(quote!(Self), &data.fields, quote!(value), &type_name)?;
+            quote!(Ok(#construct))
+        }
+        Data::Enum(data) => enum_body(data, &type_name)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromValue cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::mquickjs_rs::FromValue<'ctx> for #name #ty_generics #where_clause {
+            fn from_value(
+                value: ::mquickjs_rs::Value<'ctx>,
+            ) -> ::core::result::Result<Self, ::mquickjs_rs::JsError> {
+                #body
+            }
+        }
+    })
+}
+
+fn enum_body(data: &syn::DataEnum, type_name: &str) -> syn::Result<TokenStream> {
+    let mut unit_arms = Vec::new();
+    let mut payload_arms = Vec::new();
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(variant)?;
+        let tag = attrs.js_name(&variant.ident);
+        let ident = &variant.ident;
+        match &variant.fields {
+            Fields::Unit => {
+                unit_arms.push(quote!(#tag => Ok(Self::#ident),));
+                payload_arms.push(quote!(#tag => Ok(Self::#ident),));
+            }
+            fields => {
+                let what = format!("{type_name}::{ident}");
+                let construct = construct(quote!(Self::#ident), fields, quote!(payload), &what)?;
+                payload_arms.push(quote! {
+                    #tag => {
+                        let result: ::core::result::Result<Self, ::mquickjs_rs::JsError> =
+                            (|| Ok(#construct))();
+                        result.map_err(|err| err.at_key(#tag))
+                    }
+                });
+            }
+        }
+    }
+
+    let unknown = format!("unknown variant '{{}}' for {type_name}");
+    let string_branch = quote! {
+        if ::mquickjs_rs::__private::is_string(value) {
+            let tag: ::mquickjs_rs::__private::String = ::mquickjs_rs::FromValue::from_value(value)?;
+            return match tag.as_str() {
+                #(#unit_arms)*
+                other => Err(::mquickjs_rs::__private::error(::mquickjs_rs::__private::format!(#unknown, other))),
+            };
+        }
+    };
+
+    let has_payload = data
+        .variants
+        .iter()
+        .any(|variant| !matches!(variant.fields, Fields::Unit));
+    if !has_payload {
+        let expected = format!("expected string for {type_name}");
+        return Ok(quote! {
+            #string_branch
+            Err(::mquickjs_rs::__private::error(::mquickjs_rs::__private::String::from(#expected)))
+        });
+    }
+
+    let single_key = format!("expected object with a single key for {type_name}");
+    Ok(quote! {
+        #string_branch
+        ::mquickjs_rs::__private::expect_object(value, #type_name)?;
+        let keys = ::mquickjs_rs::__private::keys(value)?;
+        if keys.len() != 1 {
+            return Err(::mquickjs_rs::__private::error(::mquickjs_rs::__private::String::from(#single_key)));
+        }
+        let payload = ::mquickjs_rs::__private::get_property(value, &keys[0])?;
+        match keys[0].as_str() {
+            #(#payload_arms)*
+            other => Err(::mquickjs_rs::__private::error(::mquickjs_rs::__private::format!(#unknown, other))),
+        }
+    })
+}
+
+/// Build an expression constructing `path` from the JS value `source`.
+fn construct(
+    path: TokenStream,
+    fields: &Fields,
+    source: TokenStream,
+    type_name: &str,
+) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(fields) => {
+            let mut inits = Vec::new();
+            for field in &fields.named {
+                let attrs = FieldAttrs::parse(field)?;
+                let ident = field.ident.as_ref().expect("named field");
+                if attrs.skip {
+                    inits.push(quote!(#ident: ::core::default::Default::default(),));
+                    continue;
+                }
+                let key = attrs.js_name(ident);
+                let read = read_field(
+                    quote!(::mquickjs_rs::__private::get_property(#source, #key)?),
+                    attrs.default,
+                    quote!(at_key(#key)),
+                );
+                inits.push(quote!(#ident: #read,));
+            }
+            Ok(quote! {{
+                ::mquickjs_rs::__private::expect_object(#source, #type_name)?;
+                #path { #(#inits)* }
+            }})
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let attrs = FieldAttrs::parse_unnamed(&fields.unnamed[0])?;
+            if attrs.skip {
+                Ok(quote!(#path(::core::default::Default::default())))
+            } else {
+                Ok(quote!(#path(::mquickjs_rs::FromValue::from_value(#source)?)))
+            }
+        }
+        Fields::Unnamed(fields) => {
+            let mut inits = Vec::new();
+            let mut index = 0u32;
+            for field in &fields.unnamed {
+                let attrs = FieldAttrs::parse_unnamed(field)?;
+                if attrs.skip {
+                    inits.push(quote!(::core::default::Default::default(),));
+                    continue;
+                }
+                let position = index as usize;
+                let read = read_field(
+                    quote!(::mquickjs_rs::__private::get_element(#source, #index)?),
+                    attrs.default,
+                    quote!(at_index(#position)),
+                );
+                inits.push(quote!(#read,));
+                index += 1;
+            }
+            Ok(quote! {{
+                ::mquickjs_rs::__private::expect_array_length(#source, #index)?;
+                #path(#(#inits)*)
+            }})
+        }
+        Fields::Unit => Ok(path),
+    }
+}
+
+/// Read one field, recording `segment` in the error path on failure.
+fn read_field(element: TokenStream, default: bool, segment: TokenStream) -> TokenStream {
+    let convert = quote! {
+        ::mquickjs_rs::FromValue::from_value(element).map_err(|err| err.#segment)?
+    };
+    if default {
+        quote! {{
+            let element = #element;
+            if ::mquickjs_rs::__private::is_undefined(element) {
+                ::core::default::Default::default()
+            } else {
+                #convert
+            }
+        }}
+    } else {
+        quote! {{
+            let element = #element;
+            #convert
+        }}
+    }
+}
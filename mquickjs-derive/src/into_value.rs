@@ -0,0 +1,169 @@
+//! Code generation for `#[derive(IntoValue)]`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attr::{FieldAttrs, VariantAttrs, reject_container_attrs};
+use crate::ctx_generics;
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    reject_container_attrs(&input.attrs)?;
+    let name = &input.ident;
+    let generics = ctx_generics(&input.generics, quote!(::mquickjs_rs::IntoValue<'ctx>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => struct_body(&data.fields)?,
+        Data::Enum(data) => {
+            let mut arms = Vec::with_capacity(data.variants.len());
+            for variant in &data.variants {
+                let attrs = VariantAttrs::parse(variant)?;
+                let tag = attrs.js_name(&variant.ident);
+                let ident = &variant.ident;
+                let arm = match &variant.fields {
+                    Fields::Unit => quote! {
+                        Self::#ident => ::mquickjs_rs::IntoValue::into_value(#tag, ctx),
+                    },
+                    Fields::Unnamed(fields) => {
+                        let bindings: Vec<_> = (0..fields.unnamed.len())
+                            .map(|index| format_ident!("__field{}", index))
+                            .collect();
+                        let payload = if bindings.len() == 1 {
+                            let binding = &bindings[0];
+                            quote!(::mquickjs_rs::IntoValue::into_value(#binding, ctx)?)
+                        } else {
+                            array_payload(fields.unnamed.iter().zip(&bindings))?
+                        };
+                        quote! {
+                            #[allow(unused_variables)]
+                            Self::#ident(#(#bindings),*) => {
+                                let payload = #payload;
+                                let object = ::mquickjs_rs::__private::new_object(ctx)?;
+                                ::mquickjs_rs::__private::set_property(ctx, object, #tag, payload)?;
+                                Ok(object)
+                            }
+                        }
+                    }
+                    Fields::Named(fields) => {
+                        let idents: Vec<_> = fields
+                            .named
+                            .iter()
+                            .map(|field| field.ident.clone().expect("named field"))
+                            .collect();
+                        let payload = object_payload(
+                            fields.named.iter().zip(idents.iter().map(|ident| quote!(#ident))),
+                        )?;
+                        quote! {
+                            #[allow(unused_variables)]
+                            Self::#ident { #(#idents),* } => {
+                                let payload = #payload;
+                                let object = ::mquickjs_rs::__private::new_object(ctx)?;
+                                ::mquickjs_rs::__private::set_property(ctx, object, #tag, payload)?;
+                                Ok(object)
+                            }
+                        }
+                    }
+                };
+                arms.push(arm);
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "IntoValue cannot be derived for unions",
+            ));
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::mquickjs_rs::IntoValue<'ctx> for #name #ty_generics #where_clause {
+            fn into_value(
+                self,
+                ctx: &'ctx ::mquickjs_rs::Context,
+            ) -> ::core::result::Result<::mquickjs_rs::Value<'ctx>, ::mquickjs_rs::JsError> {
+                #body
+            }
+        }
+    })
+}
+
+fn struct_body(fields: &Fields) -> syn::Result<TokenStream> {
+    match fields {
+        Fields::Named(fields) => {
+            let payload = object_payload(fields.named.iter().map(|field| {
+                let ident = field.ident.as_ref().expect("named field");
+                (field, quote!(self.#ident))
+            }))?;
+            Ok(quote!(Ok(#payload)))
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let attrs = FieldAttrs::parse(&fields.unnamed[0])?;
+            if attrs.skip {
+                Ok(quote!(Ok(::mquickjs_rs::__private::null(ctx))))
+            } else {
+                Ok(quote!(::mquickjs_rs::IntoValue::into_value(self.0, ctx)))
+            }
+        }
+        Fields::Unnamed(fields) => {
+            let accessors: Vec<_> = (0..fields.unnamed.len())
+                .map(syn::Index::from)
+                .map(|index| quote!(self.#index))
+                .collect();
+            let payload = array_payload(fields.unnamed.iter().zip(&accessors))?;
+            Ok(quote!(Ok(#payload)))
+        }
+        Fields::Unit => Ok(quote!(Ok(::mquickjs_rs::__private::null(ctx)))),
+    }
+}
+
+/// Build an expression creating a JS object from `(field, accessor)` pairs.
+fn object_payload<'a>(
+    fields: impl Iterator<Item = (&'a syn::Field, TokenStream)>,
+) -> syn::Result<TokenStream> {
+    let mut sets = Vec::new();
+    for (field, accessor) in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        if attrs.skip {
+            continue;
+        }
+        let key = attrs.js_name(field.ident.as_ref().expect("named field"));
+        sets.push(quote! {
+            ::mquickjs_rs::__private::set_property(ctx, object, #key, #accessor)?;
+        });
+    }
+    Ok(quote! {{
+        let object = ::mquickjs_rs::__private::new_object(ctx)?;
+        #(#sets)*
+        object
+    }})
+}
+
+/// Build an expression creating a JS array from `(field, accessor)` pairs.
+fn array_payload<'a, A: quote::ToTokens + 'a>(
+    fields: impl Iterator<Item = (&'a syn::Field, A)>,
+) -> syn::Result<TokenStream> {
+    let mut sets = Vec::new();
+    for (field, accessor) in fields {
+        let attrs = FieldAttrs::parse(field)?;
+        if attrs.skip {
+            continue;
+        }
+        let index = sets.len() as u32;
+        sets.push(quote! {
+            ::mquickjs_rs::__private::set_element(ctx, array, #index, #accessor)?;
+        });
+    }
+    let length = sets.len();
+    Ok(quote! {{
+        let array = ::mquickjs_rs::__private::new_array(ctx, #length)?;
+        #(#sets)*
+        array
+    }})
+}
@@ -0,0 +1,46 @@
+//! Derive macros for the `mquickjs-rs` conversion traits.
+//!
+//! See the `mquickjs-rs` documentation of `FromValue` and `IntoValue` for the
+//! generated representation and the supported `#[js(...)]` attributes.
+
+mod attr;
+mod from_value;
+mod into_value;
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+/// Derive `mquickjs_rs::IntoValue`.
+#[proc_macro_derive(IntoValue, attributes(js))]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_value::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `mquickjs_rs::FromValue`.
+#[proc_macro_derive(FromValue, attributes(js))]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_value::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Add the `'ctx` lifetime and a `T: bound` predicate for every type parameter.
+pub(crate) fn ctx_generics(
+    generics: &syn::Generics,
+    bound: proc_macro2::TokenStream,
+) -> syn::Generics {
+    let mut generics = generics.clone();
+    let type_params: Vec<syn::Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    generics.params.insert(0, syn::parse_quote!('ctx));
+    let where_clause = generics.make_where_clause();
+    for ident in type_params {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#ident: #bound));
+    }
+    generics
+}
@@ -86,15 +86,34 @@ fn generate_stdlib_source(
     }
 
     let source_path = out_dir.join("mquickjs_stdlib.c");
-    let mut contents = String::from("#include <stddef.h>\n#include <stdint.h>\n#include \"mquickjs_priv.h\"\n\n");
+    // wrapper.h defines the ids of the stdlib's user classes and C closures.
+    let mut contents = String::from(
+        "#include <stddef.h>\n#include <stdint.h>\n#include \"mquickjs_priv.h\"\n#include \"wrapper.h\"\n\n",
+    );
     contents.push_str(
         "JSValue js_print(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+JSValue js_console_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic);\n\
 JSValue js_gc(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
 JSValue js_date_now(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
 JSValue js_performance_now(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
 JSValue js_load(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
 JSValue js_setTimeout(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
-JSValue js_clearTimeout(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\n",
+JSValue js_setInterval(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+JSValue js_clearTimeout(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+JSValue js_promise_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic);\n\
+JSValue js_promise_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+void js_promise_finalizer(JSContext *ctx, void *opaque);\n\
+JSValue js_promise_resolve_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_reject_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_all_element_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_finally_then_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_finally_catch_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_return_value_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_promise_throw_value_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, JSValue params);\n\
+JSValue js_collection_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic);\n\
+JSValue js_map_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+JSValue js_set_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);\n\
+\n",
     );
     contents.push_str(&String::from_utf8_lossy(&output.stdout));
     std::fs::write(&source_path, contents).expect("failed to write mquickjs_stdlib.c");
@@ -159,6 +178,7 @@ fn main() {
         .allowlist_function("JS_.*")
         .allowlist_var("JS_.*")
         .allowlist_var("js_stdlib")
+        .use_core()
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .generate()
         .expect("unable to generate bindings");
@@ -170,6 +190,7 @@ fn main() {
     let mut build = cc::Build::new();
     build.include(&mquickjs_dir);
     build.include(&out_dir);
+    build.include(&manifest_dir);
     build.warnings(false);
 
     for source in &sources {
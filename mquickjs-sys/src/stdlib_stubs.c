@@ -1,22 +1,22 @@
 #include <stddef.h>
 #include <stdint.h>
 
-#include "mquickjs.h"
+#include "../wrapper.h"
 
-typedef JSValue (*JSHostCallback)(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);
-
-static JSHostCallback host_callback = NULL;
+static JSHostHooks *host_hooks(JSContext *ctx) {
+    return (JSHostHooks *)JS_GetContextOpaque(ctx);
+}
 
-void JS_SetHostCallback(JSHostCallback callback) {
-    host_callback = callback;
+JSValue js_console_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->console) {
+        return hooks->console(ctx, this_val, argc, argv, magic);
+    }
+    return JS_UNDEFINED;
 }
 
 JSValue js_print(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    (void)ctx;
-    (void)this_val;
-    (void)argc;
-    (void)argv;
-    return JS_UNDEFINED;
+    return js_console_method(ctx, this_val, argc, argv, JS_CONSOLE_LOG);
 }
 
 JSValue js_gc(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
@@ -28,40 +28,117 @@ JSValue js_gc(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
 }
 
 JSValue js_date_now(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    (void)ctx;
-    (void)this_val;
-    (void)argc;
-    (void)argv;
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->date_now) {
+        return hooks->date_now(ctx, this_val, argc, argv);
+    }
     return JS_UNDEFINED;
 }
 
 JSValue js_performance_now(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    (void)ctx;
-    (void)this_val;
-    (void)argc;
-    (void)argv;
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->performance_now) {
+        return hooks->performance_now(ctx, this_val, argc, argv);
+    }
     return JS_UNDEFINED;
 }
 
 JSValue js_load(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    if (host_callback) {
-        return host_callback(ctx, this_val, argc, argv);
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->load) {
+        return hooks->load(ctx, this_val, argc, argv);
     }
     return JS_UNDEFINED;
 }
 
 JSValue js_setTimeout(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    (void)ctx;
-    (void)this_val;
-    (void)argc;
-    (void)argv;
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->set_timeout) {
+        return hooks->set_timeout(ctx, this_val, argc, argv);
+    }
+    return JS_UNDEFINED;
+}
+
+JSValue js_setInterval(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->set_interval) {
+        return hooks->set_interval(ctx, this_val, argc, argv);
+    }
     return JS_UNDEFINED;
 }
 
 JSValue js_clearTimeout(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
-    (void)ctx;
-    (void)this_val;
-    (void)argc;
-    (void)argv;
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->clear_timer) {
+        return hooks->clear_timer(ctx, this_val, argc, argv);
+    }
     return JS_UNDEFINED;
 }
+
+JSValue js_promise_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->promise) {
+        return hooks->promise(ctx, this_val, argc & ~FRAME_CF_CTOR, argv, magic);
+    }
+    return JS_ThrowTypeError(ctx, "Promise is not available");
+}
+
+JSValue js_promise_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
+    if (!(argc & FRAME_CF_CTOR)) {
+        return JS_ThrowTypeError(ctx, "must be called with new");
+    }
+    return js_promise_method(ctx, this_val, argc, argv, JS_PROMISE_CONSTRUCTOR);
+}
+
+void js_promise_finalizer(JSContext *ctx, void *opaque) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->promise_finalizer) {
+        hooks->promise_finalizer(ctx, opaque);
+    }
+}
+
+JSValue js_collection_method(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->collection) {
+        return hooks->collection(ctx, this_val, argc & ~FRAME_CF_CTOR, argv, magic);
+    }
+    return JS_ThrowTypeError(ctx, "Map and Set are not available");
+}
+
+JSValue js_map_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
+    if (!(argc & FRAME_CF_CTOR)) {
+        return JS_ThrowTypeError(ctx, "must be called with new");
+    }
+    return js_collection_method(ctx, this_val, argc, argv, JS_MAP_CONSTRUCTOR);
+}
+
+JSValue js_set_constructor(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv) {
+    if (!(argc & FRAME_CF_CTOR)) {
+        return JS_ThrowTypeError(ctx, "must be called with new");
+    }
+    return js_collection_method(ctx, this_val, argc, argv, JS_SET_CONSTRUCTOR);
+}
+
+static JSValue promise_function(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv,
+                                JSValue params, int func_idx) {
+    JSHostHooks *hooks = host_hooks(ctx);
+    if (hooks && hooks->promise_function) {
+        return hooks->promise_function(ctx, this_val, argc, argv, params, func_idx);
+    }
+    return JS_UNDEFINED;
+}
+
+#define PROMISE_FUNCTION(name)                                                     \
+    JSValue js_##name##_function(JSContext *ctx, JSValue *this_val, int argc,     \
+                                 JSValue *argv, JSValue params) {                 \
+        return promise_function(ctx, this_val, argc, argv, params,                \
+                                JS_CFUNCTION_##name);                             \
+    }
+
+PROMISE_FUNCTION(promise_resolve)
+PROMISE_FUNCTION(promise_reject)
+PROMISE_FUNCTION(promise_all_element)
+PROMISE_FUNCTION(promise_finally_then)
+PROMISE_FUNCTION(promise_finally_catch)
+PROMISE_FUNCTION(promise_return_value)
+PROMISE_FUNCTION(promise_throw_value)
@@ -11,8 +11,9 @@ fn expected_lib_name() -> &'static str {
 
 #[test]
 fn build_outputs_static_library() {
-    let out_dir = option_env!("MQUICKJS_SYS_OUT_DIR")
-        .expect("build script must export MQUICKJS_SYS_OUT_DIR");
+    let Some(out_dir) = option_env!("MQUICKJS_SYS_OUT_DIR") else {
+        panic!("build script must export MQUICKJS_SYS_OUT_DIR");
+    };
     let lib_path = Path::new(&out_dir).join(expected_lib_name());
     assert!(
         lib_path.exists(),
@@ -23,8 +24,9 @@ fn build_outputs_static_library() {
 
 #[test]
 fn build_lists_compiled_c_sources() {
-    let sources = option_env!("MQUICKJS_SYS_C_SOURCES")
-        .expect("build script must export MQUICKJS_SYS_C_SOURCES");
+    let Some(sources) = option_env!("MQUICKJS_SYS_C_SOURCES") else {
+        panic!("build script must export MQUICKJS_SYS_C_SOURCES");
+    };
     let basenames: HashSet<String> = sources
         .split(';')
         .filter(|entry| !entry.trim().is_empty())
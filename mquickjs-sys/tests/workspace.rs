@@ -1,4 +1,5 @@
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn crate_compiles() {
     assert!(true);
 }
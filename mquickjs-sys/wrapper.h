@@ -9,6 +9,94 @@
 extern const JSSTDLibraryDef js_stdlib;
 
 typedef JSValue (*JSHostCallback)(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv);
-void JS_SetHostCallback(JSHostCallback callback);
+typedef JSValue (*JSHostMagicCallback)(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv, int magic);
+typedef JSValue (*JSHostParamsCallback)(JSContext *ctx, JSValue *this_val, int argc, JSValue *argv,
+                                        JSValue params, int func_idx);
+typedef void (*JSHostFinalizer)(JSContext *ctx, void *opaque);
+
+/* user classes of the stdlib */
+typedef enum JSHostClassEnum {
+    JS_CLASS_PROMISE = JS_CLASS_USER,
+    JS_CLASS_MAP,
+    JS_CLASS_SET,
+    JS_HOST_CLASS_COUNT,
+} JSHostClassEnum;
+
+#define JS_CLASS_COUNT JS_HOST_CLASS_COUNT
+
+/* C closures of the stdlib, in the order of js_c_function_decl in
+   mqjs_stdlib.c */
+typedef enum JSHostFunctionEnum {
+    JS_CFUNCTION_promise_resolve = JS_CFUNCTION_USER,
+    JS_CFUNCTION_promise_reject,
+    JS_CFUNCTION_promise_all_element,
+    JS_CFUNCTION_promise_finally_then,
+    JS_CFUNCTION_promise_finally_catch,
+    JS_CFUNCTION_promise_return_value,
+    JS_CFUNCTION_promise_throw_value,
+} JSHostFunctionEnum;
+
+/* Promise methods and queueMicrotask, passed as magic to the promise hook.
+   The values are repeated in mqjs_stdlib.c. */
+typedef enum JSPromiseMethodEnum {
+    JS_PROMISE_CONSTRUCTOR,
+    JS_PROMISE_THEN,
+    JS_PROMISE_CATCH,
+    JS_PROMISE_FINALLY,
+    JS_PROMISE_RESOLVE,
+    JS_PROMISE_REJECT,
+    JS_PROMISE_ALL,
+    JS_PROMISE_RACE,
+    JS_PROMISE_QUEUE_MICROTASK,
+} JSPromiseMethodEnum;
+
+/* Map and Set constructors and methods, passed as magic to the collection
+   hook. The values are repeated in mqjs_stdlib.c. */
+typedef enum JSCollectionMethodEnum {
+    JS_MAP_CONSTRUCTOR,
+    JS_SET_CONSTRUCTOR,
+    JS_COLLECTION_GET,
+    JS_COLLECTION_SET,
+    JS_COLLECTION_ADD,
+    JS_COLLECTION_HAS,
+    JS_COLLECTION_DELETE,
+    JS_COLLECTION_CLEAR,
+    JS_COLLECTION_FOR_EACH,
+    JS_COLLECTION_KEYS,
+    JS_COLLECTION_VALUES,
+    JS_COLLECTION_ENTRIES,
+    JS_COLLECTION_SIZE,
+} JSCollectionMethodEnum;
+
+/* console methods, passed as magic to the console hook. The values are
+   repeated in the js_console table of mqjs_stdlib.c. */
+typedef enum JSConsoleMethodEnum {
+    JS_CONSOLE_LOG,
+    JS_CONSOLE_DEBUG,
+    JS_CONSOLE_INFO,
+    JS_CONSOLE_WARN,
+    JS_CONSOLE_ERROR,
+    JS_CONSOLE_ASSERT,
+    JS_CONSOLE_TIME,
+    JS_CONSOLE_TIME_END,
+    JS_CONSOLE_TABLE,
+} JSConsoleMethodEnum;
+
+/* Host hooks used by the stdlib stubs. The context opaque pointer must be
+   NULL or point to a structure starting with JSHostHooks. A NULL hook makes
+   the stub return undefined. */
+typedef struct JSHostHooks {
+    JSHostCallback load;
+    JSHostMagicCallback console; /* print() and the console methods */
+    JSHostCallback date_now; /* Date.now() */
+    JSHostCallback performance_now; /* performance.now() */
+    JSHostCallback set_timeout; /* setTimeout() */
+    JSHostCallback set_interval; /* setInterval() */
+    JSHostCallback clear_timer; /* clearTimeout() and clearInterval() */
+    JSHostMagicCallback promise; /* Promise methods and queueMicrotask() */
+    JSHostParamsCallback promise_function; /* closures of JSHostFunctionEnum */
+    JSHostFinalizer promise_finalizer; /* opaque is the Promise's opaque */
+    JSHostMagicCallback collection; /* Map and Set constructors and methods */
+} JSHostHooks;
 
 #endif /* MQUICKJS_SYS_WRAPPER_H */
@@ -0,0 +1,250 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::mem::MaybeUninit;
+
+use crate::clock::Clock;
+use crate::console::ConsoleSink;
+use crate::func::{async_callback, HostFn};
+use crate::heap::HeapGrowth;
+use crate::quota::{MemoryQuota, QuotaThreshold};
+use crate::snapshot::HeapImage;
+use crate::state::{InterruptHandler, LogSink, ThresholdHandler};
+use crate::{Context, JsData, JsError, Value};
+
+const DEFAULT_MEMORY_BYTES: usize = 1024 * 1024;
+
+enum Memory {
+    Bytes(usize),
+    Growth(HeapGrowth),
+    Buffer(&'static mut [MaybeUninit<usize>]),
+    Snapshot(Box<HeapImage>),
+}
+
+/// Configures a [`Context`] and creates it in one step.
+///
+/// ```no_run
+/// use mquickjs_rs::{ContextBuilder, Value};
+///
+/// let ctx = ContextBuilder::new()
+///     .memory_bytes(256 * 1024)
+///     .random_seed(7)
+///     .disable_global("eval")
+///     .function("echo", |args: &[Value<'_>]| Ok(args[0]))
+///     .build()
+///     .expect("context should initialize");
+/// ```
+pub struct ContextBuilder {
+    memory: Memory,
+    quota: Option<MemoryQuota>,
+    on_threshold: Option<Box<ThresholdHandler>>,
+    random_seed: Option<u64>,
+    log: Option<Box<LogSink>>,
+    console: Option<Box<dyn ConsoleSink>>,
+    clock: Option<Box<dyn Clock>>,
+    interrupt: Option<Box<InterruptHandler>>,
+    disabled_globals: Vec<String>,
+    functions: Vec<(String, HostFn)>,
+}
+
+impl ContextBuilder {
+    /// Start from the defaults: a fixed 1 MiB heap and no hooks.
+    pub fn new() -> Self {
+        Self {
+            memory: Memory::Bytes(DEFAULT_MEMORY_BYTES),
+            quota: None,
+            on_threshold: None,
+            random_seed: None,
+            log: None,
+            console: None,
+            clock: None,
+            interrupt: None,
+            disabled_globals: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Use a fixed heap of `memory_bytes`, as [`Context::new`] does.
+    pub fn memory_bytes(mut self, memory_bytes: usize) -> Self {
+        self.memory = Memory::Bytes(memory_bytes);
+        self
+    }
+
+    /// Use a growable heap, as [`Context::with_growth`] does.
+    pub fn growth(mut self, growth: HeapGrowth) -> Self {
+        self.memory = Memory::Growth(growth);
+        self
+    }
+
+    /// Run in a caller-supplied buffer, as [`Context::with_buffer`] does.
+    pub fn buffer(mut self, buffer: &'static mut [MaybeUninit<usize>]) -> Self {
+        self.memory = Memory::Buffer(buffer);
+        self
+    }
+
+    /// Clone the context in `image`, as [`Context::from_snapshot`] does.
+    ///
+    /// The image already carries the memory quota, growth policy, random
+    /// seed and disabled globals of the original; settings made here are
+    /// applied on top. Callbacks such as the console sink and the clock are
+    /// not in the image and must be set here.
+    pub fn snapshot(mut self, image: &HeapImage) -> Self {
+        self.memory = Memory::Snapshot(Box::new(image.clone()));
+        self
+    }
+
+    /// Apply a memory quota, see [`Context::set_memory_quota`].
+    pub fn memory_quota(mut self, quota: MemoryQuota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Report quota thresholds, see [`Context::on_memory_threshold`].
+    pub fn on_memory_threshold<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(QuotaThreshold) + Send + 'static,
+    {
+        self.on_threshold = Some(Box::new(handler));
+        self
+    }
+
+    /// Seed `Math.random` so runs are reproducible.
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    /// Receive the engine's debug output, such as value dumps.
+    pub fn log_sink<F>(mut self, sink: F) -> Self
+    where
+        F: FnMut(&str) + Send + 'static,
+    {
+        self.log = Some(Box::new(sink));
+        self
+    }
+
+    /// Send the output of `print` and the `console` methods to `sink`.
+    pub fn console<S>(mut self, sink: S) -> Self
+    where
+        S: ConsoleSink + 'static,
+    {
+        self.console = Some(Box::new(sink));
+        self
+    }
+
+    /// Read time from `clock`, see [`Context::set_clock`].
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Poll `handler` while scripts run; returning true interrupts them with
+    /// an uncatchable error.
+    pub fn interrupt_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut() -> bool + Send + 'static,
+    {
+        self.interrupt = Some(Box::new(handler));
+        self
+    }
+
+    /// Delete global `name` (e.g. `"eval"`) from the context.
+    ///
+    /// Registered functions rely on the `load` global, so it should stay.
+    pub fn disable_global(mut self, name: &str) -> Self {
+        self.disabled_globals.push(name.to_string());
+        self
+    }
+
+    /// Register a host function, see [`Context::register_fn`].
+    pub fn function<F>(mut self, name: &str, func: F) -> Self
+    where
+        F: for<'ctx> Fn(&[Value<'ctx>]) -> Result<Value<'ctx>, JsError> + Send + Sync + 'static,
+    {
+        self.functions.push((name.to_string(), HostFn::Sync(Arc::new(func))));
+        self
+    }
+
+    /// Register an async host function, see [`Context::register_async_fn`].
+    pub fn async_function<F, Fut>(mut self, name: &str, func: F) -> Self
+    where
+        F: Fn(Vec<JsData>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<JsData, JsError>> + Send + 'static,
+    {
+        self.functions.push((name.to_string(), HostFn::Async(async_callback(func))));
+        self
+    }
+
+    /// Register a suspending host function, see
+    /// [`Context::register_suspend_fn`].
+    #[cfg(feature = "coroutine")]
+    pub fn suspend_function<F, Fut>(mut self, name: &str, func: F) -> Self
+    where
+        F: Fn(Vec<JsData>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<JsData, JsError>> + Send + 'static,
+    {
+        self.functions.push((name.to_string(), HostFn::Suspend(async_callback(func))));
+        self
+    }
+
+    /// Create the context and apply the configuration.
+    pub fn build(self) -> Result<Context, JsError> {
+        let ctx = match self.memory {
+            Memory::Bytes(memory_bytes) => Context::new(memory_bytes)?,
+            Memory::Growth(growth) => Context::with_growth(growth)?,
+            Memory::Buffer(buffer) => Context::with_buffer(buffer)?,
+            Memory::Snapshot(image) => Context::from_image(*image)?,
+        };
+        if let Some(seed) = self.random_seed {
+            ctx.set_random_seed(seed);
+        }
+        if let Some(log) = self.log {
+            ctx.set_log_sink(log);
+        }
+        if let Some(console) = self.console {
+            ctx.set_console_boxed(console);
+        }
+        if let Some(clock) = self.clock {
+            ctx.set_clock_boxed(clock);
+        }
+        if let Some(interrupt) = self.interrupt {
+            ctx.set_interrupt_handler(interrupt);
+        }
+        if let Some(handler) = self.on_threshold {
+            ctx.on_memory_threshold(handler);
+        }
+        for name in &self.disabled_globals {
+            ctx.disable_global(name)?;
+        }
+        for (name, callback) in self.functions {
+            ctx.register_callback(&name, callback)?;
+        }
+        // Applied last so that setting up the context does not count as
+        // crossing thresholds.
+        if let Some(quota) = self.quota {
+            ctx.set_memory_quota(quota);
+        }
+        Ok(ctx)
+    }
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for ContextBuilder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ContextBuilder")
+            .field("quota", &self.quota)
+            .field("random_seed", &self.random_seed)
+            .field("disabled_globals", &self.disabled_globals)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,271 @@
+//! Time sources for `Date.now`, `performance.now` and `console.time`.
+
+use alloc::sync::Arc;
+use core::ffi::c_int;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::Waker;
+
+use mquickjs_sys::{JSContext, JSValue, JS_NewFloat64, JS_NewInt64, JS_TAG_UNDEFINED};
+
+use crate::state::ContextState;
+
+/// Time source of a context, set with
+/// [`Context::set_clock`](crate::Context::set_clock).
+///
+/// With the `std` feature contexts start with a [`SystemClock`]; without it
+/// `Date.now()` and `performance.now()` return `undefined` until a clock is
+/// set.
+pub trait Clock: Send {
+    /// Milliseconds since the Unix epoch, returned by `Date.now()` without
+    /// the fraction.
+    fn now_ms(&self) -> f64;
+
+    /// Milliseconds on a monotonic clock, returned by `performance.now()`.
+    fn monotonic_ms(&self) -> f64;
+
+    /// Block until [`monotonic_ms`](Self::monotonic_ms) reaches
+    /// `deadline_ms`, for [`Context::run_until_idle`](crate::Context::run_until_idle).
+    ///
+    /// The default returns at once, so `run_until_idle` stops at the first
+    /// timer that is not due yet.
+    fn wait_until(&self, deadline_ms: f64) {
+        let _ = deadline_ms;
+    }
+
+    /// Wake `waker` once [`monotonic_ms`](Self::monotonic_ms) reaches
+    /// `deadline_ms`, for [`Context::poll_event_loop`](crate::Context::poll_event_loop)
+    /// while it only waits for a timer.
+    ///
+    /// The default does nothing, leaving the caller to wait for
+    /// [`Context::next_timer_deadline`](crate::Context::next_timer_deadline)
+    /// before polling again.
+    fn wake_at(&self, deadline_ms: f64, waker: &Waker) {
+        let _ = (deadline_ms, waker);
+    }
+}
+
+/// The host's clocks, with `performance.now()` counting from the creation
+/// of the clock.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// Create a clock whose monotonic time starts at zero now.
+    pub fn new() -> Self {
+        Self { origin: std::time::Instant::now() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now_ms(&self) -> f64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+    }
+
+    fn monotonic_ms(&self) -> f64 {
+        self.origin.elapsed().as_secs_f64() * 1000.0
+    }
+
+    fn wait_until(&self, deadline_ms: f64) {
+        let remaining = deadline_ms - self.monotonic_ms();
+        if remaining > 0.0 {
+            std::thread::sleep(std::time::Duration::from_secs_f64(remaining / 1000.0));
+        }
+    }
+
+    /// Queue the waker on the timer thread shared by every system clock.
+    fn wake_at(&self, deadline_ms: f64, waker: &Waker) {
+        let at = self.origin + std::time::Duration::from_secs_f64(deadline_ms.max(0.0) / 1000.0);
+        TimerThread::get().schedule(at, waker.clone());
+    }
+}
+
+/// A waker due at an instant, ordered by that instant.
+#[cfg(feature = "std")]
+struct Wakeup {
+    at: std::time::Instant,
+    waker: Waker,
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for Wakeup {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for Wakeup {}
+
+#[cfg(feature = "std")]
+impl PartialOrd for Wakeup {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Ord for Wakeup {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.at.cmp(&other.at)
+    }
+}
+
+/// The one thread that sleeps until the earliest queued deadline and wakes
+/// its waker, started on the first [`SystemClock::wake_at`].
+#[cfg(feature = "std")]
+struct TimerThread {
+    queue: std::sync::Mutex<std::collections::BinaryHeap<core::cmp::Reverse<Wakeup>>>,
+    changed: std::sync::Condvar,
+}
+
+#[cfg(feature = "std")]
+impl TimerThread {
+    fn get() -> &'static Self {
+        static TIMERS: std::sync::OnceLock<TimerThread> = std::sync::OnceLock::new();
+        static STARTED: std::sync::Once = std::sync::Once::new();
+        let timers = TIMERS.get_or_init(|| TimerThread {
+            queue: std::sync::Mutex::new(std::collections::BinaryHeap::new()),
+            changed: std::sync::Condvar::new(),
+        });
+        STARTED.call_once(|| {
+            std::thread::Builder::new()
+                .name("mquickjs-timers".into())
+                .spawn(|| timers.run())
+                .expect("failed to spawn the timer thread");
+        });
+        timers
+    }
+
+    fn schedule(&self, at: std::time::Instant, waker: Waker) {
+        let mut queue = self.queue.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let earliest = queue.peek().is_none_or(|next| at < next.0.at);
+        queue.push(core::cmp::Reverse(Wakeup { at, waker }));
+        if earliest {
+            self.changed.notify_one();
+        }
+    }
+
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        loop {
+            let now = std::time::Instant::now();
+            queue = match queue.peek().map(|next| next.0.at) {
+                None => self.changed.wait(queue).unwrap_or_else(std::sync::PoisonError::into_inner),
+                Some(at) if at <= now => {
+                    let due = queue.pop().map(|due| due.0.waker);
+                    // Wakers may run arbitrary code, so wake outside the lock.
+                    drop(queue);
+                    due.into_iter().for_each(Waker::wake);
+                    self.queue.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+                }
+                Some(at) => {
+                    let (queue, _) = self
+                        .changed
+                        .wait_timeout(queue, at - now)
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
+                    queue
+                }
+            };
+        }
+    }
+}
+
+/// A clock that moves only when told to, for tests and deterministic
+/// replays.
+///
+/// Besides [`advance`](Self::advance) and [`set_now`](Self::set_now), the
+/// event loop moves it: where a [`SystemClock`] would wait for the next
+/// timer, this clock jumps straight to its deadline.
+///
+/// Clones share the same time, so keep one clone and pass another to the
+/// context.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now_bits: Arc<AtomicU64>,
+    monotonic_bits: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    /// Create a clock reading `now_ms` since the Unix epoch, with its
+    /// monotonic time at zero.
+    pub fn new(now_ms: f64) -> Self {
+        let clock = Self::default();
+        clock.set_now(now_ms);
+        clock
+    }
+
+    /// Move both the wall and the monotonic time forward by `ms`.
+    pub fn advance(&self, ms: f64) {
+        self.set_now(self.now_ms() + ms);
+        self.monotonic_bits.store((self.monotonic_ms() + ms).to_bits(), Ordering::SeqCst);
+    }
+
+    /// Set the wall time, leaving the monotonic time unchanged.
+    pub fn set_now(&self, now_ms: f64) {
+        self.now_bits.store(now_ms.to_bits(), Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> f64 {
+        f64::from_bits(self.now_bits.load(Ordering::SeqCst))
+    }
+
+    fn monotonic_ms(&self) -> f64 {
+        f64::from_bits(self.monotonic_bits.load(Ordering::SeqCst))
+    }
+
+    /// Jump forward to `deadline_ms` instead of waiting.
+    fn wait_until(&self, deadline_ms: f64) {
+        let remaining = deadline_ms - self.monotonic_ms();
+        if remaining > 0.0 {
+            self.advance(remaining);
+        }
+    }
+
+    /// Jump forward to `deadline_ms` and wake `waker` at once.
+    fn wake_at(&self, deadline_ms: f64, waker: &Waker) {
+        self.wait_until(deadline_ms);
+        waker.wake_by_ref();
+    }
+}
+
+/// `Date.now()`: the clock's wall time in whole milliseconds.
+pub(crate) unsafe extern "C" fn date_now_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    _argc: c_int,
+    _argv: *mut JSValue,
+) -> JSValue {
+    match unsafe { ContextState::from_raw(ctx) }.and_then(ContextState::now_ms) {
+        Some(now) => unsafe { JS_NewInt64(ctx, now as i64) },
+        None => JS_TAG_UNDEFINED as JSValue,
+    }
+}
+
+/// `performance.now()`: the clock's monotonic time in milliseconds.
+pub(crate) unsafe extern "C" fn performance_now_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    _argc: c_int,
+    _argv: *mut JSValue,
+) -> JSValue {
+    match unsafe { ContextState::from_raw(ctx) }.and_then(ContextState::monotonic_ms) {
+        Some(now) => unsafe { JS_NewFloat64(ctx, now) },
+        None => JS_TAG_UNDEFINED as JSValue,
+    }
+}
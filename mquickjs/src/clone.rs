@@ -0,0 +1,443 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+use mquickjs_sys::{
+    JSContext, JSValue, JS_GetArrayBuffer, JS_GetClassID, JS_GetGCCount, JS_GetPropertyStr,
+    JS_GetPropertyUint32, JS_GetTypedArrayBuffer, JS_NewArray, JS_NewArrayBufferCopy,
+    JS_NewObject, JS_NewTypedArray, JS_SetPropertyStr, JS_SetPropertyUint32, JS_TAG_UNDEFINED,
+};
+
+use crate::convert::{array_length, is_exception, js_null_value, object_keys};
+use crate::rooted::RootedValue;
+use crate::{Coerced, Context, FromValue, Function, IntoValue, JsError, PathSegment, Value, ValueKind};
+
+const ERROR_NAMES: [&str; 8] = [
+    "Error",
+    "EvalError",
+    "RangeError",
+    "ReferenceError",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+    "InternalError",
+];
+
+const FACTORY_SOURCE: &str = "(function (name, a, b) {\n  if (name === 'RegExp') return new RegExp(a, b);\n  return new globalThis[name](a);\n})";
+
+/// Context-independent copy of a value made with the structured clone algorithm.
+///
+/// Unlike `JsData`, shared references and cycles are preserved, and errors,
+/// regular expressions, array buffers and typed arrays keep their type.
+/// Functions and symbols cannot be cloned, and neither can dates since the
+/// engine cannot construct them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClonedValue {
+    root: Slot,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Slot {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Node(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Object(Vec<(String, Slot)>),
+    Array(Vec<Slot>),
+    Error {
+        name: String,
+        message: String,
+    },
+    RegExp {
+        source: String,
+        flags: String,
+    },
+    ArrayBuffer(Vec<u8>),
+    TypedArray {
+        class_id: i32,
+        buffer: usize,
+        byte_offset: usize,
+        length: usize,
+    },
+}
+
+impl ClonedValue {
+    /// Serialize `value` and everything reachable from it.
+    pub fn from_value(value: Value<'_>) -> Result<Self, JsError> {
+        let mut serializer = Serializer {
+            ctx: value.ctx(),
+            roots: Vec::new(),
+            ids: BTreeMap::new(),
+            gc_count: 0,
+            nodes: Vec::new(),
+            pending: VecDeque::new(),
+        };
+        let root = serializer.slot(value, &[])?;
+        while let Some((id, path)) = serializer.pending.pop_front() {
+            let node = serializer.read_node(id, &path).map_err(|err| at_path(err, &path))?;
+            serializer.nodes[id] = node;
+        }
+        Ok(Self {
+            root,
+            nodes: serializer.nodes,
+        })
+    }
+
+    /// Recreate the value graph in `ctx`.
+    pub fn to_value<'ctx>(&self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        let raw_ctx = ctx.raw_ctx();
+        let mut created: Vec<Option<RootedValue<'ctx>>> = Vec::with_capacity(self.nodes.len());
+        let mut factory = None;
+
+        for node in &self.nodes {
+            let value = match node {
+                Node::Object(_) => {
+                    let raw = unsafe { JS_NewObject(raw_ctx.as_ptr()) };
+                    if is_exception(raw) {
+                        return Err(JsError::conversion("failed to create object"));
+                    }
+                    Some(Value::new(raw_ctx, raw))
+                }
+                Node::Array(items) => {
+                    let raw = unsafe { JS_NewArray(raw_ctx.as_ptr(), items.len() as i32) };
+                    if is_exception(raw) {
+                        return Err(JsError::conversion("failed to create array"));
+                    }
+                    Some(Value::new(raw_ctx, raw))
+                }
+                Node::Error { name, message } => {
+                    // Only error constructors, so no name can produce another type.
+                    let name = ERROR_NAMES.into_iter().find(|known| known == name).unwrap_or("Error");
+                    Some(construct(ctx, &mut factory, name, message, "")?)
+                }
+                Node::RegExp { source, flags } => {
+                    Some(construct(ctx, &mut factory, "RegExp", source, flags)?)
+                }
+                Node::ArrayBuffer(bytes) => {
+                    let raw = unsafe {
+                        JS_NewArrayBufferCopy(raw_ctx.as_ptr(), bytes.as_ptr(), bytes.len())
+                    };
+                    if is_exception(raw) {
+                        return Err(JsError::conversion("failed to create ArrayBuffer"));
+                    }
+                    Some(Value::new(raw_ctx, raw))
+                }
+                // Created once every buffer exists.
+                Node::TypedArray { .. } => None,
+            };
+            created.push(value.map(|value| ctx.root(value)));
+        }
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            if let Node::TypedArray {
+                class_id,
+                buffer,
+                byte_offset,
+                length,
+            } = node
+            {
+                let buffer = node_value(&created, *buffer)?;
+                let raw = unsafe {
+                    JS_NewTypedArray(raw_ctx.as_ptr(), buffer.raw(), *class_id, *byte_offset, *length)
+                };
+                if is_exception(raw) {
+                    return Err(JsError::conversion("failed to create typed array"));
+                }
+                created[id] = Some(ctx.root(Value::new(raw_ctx, raw)));
+            }
+        }
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            match node {
+                Node::Object(entries) => {
+                    for (key, slot) in entries {
+                        let name = CString::new(key.as_str())
+                            .map_err(|_| JsError::conversion("property name contains null byte"))?;
+                        let value = slot_value(ctx, &created, slot)?;
+                        let result = unsafe {
+                            JS_SetPropertyStr(
+                                raw_ctx.as_ptr(),
+                                node_value(&created, id)?.raw(),
+                                name.as_ptr(),
+                                value.raw(),
+                            )
+                        };
+                        if is_exception(result) {
+                            return Err(JsError::conversion(format!(
+                                "failed to set property '{key}'"
+                            )));
+                        }
+                    }
+                }
+                Node::Array(items) => {
+                    for (index, slot) in items.iter().enumerate() {
+                        let value = slot_value(ctx, &created, slot)?;
+                        let result = unsafe {
+                            JS_SetPropertyUint32(
+                                raw_ctx.as_ptr(),
+                                node_value(&created, id)?.raw(),
+                                index as u32,
+                                value.raw(),
+                            )
+                        };
+                        if is_exception(result) {
+                            return Err(JsError::conversion(format!(
+                                "failed to set array element {index}"
+                            )));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        slot_value(ctx, &created, &self.root)
+    }
+}
+
+impl<'ctx> FromValue<'ctx> for ClonedValue {
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        ClonedValue::from_value(value)
+    }
+}
+
+impl<'ctx> IntoValue<'ctx> for ClonedValue {
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        self.to_value(ctx)
+    }
+}
+
+impl<'ctx> IntoValue<'ctx> for &ClonedValue {
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        self.to_value(ctx)
+    }
+}
+
+struct Serializer<'ctx> {
+    ctx: NonNull<JSContext>,
+    // Rooted source objects indexed by node id.
+    roots: Vec<RootedValue<'ctx>>,
+    // Raw value to node id; rebuilt from `roots` after a GC moves objects.
+    ids: BTreeMap<JSValue, usize>,
+    gc_count: u32,
+    nodes: Vec<Node>,
+    pending: VecDeque<(usize, Vec<PathSegment>)>,
+}
+
+impl<'ctx> Serializer<'ctx> {
+    fn slot(&mut self, value: Value<'ctx>, path: &[PathSegment]) -> Result<Slot, JsError> {
+        let kind = value.kind();
+        match kind {
+            ValueKind::Undefined => Ok(Slot::Undefined),
+            ValueKind::Null => Ok(Slot::Null),
+            ValueKind::Bool => Ok(Slot::Bool(value.to_bool()?)),
+            ValueKind::Int | ValueKind::Float => Ok(Slot::Number(value.to_f64()?)),
+            ValueKind::String => Ok(Slot::String(value.to_string()?)),
+            ValueKind::Function | ValueKind::Date | ValueKind::Symbol | ValueKind::Internal => {
+                Err(JsError::type_mismatch(format!("cannot clone {}", kind.as_str()), kind.as_str()))
+            }
+            _ => Ok(Slot::Node(self.node_id(value, path))),
+        }
+    }
+
+    fn node_id(&mut self, value: Value<'ctx>, path: &[PathSegment]) -> usize {
+        let gc_count = unsafe { JS_GetGCCount(self.ctx.as_ptr()) };
+        if gc_count != self.gc_count {
+            self.gc_count = gc_count;
+            self.ids = self
+                .roots
+                .iter()
+                .enumerate()
+                .map(|(id, root)| (root.to_value().raw(), id))
+                .collect();
+        }
+        if let Some(id) = self.ids.get(&value.raw()) {
+            return *id;
+        }
+
+        let id = self.roots.len();
+        self.roots.push(RootedValue::new(self.ctx, value));
+        self.ids.insert(value.raw(), id);
+        self.nodes.push(Node::Object(Vec::new()));
+        self.pending.push_back((id, path.to_vec()));
+        id
+    }
+
+    fn current(&self, id: usize) -> JSValue {
+        self.roots[id].to_value().raw()
+    }
+
+    fn read_node(&mut self, id: usize, path: &[PathSegment]) -> Result<Node, JsError> {
+        let value = Value::new(self.ctx, self.current(id));
+        match value.kind() {
+            ValueKind::Array => {
+                let length = array_length(self.ctx, value.raw())?;
+                let mut items = Vec::with_capacity(length as usize);
+                for index in 0..length {
+                    let raw =
+                        unsafe { JS_GetPropertyUint32(self.ctx.as_ptr(), self.current(id), index) };
+                    if is_exception(raw) {
+                        return Err(JsError::conversion(format!(
+                            "failed to read array element {index}"
+                        )));
+                    }
+                    let mut child = path.to_vec();
+                    child.push(PathSegment::Index(index as usize));
+                    let slot = self
+                        .slot(Value::new(self.ctx, raw), &child)
+                        .map_err(|err| err.at_index(index as usize))?;
+                    items.push(slot);
+                }
+                Ok(Node::Array(items))
+            }
+            ValueKind::Error => Ok(Node::Error {
+                name: self.coerced_property(id, "name", "Error")?,
+                message: self.coerced_property(id, "message", "")?,
+            }),
+            ValueKind::RegExp => Ok(Node::RegExp {
+                source: self.string_property(id, "source")?,
+                flags: self.string_property(id, "flags")?,
+            }),
+            ValueKind::ArrayBuffer => {
+                let mut size = 0usize;
+                let ptr = unsafe { JS_GetArrayBuffer(self.ctx.as_ptr(), &mut size, value.raw()) };
+                if ptr.is_null() {
+                    return Err(JsError::conversion("failed to read ArrayBuffer"));
+                }
+                Ok(Node::ArrayBuffer(unsafe { core::slice::from_raw_parts(ptr, size) }.to_vec()))
+            }
+            kind if kind.is_typed_array() => {
+                let class_id = unsafe { JS_GetClassID(self.ctx.as_ptr(), value.raw()) };
+                let mut byte_offset = 0usize;
+                let mut byte_length = 0usize;
+                let mut element_size = 0usize;
+                let buffer = unsafe {
+                    JS_GetTypedArrayBuffer(
+                        self.ctx.as_ptr(),
+                        value.raw(),
+                        &mut byte_offset,
+                        &mut byte_length,
+                        &mut element_size,
+                    )
+                };
+                if is_exception(buffer) {
+                    return Err(JsError::conversion("failed to read typed array"));
+                }
+                let buffer = self.node_id(Value::new(self.ctx, buffer), path);
+                Ok(Node::TypedArray {
+                    class_id,
+                    buffer,
+                    byte_offset,
+                    length: byte_length / element_size,
+                })
+            }
+            _ => {
+                let keys = object_keys(self.ctx, value)?;
+                let mut entries = Vec::with_capacity(keys.len());
+                for key in keys {
+                    let raw = self.property(id, &key)?;
+                    let mut child = path.to_vec();
+                    child.push(PathSegment::Key(key.clone()));
+                    let slot = self
+                        .slot(Value::new(self.ctx, raw), &child)
+                        .map_err(|err| err.at_key(key.as_str()))?;
+                    entries.push((key, slot));
+                }
+                Ok(Node::Object(entries))
+            }
+        }
+    }
+
+    fn property(&self, id: usize, name: &str) -> Result<JSValue, JsError> {
+        let key = CString::new(name)
+            .map_err(|_| JsError::conversion("property name contains null byte"))?;
+        let raw = unsafe { JS_GetPropertyStr(self.ctx.as_ptr(), self.current(id), key.as_ptr()) };
+        if is_exception(raw) {
+            return Err(JsError::conversion(format!("failed to get property '{name}'")));
+        }
+        Ok(raw)
+    }
+
+    fn string_property(&self, id: usize, name: &str) -> Result<String, JsError> {
+        Value::new(self.ctx, self.property(id, name)?).to_string()
+    }
+
+    /// Read a property like `String(...)` would for primitives, using
+    /// `fallback` for `undefined`, objects and symbols, whose conversion could
+    /// run script code or throw.
+    fn coerced_property(&self, id: usize, name: &str, fallback: &str) -> Result<String, JsError> {
+        let value = Value::new(self.ctx, self.property(id, name)?);
+        match value.kind() {
+            ValueKind::String | ValueKind::Int | ValueKind::Float | ValueKind::Bool | ValueKind::Null => {
+                Coerced::<String>::from_value(value).map(|Coerced(string)| string)
+            }
+            _ => Ok(fallback.to_string()),
+        }
+    }
+}
+
+fn at_path(err: JsError, path: &[PathSegment]) -> JsError {
+    path.iter().rev().fold(err, |err, segment| match segment {
+        PathSegment::Index(index) => err.at_index(*index),
+        PathSegment::Key(key) => err.at_key(key.as_str()),
+    })
+}
+
+fn node_value<'ctx>(
+    created: &[Option<RootedValue<'ctx>>],
+    id: usize,
+) -> Result<Value<'ctx>, JsError> {
+    created
+        .get(id)
+        .and_then(Option::as_ref)
+        .map(RootedValue::to_value)
+        .ok_or_else(|| JsError::conversion("cloned value refers to a missing node"))
+}
+
+fn slot_value<'ctx>(
+    ctx: &'ctx Context,
+    created: &[Option<RootedValue<'ctx>>],
+    slot: &Slot,
+) -> Result<Value<'ctx>, JsError> {
+    match slot {
+        Slot::Undefined => Ok(Value::new(ctx.raw_ctx(), JS_TAG_UNDEFINED as JSValue)),
+        Slot::Null => Ok(Value::new(ctx.raw_ctx(), js_null_value())),
+        Slot::Bool(value) => value.into_value(ctx),
+        Slot::Number(value) => value.into_value(ctx),
+        Slot::String(value) => value.as_str().into_value(ctx),
+        Slot::Node(id) => node_value(created, *id),
+    }
+}
+
+fn construct<'ctx>(
+    ctx: &'ctx Context,
+    factory: &mut Option<RootedValue<'ctx>>,
+    name: &str,
+    first: &str,
+    second: &str,
+) -> Result<Value<'ctx>, JsError> {
+    if factory.is_none() {
+        *factory = Some(ctx.root(ctx.eval(FACTORY_SOURCE, "<clone>")?));
+    }
+    let args = [
+        ctx.root(name.into_value(ctx)?),
+        ctx.root(first.into_value(ctx)?),
+        ctx.root(second.into_value(ctx)?),
+    ];
+    let function = factory
+        .as_ref()
+        .map(RootedValue::to_value)
+        .expect("factory was just created");
+    let function = Function::from_value(ctx, function)?;
+    function.call(&args.each_ref().map(RootedValue::to_value))
+}
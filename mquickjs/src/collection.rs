@@ -0,0 +1,659 @@
+//! The `Map` and `Set` classes, and their conversions from and into Rust
+//! maps and sets.
+//!
+//! Collections are instances of user classes whose user value is an array of
+//! their entries in insertion order: `[key, value, key, value, ...]` for a
+//! `Map` and `[value, ...]` for a `Set`. The GC traces that array, so a
+//! collection holding itself is still collected. Keys are compared with
+//! SameValueZero by a linear scan, so `get`, `set`, `has` and `delete` take
+//! time linear in the size of the collection, and filling one with n
+//! entries, from a script or from a Rust map, takes O(n²).
+//!
+//! `BTreeMap` and [`AsMap`] convert to and from a `Map`, `BTreeSet` and
+//! `HashSet` to and from a `Set`. A bare `HashMap<String, T>` converts into a
+//! plain object.
+//!
+//! The opaque pointer is an id naming the collection to the `forEach` loops
+//! in progress, whose positions deletions adjust.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::vec::Vec;
+use core::ffi::{c_int, c_void};
+use core::ptr::NonNull;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::Hash;
+
+use mquickjs_sys::{
+    JS_GetClassID, JS_GetOpaque, JS_GetPropertyUint32, JS_GetUserValue, JS_IsNumber, JS_NewArray,
+    JS_NewInt32, JS_NewObjectClassUser, JS_NewUint32, JS_SameValueZero, JS_SetOpaque,
+    JS_SetPropertyStr, JS_SetUserValue, JS_ToNumber, JSCollectionMethodEnum,
+    JSCollectionMethodEnum_JS_COLLECTION_ADD, JSCollectionMethodEnum_JS_COLLECTION_CLEAR,
+    JSCollectionMethodEnum_JS_COLLECTION_DELETE, JSCollectionMethodEnum_JS_COLLECTION_ENTRIES,
+    JSCollectionMethodEnum_JS_COLLECTION_FOR_EACH, JSCollectionMethodEnum_JS_COLLECTION_GET,
+    JSCollectionMethodEnum_JS_COLLECTION_HAS, JSCollectionMethodEnum_JS_COLLECTION_KEYS,
+    JSCollectionMethodEnum_JS_COLLECTION_SET, JSCollectionMethodEnum_JS_COLLECTION_SIZE,
+    JSCollectionMethodEnum_JS_COLLECTION_VALUES, JSCollectionMethodEnum_JS_MAP_CONSTRUCTOR,
+    JSCollectionMethodEnum_JS_SET_CONSTRUCTOR, JSContext, JSHostClassEnum_JS_CLASS_MAP,
+    JSHostClassEnum_JS_CLASS_SET, JSValue,
+};
+
+use crate::context::exception_error;
+use crate::convert::{boolean, exception, is_exception, js_null_value, undefined};
+use crate::rooted::{
+    Local, arguments, array_len, call, checked, is_function, local, new_array, set_element,
+    throw_type_error,
+};
+use crate::state::{ContextState, context_state};
+use crate::{Context, FromValue, IntoValue, JsError, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Map,
+    Set,
+}
+
+impl Kind {
+    /// Array elements per entry.
+    fn width(self) -> u32 {
+        match self {
+            Kind::Map => 2,
+            Kind::Set => 1,
+        }
+    }
+
+    fn class_id(self) -> c_int {
+        match self {
+            Kind::Map => JSHostClassEnum_JS_CLASS_MAP as c_int,
+            Kind::Set => JSHostClassEnum_JS_CLASS_SET as c_int,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Map => "Map",
+            Kind::Set => "Set",
+        }
+    }
+}
+
+/// Collection ids and the `forEach` loops in progress.
+#[derive(Default)]
+pub(crate) struct Cursors {
+    next_id: usize,
+    /// Collection id and index of the next entry to visit, innermost loop
+    /// last.
+    loops: Vec<(usize, u32)>,
+}
+
+impl Cursors {
+    fn insert(&mut self) -> usize {
+        self.next_id = self.next_id.wrapping_add(1);
+        self.next_id
+    }
+
+    /// Keep the loops over collection `id` on the entry they would visit
+    /// next once entry `index` is removed.
+    fn removed(&mut self, id: usize, index: u32) {
+        for (loop_id, next) in &mut self.loops {
+            if *loop_id == id && *next > index {
+                *next -= 1;
+            }
+        }
+    }
+
+    fn cleared(&mut self, id: usize) {
+        for (loop_id, next) in &mut self.loops {
+            if *loop_id == id {
+                *next = 0;
+            }
+        }
+    }
+}
+
+/// `new Map`, `new Set` and the methods and `size` getter of both.
+#[allow(non_upper_case_globals)]
+pub(crate) unsafe extern "C" fn collection_callback(
+    ctx: *mut JSContext,
+    this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+    magic: c_int,
+) -> JSValue {
+    let Some(ctx) = NonNull::new(ctx) else {
+        return undefined();
+    };
+    let Some(state) = (unsafe { ContextState::from_raw(ctx.as_ptr()) }) else {
+        return undefined();
+    };
+    // Root everything first: any allocation below may move the values.
+    let this = local(
+        ctx,
+        if this_val.is_null() {
+            undefined()
+        } else {
+            unsafe { *this_val }
+        },
+    );
+    let args = unsafe { arguments(ctx, argc, argv, 2) };
+    let method = magic as JSCollectionMethodEnum;
+    let result = unsafe {
+        match method {
+            JSCollectionMethodEnum_JS_MAP_CONSTRUCTOR => construct(ctx, state, Kind::Map, &args[0]),
+            JSCollectionMethodEnum_JS_SET_CONSTRUCTOR => construct(ctx, state, Kind::Set, &args[0]),
+            JSCollectionMethodEnum_JS_COLLECTION_GET => {
+                receiver(ctx, &this, Some(Kind::Map)).map(|kind| {
+                    let entries = entries(ctx, &this);
+                    match find(ctx, kind, &entries, &args[0]) {
+                        Some(index) => entry(ctx, kind, &entries, index).1,
+                        None => local(ctx, undefined()),
+                    }
+                })
+            }
+            JSCollectionMethodEnum_JS_COLLECTION_SET => receiver(ctx, &this, Some(Kind::Map))
+                .and_then(|kind| insert(ctx, kind, &this, &args[0], Some(&args[1])))
+                .map(|()| this),
+            JSCollectionMethodEnum_JS_COLLECTION_ADD => receiver(ctx, &this, Some(Kind::Set))
+                .and_then(|kind| insert(ctx, kind, &this, &args[0], None))
+                .map(|()| this),
+            JSCollectionMethodEnum_JS_COLLECTION_HAS => receiver(ctx, &this, None).map(|kind| {
+                let found = find(ctx, kind, &entries(ctx, &this), &args[0]).is_some();
+                local(ctx, boolean(found))
+            }),
+            JSCollectionMethodEnum_JS_COLLECTION_DELETE => receiver(ctx, &this, None)
+                .and_then(|kind| remove(ctx, state, kind, &this, &args[0]))
+                .map(|removed| local(ctx, boolean(removed))),
+            JSCollectionMethodEnum_JS_COLLECTION_CLEAR => receiver(ctx, &this, None)
+                .and_then(|_| clear(ctx, state, &this))
+                .map(|()| local(ctx, undefined())),
+            JSCollectionMethodEnum_JS_COLLECTION_FOR_EACH => receiver(ctx, &this, None)
+                .and_then(|kind| for_each(ctx, state, kind, &this, &args[0], &args[1])),
+            JSCollectionMethodEnum_JS_COLLECTION_KEYS
+            | JSCollectionMethodEnum_JS_COLLECTION_VALUES
+            | JSCollectionMethodEnum_JS_COLLECTION_ENTRIES => {
+                receiver(ctx, &this, None).and_then(|kind| list(ctx, kind, &this, method))
+            }
+            JSCollectionMethodEnum_JS_COLLECTION_SIZE => receiver(ctx, &this, None).map(|kind| {
+                let size = size(ctx, kind, &entries(ctx, &this));
+                local(ctx, JS_NewUint32(ctx.as_ptr(), size))
+            }),
+            _ => Ok(local(ctx, undefined())),
+        }
+    };
+    match result {
+        Ok(value) => value.raw(),
+        Err(()) => exception(),
+    }
+}
+
+/// `new Map(entries)` or `new Set(values)`, filled from an array or another
+/// collection. A `Map` takes `[key, value]` entry objects.
+unsafe fn construct(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    kind: Kind,
+    items: &Local,
+) -> Result<Local, ()> {
+    let collection = unsafe { new_collection(ctx, state, kind) }?;
+    if items.raw() == undefined() || items.raw() == js_null_value() {
+        return Ok(collection);
+    }
+    if let Some(source) = kind_of(ctx, items.raw()) {
+        let from = entries(ctx, items);
+        for index in 0..size(ctx, source, &from) {
+            let (key, value) = entry(ctx, source, &from, index);
+            match (kind, source) {
+                (Kind::Map, Kind::Map) => insert(ctx, kind, &collection, &key, Some(&value))?,
+                (Kind::Map, Kind::Set) => insert_entry(ctx, &collection, &key)?,
+                (Kind::Set, Kind::Map) => {
+                    let pair = new_array(ctx, &[&key, &value])?;
+                    insert(ctx, kind, &collection, &pair, None)?
+                }
+                (Kind::Set, Kind::Set) => insert(ctx, kind, &collection, &key, None)?,
+            }
+        }
+        return Ok(collection);
+    }
+    let Some(length) = array_len(ctx, items) else {
+        return throw_type_error(ctx, c"value is not iterable");
+    };
+    for index in 0..length {
+        let item = checked(ctx, unsafe {
+            JS_GetPropertyUint32(ctx.as_ptr(), items.raw(), index)
+        })?;
+        match kind {
+            Kind::Map => insert_entry(ctx, &collection, &item)?,
+            Kind::Set => insert(ctx, kind, &collection, &item, None)?,
+        }
+    }
+    Ok(collection)
+}
+
+/// Create an empty collection.
+unsafe fn new_collection(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    kind: Kind,
+) -> Result<Local, ()> {
+    let entries = checked(ctx, unsafe { JS_NewArray(ctx.as_ptr(), 0) })?;
+    let collection = checked(ctx, unsafe {
+        JS_NewObjectClassUser(ctx.as_ptr(), kind.class_id())
+    })?;
+    let id = state.cursors.borrow_mut().insert();
+    unsafe {
+        JS_SetOpaque(ctx.as_ptr(), collection.raw(), id as *mut c_void);
+        JS_SetUserValue(ctx.as_ptr(), collection.raw(), entries.raw());
+    }
+    Ok(collection)
+}
+
+fn kind_of(ctx: NonNull<JSContext>, value: JSValue) -> Option<Kind> {
+    let class_id = unsafe { JS_GetClassID(ctx.as_ptr(), value) };
+    [Kind::Map, Kind::Set]
+        .into_iter()
+        .find(|kind| kind.class_id() == class_id)
+}
+
+/// Kind of the collection `this`, which must be a `kind` if one is given.
+fn receiver(ctx: NonNull<JSContext>, this: &Local, kind: Option<Kind>) -> Result<Kind, ()> {
+    match (kind_of(ctx, this.raw()), kind) {
+        (Some(found), None) => Ok(found),
+        (Some(found), Some(kind)) if found == kind => Ok(found),
+        (_, Some(Kind::Map)) => throw_type_error(ctx, c"not a Map"),
+        (_, Some(Kind::Set)) => throw_type_error(ctx, c"not a Set"),
+        (None, None) => throw_type_error(ctx, c"not a Map or Set"),
+    }
+}
+
+fn collection_id(ctx: NonNull<JSContext>, collection: &Local) -> usize {
+    unsafe { JS_GetOpaque(ctx.as_ptr(), collection.raw()) as usize }
+}
+
+/// The entry array of `collection`.
+fn entries(ctx: NonNull<JSContext>, collection: &Local) -> Local {
+    local(ctx, unsafe {
+        JS_GetUserValue(ctx.as_ptr(), collection.raw())
+    })
+}
+
+/// Number of entries in the entry array.
+fn size(ctx: NonNull<JSContext>, kind: Kind, entries: &Local) -> u32 {
+    array_len(ctx, entries).unwrap_or(0) / kind.width()
+}
+
+fn element(ctx: NonNull<JSContext>, entries: &Local, position: u32) -> JSValue {
+    unsafe { JS_GetPropertyUint32(ctx.as_ptr(), entries.raw(), position) }
+}
+
+/// Key and value of entry `index`; the value of a `Set` entry is its key.
+fn entry(ctx: NonNull<JSContext>, kind: Kind, entries: &Local, index: u32) -> (Local, Local) {
+    let position = index * kind.width();
+    let key = element(ctx, entries, position);
+    let value = match kind {
+        Kind::Map => element(ctx, entries, position + 1),
+        Kind::Set => key,
+    };
+    (local(ctx, key), local(ctx, value))
+}
+
+/// Index of the entry whose key is SameValueZero to `key`.
+fn find(ctx: NonNull<JSContext>, kind: Kind, entries: &Local, key: &Local) -> Option<u32> {
+    (0..size(ctx, kind, entries)).find(|index| unsafe {
+        JS_SameValueZero(
+            ctx.as_ptr(),
+            key.raw(),
+            element(ctx, entries, index * kind.width()),
+        ) != 0
+    })
+}
+
+/// Add an entry for `key`, or replace the value of the one it has. `value`
+/// is `None` for a `Set`.
+fn insert(
+    ctx: NonNull<JSContext>,
+    kind: Kind,
+    collection: &Local,
+    key: &Local,
+    value: Option<&Local>,
+) -> Result<(), ()> {
+    let entries = entries(ctx, collection);
+    let (position, key) = match find(ctx, kind, &entries, key) {
+        Some(index) => (index * kind.width(), None),
+        None => (size(ctx, kind, &entries) * kind.width(), Some(key)),
+    };
+    if let Some(key) = key {
+        // Keys are stored with -0 replaced by +0.
+        let mut number = 0f64;
+        let raw = if unsafe { JS_IsNumber(ctx.as_ptr(), key.raw()) } != 0
+            && unsafe { JS_ToNumber(ctx.as_ptr(), &mut number, key.raw()) } == 0
+            && number == 0.0
+        {
+            unsafe { JS_NewInt32(ctx.as_ptr(), 0) }
+        } else {
+            key.raw()
+        };
+        set_element(ctx, &entries, position, raw)?;
+    }
+    match value {
+        Some(value) => set_element(ctx, &entries, position + 1, value.raw()),
+        None => Ok(()),
+    }
+}
+
+/// Insert an `[key, value]` entry object into a map.
+fn insert_entry(ctx: NonNull<JSContext>, map: &Local, item: &Local) -> Result<(), ()> {
+    if unsafe { JS_GetClassID(ctx.as_ptr(), item.raw()) } < 0 {
+        return throw_type_error(ctx, c"iterator value is not an entry object");
+    }
+    let key = checked(ctx, unsafe {
+        JS_GetPropertyUint32(ctx.as_ptr(), item.raw(), 0)
+    })?;
+    let value = checked(ctx, unsafe {
+        JS_GetPropertyUint32(ctx.as_ptr(), item.raw(), 1)
+    })?;
+    insert(ctx, Kind::Map, map, &key, Some(&value))
+}
+
+/// Remove the entry for `key`, shifting the later ones down.
+fn remove(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    kind: Kind,
+    collection: &Local,
+    key: &Local,
+) -> Result<bool, ()> {
+    let entries = entries(ctx, collection);
+    let Some(index) = find(ctx, kind, &entries, key) else {
+        return Ok(false);
+    };
+    let width = kind.width();
+    let length = size(ctx, kind, &entries) * width;
+    for position in index * width..length - width {
+        set_element(
+            ctx,
+            &entries,
+            position,
+            element(ctx, &entries, position + width),
+        )?;
+    }
+    set_length(ctx, &entries, length - width)?;
+    state
+        .cursors
+        .borrow_mut()
+        .removed(collection_id(ctx, collection), index);
+    Ok(true)
+}
+
+fn clear(ctx: NonNull<JSContext>, state: &ContextState, collection: &Local) -> Result<(), ()> {
+    set_length(ctx, &entries(ctx, collection), 0)?;
+    state
+        .cursors
+        .borrow_mut()
+        .cleared(collection_id(ctx, collection));
+    Ok(())
+}
+
+fn set_length(ctx: NonNull<JSContext>, entries: &Local, length: u32) -> Result<(), ()> {
+    let result = unsafe {
+        JS_SetPropertyStr(
+            ctx.as_ptr(),
+            entries.raw(),
+            c"length".as_ptr(),
+            JS_NewUint32(ctx.as_ptr(), length),
+        )
+    };
+    if is_exception(result) {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// `forEach(callback, this_arg)`: call `callback(value, key, collection)`
+/// for each entry, including the ones added during the loop.
+unsafe fn for_each(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    kind: Kind,
+    collection: &Local,
+    callback: &Local,
+    this_arg: &Local,
+) -> Result<Local, ()> {
+    if !is_function(ctx, callback.raw()) {
+        return throw_type_error(ctx, c"forEach callback is not a function");
+    }
+    let depth = {
+        let mut cursors = state.cursors.borrow_mut();
+        cursors.loops.push((collection_id(ctx, collection), 0));
+        cursors.loops.len() - 1
+    };
+    let result = loop {
+        let entries = entries(ctx, collection);
+        let index = state.cursors.borrow().loops[depth].1;
+        if index >= size(ctx, kind, &entries) {
+            break Ok(local(ctx, undefined()));
+        }
+        state.cursors.borrow_mut().loops[depth].1 = index + 1;
+        let (key, value) = entry(ctx, kind, &entries, index);
+        if let Err(()) = unsafe { call(ctx, callback, Some(this_arg), &[&value, &key, collection]) }
+        {
+            break Err(());
+        }
+    };
+    state.cursors.borrow_mut().loops.truncate(depth);
+    result
+}
+
+/// `keys()`, `values()` or `entries()` as a new array.
+#[allow(non_upper_case_globals)]
+fn list(
+    ctx: NonNull<JSContext>,
+    kind: Kind,
+    collection: &Local,
+    method: JSCollectionMethodEnum,
+) -> Result<Local, ()> {
+    let entries = entries(ctx, collection);
+    let count = size(ctx, kind, &entries);
+    let array = checked(ctx, unsafe { JS_NewArray(ctx.as_ptr(), count as c_int) })?;
+    for index in 0..count {
+        let (key, value) = entry(ctx, kind, &entries, index);
+        let item = match method {
+            JSCollectionMethodEnum_JS_COLLECTION_KEYS => key,
+            JSCollectionMethodEnum_JS_COLLECTION_VALUES => value,
+            _ => new_array(ctx, &[&key, &value])?,
+        };
+        set_element(ctx, &array, index, item.raw())?;
+    }
+    Ok(array)
+}
+
+/// Create a collection from Rust entries; the values are `None` for a `Set`.
+fn collect<'ctx, K, V, I>(ctx: &'ctx Context, kind: Kind, items: I) -> Result<Value<'ctx>, JsError>
+where
+    K: IntoValue<'ctx>,
+    V: IntoValue<'ctx>,
+    I: IntoIterator<Item = (K, Option<V>)>,
+{
+    let raw_ctx = ctx.raw_ctx();
+    let state = context_state(raw_ctx)?;
+    let error = || exception_error(raw_ctx.as_ptr());
+    let collection = unsafe { new_collection(raw_ctx, state, kind) }.map_err(|()| error())?;
+    for (key, value) in items {
+        let key = local(raw_ctx, key.into_value(ctx)?.raw());
+        let value = match value {
+            Some(value) => Some(local(raw_ctx, value.into_value(ctx)?.raw())),
+            None => None,
+        };
+        insert(raw_ctx, kind, &collection, &key, value.as_ref()).map_err(|()| error())?;
+    }
+    Ok(Value::new(raw_ctx, collection.raw()))
+}
+
+/// Convert the entries of `value`, which must be a `kind`. `convert` gets
+/// the rooted key and value of each entry.
+fn read<'ctx, T>(
+    value: Value<'ctx>,
+    kind: Kind,
+    mut convert: impl FnMut(&Local, &Local) -> Result<T, JsError>,
+) -> Result<Vec<T>, JsError> {
+    let raw_ctx = value.ctx();
+    if kind_of(raw_ctx, value.raw()) != Some(kind) {
+        return Err(JsError::type_mismatch(
+            format!("expected {}", kind.name()),
+            value.type_name(),
+        ));
+    }
+    let collection = local(raw_ctx, value.raw());
+    let entries = entries(raw_ctx, &collection);
+    (0..size(raw_ctx, kind, &entries))
+        .map(|index| {
+            let (key, value) = entry(raw_ctx, kind, &entries, index);
+            convert(&key, &value).map_err(|err| err.at_index(index as usize))
+        })
+        .collect()
+}
+
+/// Entries of `value` if it is a `Map`.
+pub(crate) fn map_entries<'ctx, K, V>(value: Value<'ctx>) -> Option<Result<Vec<(K, V)>, JsError>>
+where
+    K: FromValue<'ctx>,
+    V: FromValue<'ctx>,
+{
+    if kind_of(value.ctx(), value.raw()) != Some(Kind::Map) {
+        return None;
+    }
+    Some(read(value, Kind::Map, |key, value| {
+        Ok((
+            K::from_value(key.to_value())?,
+            V::from_value(value.to_value())?,
+        ))
+    }))
+}
+
+/// Converts into a JavaScript `Map` with the entries in key order.
+impl<'ctx, K, V> IntoValue<'ctx> for BTreeMap<K, V>
+where
+    K: IntoValue<'ctx>,
+    V: IntoValue<'ctx>,
+{
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        collect(
+            ctx,
+            Kind::Map,
+            self.into_iter().map(|(key, value)| (key, Some(value))),
+        )
+    }
+}
+
+/// Converts from a JavaScript `Map`.
+impl<'ctx, K, V> FromValue<'ctx> for BTreeMap<K, V>
+where
+    K: FromValue<'ctx> + Ord,
+    V: FromValue<'ctx>,
+{
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        map_entries(value)
+            .unwrap_or_else(|| Err(JsError::type_mismatch("expected Map", value.type_name())))
+            .map(|entries| entries.into_iter().collect())
+    }
+}
+
+/// Converts into a JavaScript `Set` with the values in order.
+impl<'ctx, T> IntoValue<'ctx> for BTreeSet<T>
+where
+    T: IntoValue<'ctx>,
+{
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        collect(
+            ctx,
+            Kind::Set,
+            self.into_iter().map(|item| (item, None::<T>)),
+        )
+    }
+}
+
+/// Converts from a JavaScript `Set`.
+impl<'ctx, T> FromValue<'ctx> for BTreeSet<T>
+where
+    T: FromValue<'ctx> + Ord,
+{
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        let items = read(value, Kind::Set, |item, _| T::from_value(item.to_value()))?;
+        Ok(items.into_iter().collect())
+    }
+}
+
+/// Converts into a JavaScript `Set`.
+#[cfg(feature = "std")]
+impl<'ctx, T> IntoValue<'ctx> for HashSet<T>
+where
+    T: IntoValue<'ctx>,
+{
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        collect(
+            ctx,
+            Kind::Set,
+            self.into_iter().map(|item| (item, None::<T>)),
+        )
+    }
+}
+
+/// Converts from a JavaScript `Set`.
+#[cfg(feature = "std")]
+impl<'ctx, T> FromValue<'ctx> for HashSet<T>
+where
+    T: FromValue<'ctx> + Eq + Hash,
+{
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        let items = read(value, Kind::Set, |item, _| T::from_value(item.to_value()))?;
+        Ok(items.into_iter().collect())
+    }
+}
+
+/// `HashMap` that converts to and from a JavaScript `Map`, with keys of any
+/// convertible type.
+///
+/// A bare `HashMap<String, T>` converts into a plain object instead. The
+/// entries of the `Map` are in the map's iteration order.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct AsMap<K, V>(pub HashMap<K, V>);
+
+#[cfg(feature = "std")]
+impl<K, V> AsMap<K, V> {
+    pub fn into_inner(self) -> HashMap<K, V> {
+        self.0
+    }
+}
+
+/// Converts into a JavaScript `Map`.
+#[cfg(feature = "std")]
+impl<'ctx, K, V> IntoValue<'ctx> for AsMap<K, V>
+where
+    K: IntoValue<'ctx>,
+    V: IntoValue<'ctx>,
+{
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        collect(
+            ctx,
+            Kind::Map,
+            self.0.into_iter().map(|(key, value)| (key, Some(value))),
+        )
+    }
+}
+
+/// Converts from a JavaScript `Map`.
+#[cfg(feature = "std")]
+impl<'ctx, K, V> FromValue<'ctx> for AsMap<K, V>
+where
+    K: FromValue<'ctx> + Eq + Hash,
+    V: FromValue<'ctx>,
+{
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        map_entries(value)
+            .unwrap_or_else(|| Err(JsError::type_mismatch("expected Map", value.type_name())))
+            .map(|entries| AsMap(entries.into_iter().collect()))
+    }
+}
@@ -0,0 +1,404 @@
+//! Script output from `print` and the `console` object.
+
+use alloc::collections::btree_map::Entry;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::ptr::NonNull;
+
+use mquickjs_sys::{
+    JSConsoleMethodEnum, JSConsoleMethodEnum_JS_CONSOLE_ASSERT, JSConsoleMethodEnum_JS_CONSOLE_DEBUG,
+    JSConsoleMethodEnum_JS_CONSOLE_ERROR, JSConsoleMethodEnum_JS_CONSOLE_INFO,
+    JSConsoleMethodEnum_JS_CONSOLE_TABLE, JSConsoleMethodEnum_JS_CONSOLE_TIME,
+    JSConsoleMethodEnum_JS_CONSOLE_TIME_END, JSConsoleMethodEnum_JS_CONSOLE_WARN, JSContext,
+    JSGCRef, JSValue, JS_DUMP_LONG, JS_GetPropertyStr, JS_IsString, JS_PopGCRef, JS_PrintValueF,
+    JS_PushGCRef, JS_TAG_UNDEFINED,
+};
+
+use crate::convert::{object_keys, FromValue};
+use crate::func::catch_panic;
+use crate::state::ContextState;
+use crate::value::{Value, ValueKind};
+
+/// Severity of a console line, from the method that printed it.
+///
+/// `print`, `console.log`, `console.table` and `console.timeEnd` print at
+/// [`Log`](Self::Log); `console.assert` reports failures at
+/// [`Error`](Self::Error).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsoleLevel {
+    Log,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl ConsoleLevel {
+    /// Name of the console method for this level, e.g. `"warn"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConsoleLevel::Log => "log",
+            ConsoleLevel::Debug => "debug",
+            ConsoleLevel::Info => "info",
+            ConsoleLevel::Warn => "warn",
+            ConsoleLevel::Error => "error",
+        }
+    }
+}
+
+/// Destination for lines printed by scripts, set with
+/// [`Context::set_console`](crate::Context::set_console).
+///
+/// Arguments are formatted like the `mqjs` shell does: strings as is, other
+/// values as the engine dumps them, separated by spaces. Closures taking a
+/// [`ConsoleLevel`] and a `&str` are sinks too.
+pub trait ConsoleSink: Send {
+    /// Receive the output of one console call, without the trailing newline.
+    ///
+    /// `console.table` output spans several lines separated by `\n`.
+    fn log(&mut self, level: ConsoleLevel, line: &str);
+}
+
+impl<F> ConsoleSink for F
+where
+    F: FnMut(ConsoleLevel, &str) + Send,
+{
+    fn log(&mut self, level: ConsoleLevel, line: &str) {
+        self(level, line)
+    }
+}
+
+/// Writes warnings and errors to standard error and everything else to
+/// standard output.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutConsole;
+
+#[cfg(feature = "std")]
+impl ConsoleSink for StdoutConsole {
+    fn log(&mut self, level: ConsoleLevel, line: &str) {
+        match level {
+            ConsoleLevel::Warn | ConsoleLevel::Error => std::eprintln!("{line}"),
+            _ => std::println!("{line}"),
+        }
+    }
+}
+
+/// Collects lines in memory, e.g. to assert on script output in tests.
+///
+/// Clones share the same buffer, so keep one clone and pass another to the
+/// context.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct CaptureConsole {
+    entries: std::sync::Arc<std::sync::Mutex<std::vec::Vec<(ConsoleLevel, String)>>>,
+}
+
+#[cfg(feature = "std")]
+impl CaptureConsole {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines captured so far, at every level.
+    pub fn lines(&self) -> std::vec::Vec<String> {
+        self.lock().iter().map(|(_, line)| line.clone()).collect()
+    }
+
+    /// Lines captured so far, with their levels.
+    pub fn entries(&self) -> std::vec::Vec<(ConsoleLevel, String)> {
+        self.lock().clone()
+    }
+
+    /// Remove and return the lines captured so far.
+    pub fn take(&self) -> std::vec::Vec<String> {
+        core::mem::take(&mut *self.lock()).into_iter().map(|(_, line)| line).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, std::vec::Vec<(ConsoleLevel, String)>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(feature = "std")]
+impl ConsoleSink for CaptureConsole {
+    fn log(&mut self, level: ConsoleLevel, line: &str) {
+        self.lock().push((level, line.into()));
+    }
+}
+
+/// Forwards lines to the `log` crate, target `"mquickjs"`.
+///
+/// `console.log` and `console.info` map to info level; the other methods
+/// map to the level of the same name.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogConsole;
+
+#[cfg(feature = "log")]
+impl ConsoleSink for LogConsole {
+    fn log(&mut self, level: ConsoleLevel, line: &str) {
+        let level = match level {
+            ConsoleLevel::Log | ConsoleLevel::Info => log::Level::Info,
+            ConsoleLevel::Debug => log::Level::Debug,
+            ConsoleLevel::Warn => log::Level::Warn,
+            ConsoleLevel::Error => log::Level::Error,
+        };
+        log::log!(target: "mquickjs", level, "{line}");
+    }
+}
+
+/// Emits lines as `tracing` events, target `"mquickjs"`, with levels mapped
+/// as for [`LogConsole`].
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingConsole;
+
+#[cfg(feature = "tracing")]
+impl ConsoleSink for TracingConsole {
+    fn log(&mut self, level: ConsoleLevel, line: &str) {
+        match level {
+            ConsoleLevel::Log | ConsoleLevel::Info => tracing::info!(target: "mquickjs", "{line}"),
+            ConsoleLevel::Debug => tracing::debug!(target: "mquickjs", "{line}"),
+            ConsoleLevel::Warn => tracing::warn!(target: "mquickjs", "{line}"),
+            ConsoleLevel::Error => tracing::error!(target: "mquickjs", "{line}"),
+        }
+    }
+}
+
+/// Run `print` or a `console` method, `magic` being a `JSConsoleMethodEnum`,
+/// and pass its output to the context's console sink.
+#[allow(non_upper_case_globals)]
+pub(crate) unsafe extern "C" fn console_callback(
+    ctx_ptr: *mut JSContext,
+    _this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+    magic: c_int,
+) -> JSValue {
+    let undefined = JS_TAG_UNDEFINED as JSValue;
+    let (Some(ctx), Some(state)) = (NonNull::new(ctx_ptr), unsafe { ContextState::from_raw(ctx_ptr) })
+    else {
+        return undefined;
+    };
+    if state.console.borrow().is_none() {
+        return undefined;
+    }
+    let args = if argv.is_null() || argc <= 0 {
+        &[][..]
+    } else {
+        unsafe { core::slice::from_raw_parts(argv, argc as usize) }
+    };
+
+    let output = match magic as JSConsoleMethodEnum {
+        JSConsoleMethodEnum_JS_CONSOLE_DEBUG => Some((ConsoleLevel::Debug, format_args(ctx, state, args))),
+        JSConsoleMethodEnum_JS_CONSOLE_INFO => Some((ConsoleLevel::Info, format_args(ctx, state, args))),
+        JSConsoleMethodEnum_JS_CONSOLE_WARN => Some((ConsoleLevel::Warn, format_args(ctx, state, args))),
+        JSConsoleMethodEnum_JS_CONSOLE_ERROR => Some((ConsoleLevel::Error, format_args(ctx, state, args))),
+        JSConsoleMethodEnum_JS_CONSOLE_ASSERT => {
+            if args.first().is_some_and(|arg| is_truthy(Value::new(ctx, *arg))) {
+                None
+            } else if args.len() > 1 {
+                let message = format_args(ctx, state, &args[1..]);
+                Some((ConsoleLevel::Error, format!("Assertion failed: {message}")))
+            } else {
+                Some((ConsoleLevel::Error, "Assertion failed".into()))
+            }
+        }
+        JSConsoleMethodEnum_JS_CONSOLE_TIME => {
+            let label = timer_label(ctx, state, args);
+            match state.console_timers.borrow_mut().entry(label) {
+                Entry::Occupied(entry) => {
+                    Some((ConsoleLevel::Warn, format!("Timer '{}' already exists", entry.key())))
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(state.monotonic_ms().unwrap_or(0.0));
+                    None
+                }
+            }
+        }
+        JSConsoleMethodEnum_JS_CONSOLE_TIME_END => {
+            let label = timer_label(ctx, state, args);
+            match state.console_timers.borrow_mut().remove(&label) {
+                Some(start) => {
+                    let elapsed = state.monotonic_ms().unwrap_or(start) - start;
+                    Some((ConsoleLevel::Log, format!("{label}: {elapsed:.3}ms")))
+                }
+                None => Some((ConsoleLevel::Warn, format!("Timer '{label}' does not exist"))),
+            }
+        }
+        JSConsoleMethodEnum_JS_CONSOLE_TABLE => {
+            let table = args.first().and_then(|_| format_table(ctx, state, argv, args.get(1).copied()));
+            Some((ConsoleLevel::Log, table.unwrap_or_else(|| format_args(ctx, state, args))))
+        }
+        _ => Some((ConsoleLevel::Log, format_args(ctx, state, args))),
+    };
+
+    if let Some((level, line)) = output
+        && let Ok(mut console) = state.console.try_borrow_mut()
+        && let Some(console) = console.as_mut()
+    {
+        catch_panic(|| console.log(level, &line));
+    }
+    undefined
+}
+
+/// Join `args` with spaces: strings as is, other values dumped.
+fn format_args(ctx: NonNull<JSContext>, state: &ContextState, args: &[JSValue]) -> String {
+    let mut line = String::new();
+    for (index, arg) in args.iter().enumerate() {
+        if index != 0 {
+            line.push(' ');
+        }
+        if unsafe { JS_IsString(ctx.as_ptr(), *arg) } != 0 {
+            if let Ok(text) = Value::new(ctx, *arg).to_string() {
+                line.push_str(&text);
+            }
+        } else {
+            line.push_str(&dump_value(ctx.as_ptr(), state, *arg));
+        }
+    }
+    line
+}
+
+/// JavaScript truthiness, for `console.assert`.
+fn is_truthy(value: Value<'_>) -> bool {
+    match value.kind() {
+        ValueKind::Undefined | ValueKind::Null => false,
+        ValueKind::Bool => value.to_bool().unwrap_or(false),
+        ValueKind::Int | ValueKind::Float => value.to_f64().is_ok_and(|n| n != 0.0 && !n.is_nan()),
+        ValueKind::String => value.to_string().is_ok_and(|text| !text.is_empty()),
+        _ => true,
+    }
+}
+
+/// Label of `console.time`/`console.timeEnd`, `"default"` when omitted.
+fn timer_label(ctx: NonNull<JSContext>, state: &ContextState, args: &[JSValue]) -> String {
+    match args.first() {
+        Some(arg) if !Value::new(ctx, *arg).is_undefined() => format_args(ctx, state, &args[..1]),
+        _ => "default".into(),
+    }
+}
+
+/// One row of `console.table`: the property name, the formatted cells of an
+/// object value by column, or the formatted value itself.
+struct TableRow {
+    key: String,
+    cells: Vec<(String, String)>,
+    plain: Option<String>,
+}
+
+/// Render `argv[0]` as a box-drawn table with one row per own property,
+/// or `None` if it is not an object.
+///
+/// `argv[0]` is re-read after every call into the engine because a GC may
+/// move the object; rows are kept in a GC reference for the same reason.
+fn format_table(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    argv: *mut JSValue,
+    columns: Option<JSValue>,
+) -> Option<String> {
+    let data = || unsafe { *argv };
+    if !Value::new(ctx, data()).is_object() {
+        return None;
+    }
+    let mut headers: Vec<String> = match columns {
+        Some(columns) if Value::new(ctx, columns).is_array() => {
+            Vec::<String>::from_value(Value::new(ctx, columns)).ok()?
+        }
+        _ => Vec::new(),
+    };
+    let row_keys = object_keys(ctx, Value::new(ctx, data())).ok()?;
+    let pick_columns = headers.is_empty();
+
+    let mut gc_ref = JSGCRef { val: JS_TAG_UNDEFINED as JSValue, prev: core::ptr::null_mut() };
+    let row = unsafe { JS_PushGCRef(ctx.as_ptr(), &mut gc_ref) };
+    let mut plain_values = false;
+    let mut rows = Vec::new();
+    for key in &row_keys {
+        let Ok(name) = CString::new(key.as_str()) else { continue };
+        unsafe { *row = JS_GetPropertyStr(ctx.as_ptr(), data(), name.as_ptr()) };
+        let value = Value::new(ctx, unsafe { *row });
+        if !value.is_object() || value.is_function() {
+            plain_values = true;
+            let plain = dump_value(ctx.as_ptr(), state, unsafe { *row });
+            rows.push(TableRow { key: key.clone(), cells: Vec::new(), plain: Some(plain) });
+            continue;
+        }
+        let mut cells = Vec::new();
+        for column in object_keys(ctx, value).unwrap_or_default() {
+            if pick_columns && !headers.contains(&column) {
+                headers.push(column.clone());
+            }
+            let Ok(column_name) = CString::new(column.as_str()) else { continue };
+            let cell = unsafe { JS_GetPropertyStr(ctx.as_ptr(), *row, column_name.as_ptr()) };
+            cells.push((column, dump_value(ctx.as_ptr(), state, cell)));
+        }
+        rows.push(TableRow { key: key.clone(), cells, plain: None });
+    }
+    unsafe { JS_PopGCRef(ctx.as_ptr(), &mut gc_ref) };
+
+    let mut table: Vec<Vec<String>> = Vec::with_capacity(rows.len() + 1);
+    let mut header_row = vec![String::from("(index)")];
+    header_row.extend(headers.iter().cloned());
+    if plain_values {
+        header_row.push("Values".into());
+    }
+    table.push(header_row);
+    for row in rows {
+        let mut line = vec![row.key];
+        for header in &headers {
+            let cell = row.cells.iter().find(|(column, _)| column == header);
+            line.push(cell.map(|(_, text)| text.clone()).unwrap_or_default());
+        }
+        if plain_values {
+            line.push(row.plain.unwrap_or_default());
+        }
+        table.push(line);
+    }
+    Some(draw_table(&table))
+}
+
+/// Draw `rows`, the first being the header, with box-drawing characters.
+fn draw_table(rows: &[Vec<String>]) -> String {
+    let mut widths = vec![0; rows[0].len()];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let border = |left: &str, middle: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        format!("{left}{}{right}", segments.join(middle))
+    };
+    let line = |row: &[String]| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!(" {cell}{} ", " ".repeat(width - cell.chars().count())))
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+
+    let mut lines = vec![border("┌", "┬", "┐"), line(&rows[0]), border("├", "┼", "┤")];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.push(border("└", "┴", "┘"));
+    lines.join("\n")
+}
+
+/// Format `value` with the engine's dump routine, which writes through the
+/// log function.
+fn dump_value(ctx: *mut JSContext, state: &ContextState, value: JSValue) -> String {
+    let previous = state.capture.replace(Some(String::new()));
+    unsafe {
+        JS_PrintValueF(ctx, value, JS_DUMP_LONG as c_int);
+    }
+    let text = state.capture.replace(previous);
+    text.unwrap_or_default()
+}
@@ -1,48 +1,183 @@
-use std::ffi::{c_char, c_void, CString};
-use std::ptr::NonNull;
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void};
+use core::future::{self, Future};
+use core::mem::MaybeUninit;
+use core::ptr::{self, NonNull};
+use core::task::{self, Poll};
 
 use mquickjs_sys::{
     js_stdlib, JSContext, JS_EVAL_RETVAL, JS_Eval, JS_EX_NORMAL, JS_FreeContext,
-    JS_GC, JS_GetErrorStr, JS_NewContext, JS_TAG_EXCEPTION, JS_TAG_SPECIAL_BITS, JSValue,
+    JS_GC, JS_GetErrorStr, JS_GetMemorySize, JS_GetOutOfMemorySize, JS_IsOutOfMemoryException,
+    JS_NewContext, JS_PrepareSnapshot, JS_RelocateContext, JS_SetContextOpaque,
+    JS_GetMemoryUsed, JS_SetInterruptHandler, JS_SetLogFunc, JS_SetMemoryHandler,
+    JS_SetMemoryQuota, JS_SetMemoryThreshold, JS_SetRandomSeed, JS_TAG_EXCEPTION, JS_TAG_SPECIAL_BITS, JSValue,
+    JS_Call, JS_PushArg, JS_StackCheck, JS_TAG_NULL,
 };
 
+use crate::clock::Clock;
+use crate::console::ConsoleSink;
+use crate::convert::is_exception;
+#[cfg(feature = "coroutine")]
+use crate::coroutine::Suspendable;
 use crate::error::JsError;
-use crate::func::{register_callback, register_context, unregister_context};
+use crate::data::JsData;
+use crate::func::{async_callback, HostFn};
+use crate::heap::{Heap, HeapGrowth};
+use crate::promise;
 use crate::rooted::RootedValue;
+use crate::snapshot::HeapImage;
+use crate::quota::{MemoryQuota, QuotaState, QuotaThreshold};
+use crate::state::{interrupt_handler, log_write, memory_handler, ContextState};
+use crate::stats::MemoryStats;
+use crate::task::poll_tasks;
 use crate::value::Value;
 
 /// JavaScript execution context owning the underlying mquickjs state.
-#[derive(Debug)]
+///
+/// A context can move to another thread but cannot be shared between threads.
 pub struct Context {
     ctx: NonNull<JSContext>,
-    _heap: Vec<usize>,
+    state: Box<ContextState>,
+    heap: Heap,
 }
 
+// SAFETY: the engine state lives in `heap` and `state`, both owned by the
+// context. Everything stored in `state` is `Send`, and values borrowing the
+// context cannot outlive it, so nothing is left behind on the old thread.
+unsafe impl Send for Context {}
+
 impl Context {
     /// Create a new JavaScript context with the given memory buffer size.
     ///
     /// The buffer must be at least 1024 bytes.
     pub fn new(memory_bytes: usize) -> Result<Self, JsError> {
-        if memory_bytes < 1024 {
+        check_memory_size(memory_bytes)?;
+        Self::with_heap(Heap::owned(memory_bytes))
+    }
+
+    /// Create a context whose heap is `buffer`, e.g. a `static` placed in a
+    /// dedicated memory region. Nothing is allocated for the heap.
+    ///
+    /// The buffer must be at least 1024 bytes.
+    pub fn with_buffer(buffer: &'static mut [MaybeUninit<usize>]) -> Result<Self, JsError> {
+        check_memory_size(size_of_val(buffer))?;
+        Self::with_heap(Heap::from_static(buffer))
+    }
+
+    /// Create a context whose memory grows on demand as set by `growth`.
+    ///
+    /// Only `growth.initial_bytes` are allocated up front. When the heap
+    /// needs more, it moves to a larger buffer; with `growth.shrink` set it
+    /// moves to a smaller one after a GC. Scripts get
+    /// [`JsError::OutOfMemory`] only once `max_bytes` is exhausted.
+    pub fn with_growth(growth: HeapGrowth) -> Result<Self, JsError> {
+        check_memory_size(growth.initial_bytes)?;
+        if growth.max_bytes < growth.initial_bytes {
+            return Err(JsError::ContextInit {
+                message: "maximum heap size must be at least the initial size".to_string(),
+            });
+        }
+        if !(growth.factor > 1.0 && growth.factor.is_finite()) {
             return Err(JsError::ContextInit {
-                message: "memory buffer must be at least 1024 bytes".to_string(),
+                message: "heap growth factor must be greater than 1".to_string(),
             });
         }
+        let ctx = Self::with_heap(Heap::owned(growth.initial_bytes))?;
+        ctx.state.growth.set(Some(growth));
+        ctx.install_growth();
+        Ok(ctx)
+    }
 
-        let word_size = std::mem::size_of::<usize>();
-        let words = (memory_bytes + word_size - 1) / word_size;
-        let mut heap = vec![0usize; words];
+    fn with_heap(mut heap: Heap) -> Result<Self, JsError> {
         let mem_start = heap.as_mut_ptr() as *mut c_void;
-        let mem_size = heap.len() * word_size;
-
-        let ctx = unsafe { JS_NewContext(mem_start, mem_size, &js_stdlib) };
+        let ctx = unsafe { JS_NewContext(mem_start, heap.byte_len(), &js_stdlib) };
         let ctx = NonNull::new(ctx).ok_or_else(|| JsError::ContextInit {
             message: "JS_NewContext returned null".to_string(),
         })?;
 
-        register_context(ctx);
+        Ok(Self::with_state(ctx, ContextState::new(), heap))
+    }
+
+    /// Create a context from a heap image taken with [`Context::snapshot`].
+    ///
+    /// The copy has the globals, registered functions, disabled globals,
+    /// heap growth policy and memory quota of the original. It gets its own
+    /// random seed unless one was set on the original. Callbacks are not
+    /// copied: the clone has no console or log sink, clock, interrupt or
+    /// threshold handler; use [`ContextBuilder::snapshot`](crate::ContextBuilder::snapshot)
+    /// to set them.
+    pub fn from_snapshot(image: &HeapImage) -> Result<Self, JsError> {
+        Self::from_image(image.clone())
+    }
+
+    pub(crate) fn from_image(image: HeapImage) -> Result<Self, JsError> {
+        let mut heap = Heap::Owned(image.heap);
+        let mut grown_heap = image.grown_heap.map(Heap::Owned);
+        let offset = image.ctx_offset;
+        let mem_start = unsafe { heap.as_mut_ptr().byte_add(offset) } as *mut c_void;
+        let heap_start = grown_heap
+            .as_mut()
+            .map_or(ptr::null_mut(), |heap| heap.as_mut_ptr() as *mut c_void);
+        let ctx = unsafe { JS_RelocateContext(mem_start, image.base + offset, heap_start, image.grown_base) };
+        let ctx = NonNull::new(ctx).ok_or_else(|| JsError::ContextInit {
+            message: "JS_RelocateContext returned null".to_string(),
+        })?;
+        let seed = image.random_seed.unwrap_or_else(|| random_seed(mem_start as usize));
+        unsafe {
+            JS_SetRandomSeed(ctx.as_ptr(), seed);
+        }
 
-        Ok(Self { ctx, _heap: heap })
+        let state = ContextState::with_registry(image.registry);
+        state.growth.set(image.growth);
+        *state.grown_heap.borrow_mut() = grown_heap;
+        state.random_seed.set(image.random_seed);
+        *state.disabled_globals.borrow_mut() = image.disabled_globals;
+        *state.quota.borrow_mut() = image.quota;
+        let ctx = Self::with_state(ctx, state, heap);
+        ctx.install_growth();
+        ctx.install_quota();
+        Ok(ctx)
+    }
+
+    fn with_state(ctx: NonNull<JSContext>, state: Box<ContextState>, heap: Heap) -> Self {
+        unsafe {
+            JS_SetContextOpaque(ctx.as_ptr(), &*state as *const ContextState as *mut c_void);
+            JS_SetLogFunc(ctx.as_ptr(), Some(log_write));
+        }
+        Self { ctx, state, heap }
+    }
+
+    /// Copy the heap so that [`Context::from_snapshot`] can clone this context.
+    ///
+    /// Runs a GC first. Fails while values are rooted, from inside a host
+    /// callback, or when the heap holds objects of user classes.
+    pub fn snapshot(&self) -> Result<HeapImage, JsError> {
+        if unsafe { JS_PrepareSnapshot(self.ctx.as_ptr()) } != 0 {
+            return Err(JsError::Runtime {
+                message: "context cannot be snapshotted while values are rooted or code is running"
+                    .to_string(),
+            });
+        }
+        let base = self.heap.as_ptr() as usize;
+        let grown_heap = self.state.grown_heap.borrow();
+        Ok(HeapImage {
+            heap: self.heap.to_vec(),
+            base,
+            ctx_offset: self.ctx.as_ptr() as usize - base,
+            grown_heap: grown_heap.as_ref().map(|heap| heap.to_vec()),
+            grown_base: grown_heap.as_ref().map_or(0, |heap| heap.as_ptr() as usize),
+            registry: self.state.registry.borrow().clone(),
+            growth: self.state.growth.get(),
+            quota: self.state.quota.borrow().clone(),
+            random_seed: self.state.random_seed.get(),
+            disabled_globals: self.state.disabled_globals.borrow().clone(),
+        })
     }
 
     /// Evaluate a script and return a raw value wrapper.
@@ -55,6 +190,11 @@ impl Context {
         self.ctx
     }
 
+    #[cfg(feature = "coroutine")]
+    pub(crate) fn state(&self) -> &ContextState {
+        &self.state
+    }
+
     /// Evaluate a script and convert the result to i32.
     pub fn eval_i32(&self, script: &str, filename: &str) -> Result<i32, JsError> {
         self.eval(script, filename)?.to_i32()
@@ -82,6 +222,11 @@ impl Context {
         }
     }
 
+    /// Report heap usage: sizes, free space and object counts per class.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats::collect(self.ctx)
+    }
+
     /// Root a value to keep it alive across GC cycles.
     pub fn root<'ctx>(&'ctx self, value: Value<'ctx>) -> RootedValue<'ctx> {
         RootedValue::new(self.ctx, value)
@@ -103,9 +248,93 @@ impl Context {
     /// ```
     pub fn register_fn<F>(&self, name: &str, func: F) -> Result<(), JsError>
     where
-        F: for<'ctx> Fn(&[Value<'ctx>]) -> Result<Value<'ctx>, JsError> + 'static,
+        F: for<'ctx> Fn(&[Value<'ctx>]) -> Result<Value<'ctx>, JsError> + Send + Sync + 'static,
+    {
+        self.register_callback(name, HostFn::Sync(Arc::new(func)))
+    }
+
+    /// Register an async Rust function callable from JavaScript.
+    ///
+    /// The arguments are copied into [`JsData`] trees and the call returns a
+    /// `Promise`. It is fulfilled with the data the future completes with, or
+    /// rejected with the error message, the way [`register_fn`](Self::register_fn)
+    /// callbacks throw. The future only makes progress while the event loop is
+    /// driven with [`poll_event_loop`](Self::poll_event_loop) or
+    /// [`run_async`](Self::run_async).
+    ///
+    /// ```no_run
+    /// use mquickjs_rs::{Context, JsData};
+    ///
+    /// # async fn example() {
+    /// let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    /// ctx.register_async_fn("lookup", |args: Vec<JsData>| async move {
+    ///     let key = args.first().cloned().unwrap_or(JsData::Undefined);
+    ///     Ok(JsData::Array(vec![key]))
+    /// }).expect("register should succeed");
+    ///
+    /// ctx.eval("lookup('a').then(function (row) { globalThis.row = row[0]; })", "example")
+    ///     .expect("eval should succeed");
+    /// ctx.run_async().await.expect("event loop should run");
+    /// assert_eq!(ctx.eval_string("row", "example").expect("eval should succeed"), "a");
+    /// # }
+    /// ```
+    pub fn register_async_fn<F, Fut>(&self, name: &str, func: F) -> Result<(), JsError>
+    where
+        F: Fn(Vec<JsData>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<JsData, JsError>> + Send + 'static,
     {
-        let id = register_callback(self.ctx, Box::new(func));
+        self.register_callback(name, HostFn::Async(async_callback(func)))
+    }
+
+    /// Register a Rust function that looks blocking to scripts but waits for
+    /// a future without blocking the thread.
+    ///
+    /// The arguments are copied into [`JsData`] trees. Called from a script
+    /// run with [`eval_async`](Self::eval_async) or
+    /// [`Function::call_async`](crate::Function::call_async), it suspends the
+    /// script until the future completes and returns the data, or throws the
+    /// error message. Called from any other script it throws.
+    ///
+    /// ```no_run
+    /// use mquickjs_rs::{Context, JsData};
+    ///
+    /// # async fn example() {
+    /// let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    /// ctx.register_suspend_fn("get", |args: Vec<JsData>| async move {
+    ///     Ok(args.into_iter().next().unwrap_or(JsData::Null))
+    /// }).expect("register should succeed");
+    ///
+    /// let value = ctx.eval_async("get(40) + 2", "example").await.expect("eval should succeed");
+    /// assert_eq!(value.to_i32().expect("number"), 42);
+    /// # }
+    /// ```
+    #[cfg(feature = "coroutine")]
+    pub fn register_suspend_fn<F, Fut>(&self, name: &str, func: F) -> Result<(), JsError>
+    where
+        F: Fn(Vec<JsData>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<JsData, JsError>> + Send + 'static,
+    {
+        self.register_callback(name, HostFn::Suspend(async_callback(func)))
+    }
+
+    /// Evaluate a script on a separate stack, so that functions registered
+    /// with [`register_suspend_fn`](Self::register_suspend_fn) can suspend
+    /// it while their futures are pending.
+    ///
+    /// Only one such script runs per context at a time; starting another
+    /// while one is suspended fails. Dropping the future before it completes
+    /// runs the rest of the script at once, with suspending calls throwing.
+    #[cfg(feature = "coroutine")]
+    pub async fn eval_async(&self, script: &str, filename: &str) -> Result<Value<'_>, JsError> {
+        Suspendable::new(self, move || self.eval_raw(script, filename)).await
+    }
+
+    pub(crate) fn register_callback(&self, name: &str, callback: HostFn) -> Result<(), JsError> {
+        let id = self.state.registry.borrow_mut().insert(name, callback);
+        self.install_fn(name, id)
+    }
+
+    fn install_fn(&self, name: &str, id: u32) -> Result<(), JsError> {
         let name = escape_js_string(name);
         let script = format!(
             "globalThis['{name}'] = function() {{\n  var args = [{id}];\n  for (var i = 0; i < arguments.length; i++) {{\n    args.push(arguments[i]);\n  }}\n  return load.apply(null, args);\n}};"
@@ -114,7 +343,370 @@ impl Context {
         Ok(())
     }
 
-    fn eval_raw(&self, script: &str, filename: &str) -> Result<JSValue, JsError> {
+    /// Restore the context to its just-created state in place.
+    ///
+    /// Pending timers, jobs and async host calls are dropped. The heap is
+    /// cleared and `JS_NewContext` runs again over the same buffer, then
+    /// registered functions and the configuration from
+    /// [`ContextBuilder`](crate::ContextBuilder) are applied again; a
+    /// growable context starts over at its initial size and the memory quota
+    /// stays in place. Taking `&mut self` ensures no values or rooted handles
+    /// of the previous state are still alive.
+    ///
+    /// If the engine cannot be set up again a
+    /// [`JsError::ContextInit`](crate::JsError::ContextInit) is returned and
+    /// the context should only be dropped.
+    pub fn reset(&mut self) -> Result<(), JsError> {
+        unsafe {
+            JS_FreeContext(self.ctx.as_ptr());
+        }
+        self.heap.fill(0);
+        self.state.grown_heap.borrow_mut().take();
+        self.state.timers.borrow_mut().forget();
+        self.state.promises.borrow_mut().forget();
+        self.state.collected_promises.borrow_mut().clear();
+        self.state.tasks.borrow_mut().forget();
+        self.state.timer_wake.borrow_mut().take();
+        self.state.console_timers.borrow_mut().clear();
+        let mem_start = self.heap.as_mut_ptr() as *mut c_void;
+        let ctx = unsafe { JS_NewContext(mem_start, self.heap.byte_len(), &js_stdlib) };
+        // On failure `self.ctx` still points at the zeroed buffer, where
+        // `JS_FreeContext` finds no objects to finalize, so dropping is safe.
+        self.ctx = NonNull::new(ctx).ok_or_else(|| JsError::ContextInit {
+            message: "JS_NewContext returned null".to_string(),
+        })?;
+        unsafe {
+            JS_SetContextOpaque(self.ctx.as_ptr(), &*self.state as *const ContextState as *mut c_void);
+            if self.state.interrupt.borrow().is_some() {
+                JS_SetInterruptHandler(self.ctx.as_ptr(), Some(interrupt_handler));
+            }
+            JS_SetLogFunc(self.ctx.as_ptr(), Some(log_write));
+            if let Some(seed) = self.state.random_seed.get() {
+                JS_SetRandomSeed(self.ctx.as_ptr(), seed);
+            }
+        }
+        self.install_growth();
+        let used = self.memory_used();
+        if let Some(quota) = self.state.quota.get_mut() {
+            quota.rearm(used);
+        }
+        self.install_quota();
+
+        let disabled = self.state.disabled_globals.borrow().clone();
+        for name in disabled {
+            self.delete_global(&name)?;
+        }
+        let globals = self.state.registry.borrow().globals().to_vec();
+        for (name, id) in globals {
+            self.install_fn(&name, id)?;
+        }
+        Ok(())
+    }
+
+    /// Forget registered functions and configuration, so the next
+    /// [`reset`](Self::reset) leaves the context as [`Context::new`] made it.
+    #[cfg(feature = "std")]
+    pub(crate) fn clear_config(&mut self) {
+        self.state.clear_config();
+    }
+
+    /// Seed `Math.random`, also after [`Context::reset`].
+    pub(crate) fn set_random_seed(&self, seed: u64) {
+        self.state.random_seed.set(Some(seed));
+        unsafe {
+            JS_SetRandomSeed(self.ctx.as_ptr(), seed);
+        }
+    }
+
+    /// Send the engine's debug output (value dumps, GC traces) to `sink`.
+    pub(crate) fn set_log_sink<F>(&self, sink: F)
+    where
+        F: FnMut(&str) + Send + 'static,
+    {
+        *self.state.log.borrow_mut() = Some(Box::new(sink));
+    }
+
+    /// Send the output of `print` and the `console` methods to `sink`.
+    ///
+    /// Without a sink the output is discarded.
+    pub fn set_console<S>(&self, sink: S)
+    where
+        S: ConsoleSink + 'static,
+    {
+        self.set_console_boxed(Box::new(sink));
+    }
+
+    pub(crate) fn set_console_boxed(&self, sink: Box<dyn ConsoleSink>) {
+        *self.state.console.borrow_mut() = Some(sink);
+    }
+
+    /// Use `clock` for `Date.now`, `performance.now` and `console.time`.
+    ///
+    /// The clock is kept across [`reset`](Self::reset).
+    pub fn set_clock<C>(&self, clock: C)
+    where
+        C: Clock + 'static,
+    {
+        self.set_clock_boxed(Box::new(clock));
+    }
+
+    pub(crate) fn set_clock_boxed(&self, clock: Box<dyn Clock>) {
+        *self.state.clock.borrow_mut() = Some(clock);
+    }
+
+    /// Run queued promise reactions and `queueMicrotask` callbacks until
+    /// none are left, including those queued while running, and return how
+    /// many ran.
+    ///
+    /// Exceptions thrown by promise handlers reject the promise returned by
+    /// `then`. An exception thrown by a `queueMicrotask` callback, or an
+    /// interrupt, is returned at once and the remaining jobs stay queued.
+    pub fn run_jobs(&self) -> Result<usize, JsError> {
+        let mut ran = 0;
+        loop {
+            let Some(job) = self.state.promises.borrow_mut().pop_job() else {
+                return Ok(ran);
+            };
+            ran += 1;
+            if unsafe { promise::run_job(self.ctx, &self.state, job) }.is_err() {
+                return Err(exception_error(self.ctx.as_ptr()));
+            }
+        }
+    }
+
+    /// Return true if [`run_jobs`](Self::run_jobs) has jobs to run.
+    pub fn has_pending_jobs(&self) -> bool {
+        self.state.promises.borrow().has_jobs()
+    }
+
+    /// Run the `setTimeout`/`setInterval` callbacks that are due on the
+    /// context's clock and return how many ran.
+    ///
+    /// Queued jobs run after each callback, as in browsers. Timers scheduled
+    /// by the callbacks wait for the next call, even with a zero delay. An
+    /// exception thrown by a callback is returned at once and the remaining
+    /// due timers stay queued.
+    pub fn run_timers(&self) -> Result<usize, JsError> {
+        self.run_due_timers(usize::MAX)
+    }
+
+    /// Monotonic time, in the clock's milliseconds, at which the next timer
+    /// is due, or `None` without pending timers.
+    pub fn next_timer_deadline(&self) -> Option<f64> {
+        self.state.timers.borrow().next_deadline()
+    }
+
+    /// Run jobs and timers until none are pending, waiting for each deadline
+    /// with [`Clock::wait_until`], and return how many timer callbacks ran.
+    ///
+    /// Stops after `max` callbacks so intervals cannot run forever, and when
+    /// the clock does not reach the next deadline.
+    pub fn run_until_idle(&self, max: usize) -> Result<usize, JsError> {
+        self.run_jobs()?;
+        let mut ran = 0;
+        while ran < max {
+            let Some(deadline) = self.next_timer_deadline() else {
+                break;
+            };
+            if self.state.monotonic_ms().unwrap_or(0.0) < deadline {
+                if let Some(clock) = self.state.clock.borrow().as_ref() {
+                    clock.wait_until(deadline);
+                }
+                if self.state.monotonic_ms().unwrap_or(0.0) < deadline {
+                    break;
+                }
+            }
+            ran += self.run_due_timers(max - ran)?;
+        }
+        Ok(ran)
+    }
+
+    /// Make progress on everything the scripts wait for: run queued jobs,
+    /// poll the futures of [async host functions](Self::register_async_fn)
+    /// and settle their promises, and run due timers.
+    ///
+    /// Returns `Ready` once no jobs, host calls or timers are pending. Host
+    /// futures wake `cx` when they can make progress. While the next timer
+    /// is not due yet, the clock is asked to wake `cx` at its deadline with
+    /// [`Clock::wake_at`]; with a clock that cannot, callers wait for
+    /// [`next_timer_deadline`](Self::next_timer_deadline) before polling
+    /// again. An exception thrown by a timer or microtask is returned as in
+    /// [`run_timers`](Self::run_timers).
+    pub fn poll_event_loop(&self, cx: &mut task::Context<'_>) -> Poll<Result<(), JsError>> {
+        self.run_jobs()?;
+        // Settling promises runs handlers that may start more host calls,
+        // which are polled in the next round.
+        while poll_tasks(self, &self.state, cx)? > 0 {
+            self.run_jobs()?;
+        }
+        // Timer callbacks may also have started host calls that were not
+        // polled yet.
+        if self.run_timers()? > 0 {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        if let Some(deadline) = self.next_timer_deadline() {
+            if self.state.monotonic_ms().is_some_and(|now| now >= deadline) {
+                cx.waker().wake_by_ref();
+            } else {
+                self.state.wake_at(deadline, cx.waker());
+            }
+            return Poll::Pending;
+        }
+        if self.state.tasks.borrow().is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Drive the event loop with [`poll_event_loop`](Self::poll_event_loop)
+    /// until no jobs, host calls or timers are pending.
+    pub async fn run_async(&self) -> Result<(), JsError> {
+        future::poll_fn(|cx| self.poll_event_loop(cx)).await
+    }
+
+    fn run_due_timers(&self, max: usize) -> Result<usize, JsError> {
+        let ctx = self.ctx.as_ptr();
+        let now = self.state.monotonic_ms().unwrap_or(0.0);
+        let due = self.state.timers.borrow().due(now);
+        let mut ran = 0;
+        for id in due.into_iter().take(max) {
+            // An earlier callback may have cleared it.
+            let Some(mut timer) = self.state.timers.borrow_mut().remove(id) else {
+                continue;
+            };
+            if unsafe { JS_StackCheck(ctx, (timer.args.len() + 2) as u32) } != 0 {
+                self.state.timers.borrow_mut().reschedule(id, timer);
+                return Err(JsError::Runtime {
+                    message: "stack overflow when running a timer".to_string(),
+                });
+            }
+            unsafe {
+                for arg in timer.args.iter().rev() {
+                    JS_PushArg(ctx, arg.val);
+                }
+                JS_PushArg(ctx, timer.func.val);
+                JS_PushArg(ctx, JS_TAG_NULL as JSValue);
+            }
+            // The call arguments are on the JS stack now, so the timer can be
+            // rescheduled or released before running, as `clearInterval` from
+            // inside the callback expects.
+            let argc = timer.args.len() as c_int;
+            match timer.interval {
+                Some(interval) => {
+                    timer.deadline = now + interval;
+                    self.state.timers.borrow_mut().reschedule(id, timer);
+                }
+                None => timer.release(ctx),
+            }
+            let result = unsafe { JS_Call(ctx, argc) };
+            ran += 1;
+            if is_exception(result) {
+                return Err(exception_error(ctx));
+            }
+            self.run_jobs()?;
+        }
+        Ok(ran)
+    }
+
+    /// Delete global `name`, also after [`Context::reset`].
+    pub(crate) fn disable_global(&self, name: &str) -> Result<(), JsError> {
+        self.delete_global(name)?;
+        self.state.disabled_globals.borrow_mut().push(name.to_string());
+        Ok(())
+    }
+
+    fn delete_global(&self, name: &str) -> Result<(), JsError> {
+        let name = escape_js_string(name);
+        self.eval_raw(&format!("delete globalThis['{name}'];"), "<disable_global>")?;
+        Ok(())
+    }
+
+    /// Let a growable context move its heap from the memory handler.
+    fn install_growth(&self) {
+        if self.state.growth.get().is_none() {
+            return;
+        }
+        unsafe {
+            JS_SetMemoryHandler(self.ctx.as_ptr(), Some(memory_handler));
+        }
+    }
+
+    /// Limit the memory the context may use, independently of its buffer.
+    ///
+    /// Once usage would exceed `quota.limit_bytes` even after a GC, the
+    /// allocation throws a `RangeError` that scripts can catch. Crossing one
+    /// of `quota.thresholds` calls the handler set with
+    /// [`Context::on_memory_threshold`]; a threshold fires again after a GC
+    /// brings usage back below it.
+    pub fn set_memory_quota(&self, quota: MemoryQuota) {
+        *self.state.quota.borrow_mut() = Some(QuotaState::new(quota, self.memory_used()));
+        self.install_quota();
+    }
+
+    /// Remove the memory quota.
+    pub fn clear_memory_quota(&self) {
+        *self.state.quota.borrow_mut() = None;
+        unsafe {
+            JS_SetMemoryQuota(self.ctx.as_ptr(), 0);
+            JS_SetMemoryThreshold(self.ctx.as_ptr(), 0);
+        }
+    }
+
+    /// The memory quota in bytes, if one is set.
+    pub fn memory_quota(&self) -> Option<usize> {
+        self.state.quota.borrow().as_ref().map(|quota| quota.limit_bytes)
+    }
+
+    /// Call `handler` when usage crosses a threshold of the memory quota.
+    ///
+    /// The handler runs inside the engine allocator and cannot reach the
+    /// context; record the event and act on it once evaluation returns.
+    pub fn on_memory_threshold<F>(&self, handler: F)
+    where
+        F: FnMut(QuotaThreshold) + Send + 'static,
+    {
+        *self.state.on_threshold.borrow_mut() = Some(Box::new(handler));
+    }
+
+    /// Bytes allocated for the context memory: its buffer, plus the heap
+    /// buffer of a growable context that outgrew it.
+    pub fn buffer_bytes(&self) -> usize {
+        let grown = self.state.grown_heap.borrow();
+        self.heap.byte_len() + grown.as_ref().map_or(0, |heap| heap.byte_len())
+    }
+
+    /// Bytes in use by the context header, heap and stack, as counted
+    /// against the memory quota.
+    pub fn memory_used(&self) -> usize {
+        unsafe { JS_GetMemoryUsed(self.ctx.as_ptr()) }
+    }
+
+    fn install_quota(&self) {
+        let quota = self.state.quota.borrow();
+        let Some(quota) = quota.as_ref() else {
+            return;
+        };
+        unsafe {
+            JS_SetMemoryQuota(self.ctx.as_ptr(), quota.limit_bytes);
+            JS_SetMemoryThreshold(self.ctx.as_ptr(), quota.armed_bytes());
+            JS_SetMemoryHandler(self.ctx.as_ptr(), Some(memory_handler));
+        }
+    }
+
+    /// Install a handler polled while scripts run; returning true interrupts them.
+    pub(crate) fn set_interrupt_handler<F>(&self, handler: F)
+    where
+        F: FnMut() -> bool + Send + 'static,
+    {
+        *self.state.interrupt.borrow_mut() = Some(Box::new(handler));
+        unsafe {
+            JS_SetInterruptHandler(self.ctx.as_ptr(), Some(interrupt_handler));
+        }
+    }
+
+    pub(crate) fn eval_raw(&self, script: &str, filename: &str) -> Result<JSValue, JsError> {
         let script = CString::new(script).map_err(|_| JsError::Runtime {
             message: "script contains null byte".to_string(),
         })?;
@@ -140,15 +732,46 @@ impl Context {
     }
 }
 
+impl core::fmt::Debug for Context {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Context")
+            .field("ctx", &self.ctx)
+            .field("heap_bytes", &self.buffer_bytes())
+            .finish()
+    }
+}
+
 impl Drop for Context {
     fn drop(&mut self) {
-        unregister_context(self.ctx.as_ptr());
         unsafe {
             JS_FreeContext(self.ctx.as_ptr());
         }
     }
 }
 
+/// Seed for a context cloned from a snapshot, so clones do not share a
+/// `Math.random` sequence.
+#[cfg(feature = "std")]
+fn random_seed(salt: usize) -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+    RandomState::new().hash_one(salt)
+}
+
+/// Without `std` there is no entropy source; mix the heap address instead.
+#[cfg(not(feature = "std"))]
+fn random_seed(salt: usize) -> u64 {
+    (salt as u64 ^ 0x9e37_79b9_7f4a_7c15).wrapping_mul(0xbf58_476d_1ce4_e5b9)
+}
+
+fn check_memory_size(memory_bytes: usize) -> Result<(), JsError> {
+    if memory_bytes < 1024 {
+        return Err(JsError::ContextInit {
+            message: "memory buffer must be at least 1024 bytes".to_string(),
+        });
+    }
+    Ok(())
+}
+
 fn js_exception_value() -> JSValue {
     (JS_TAG_EXCEPTION as JSValue) | ((JS_EX_NORMAL as JSValue) << JS_TAG_SPECIAL_BITS)
 }
@@ -171,7 +794,15 @@ fn error_details(ctx: *mut JSContext) -> (String, Option<String>) {
 }
 
 pub(crate) fn exception_error(ctx: *mut JSContext) -> JsError {
+    // Read first: formatting the message may allocate and throw again.
+    let out_of_memory = unsafe { JS_IsOutOfMemoryException(ctx) } != 0;
     let (message, stack) = error_details(ctx);
+    if out_of_memory {
+        return JsError::OutOfMemory {
+            requested: unsafe { JS_GetOutOfMemorySize(ctx) },
+            heap_size: unsafe { JS_GetMemorySize(ctx) },
+        };
+    }
     JsError::Exception { message, stack }
 }
 
@@ -1,16 +1,26 @@
-use std::collections::{HashMap, VecDeque};
-use std::ffi::{c_char, CString};
-use std::ptr::NonNull;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+use alloc::ffi::CString;
+use core::ffi::c_char;
+use core::ptr::NonNull;
 
 use mquickjs_sys::{
-    JSCStringBuf, JSContext, JSValue, JS_Call, JS_EX_NORMAL, JS_GetGlobalObject,
+    JSCStringBuf, JSContext, JSValue, JS_Call, JS_EX_NORMAL, JS_GetClassID, JS_GetGlobalObject,
     JS_GetPropertyStr, JS_GetPropertyUint32, JS_IsNumber, JS_NewArray,
-    JS_NewFloat64, JS_NewInt32, JS_NewInt64, JS_NewObject, JS_NewStringLen,
-    JS_NewUint32, JS_PushArg, JS_SetPropertyStr, JS_SetPropertyUint32,
+    JS_NewFloat64, JS_NewInt32, JS_NewInt64, JS_NewStringLen,
+    JS_NewUint32, JS_PushArg, JS_SetPropertyUint32,
     JS_StackCheck, JS_TAG_BOOL, JS_TAG_EXCEPTION, JS_TAG_NULL, JS_TAG_SPECIAL_BITS,
     JS_TAG_UNDEFINED, JS_ToCStringLen, JS_ToNumber, JS_ToString, JS_ToUint32,
 };
+#[cfg(feature = "std")]
+use mquickjs_sys::{JS_NewObject, JS_SetPropertyStr};
 
+#[cfg(feature = "std")]
+use crate::collection::map_entries;
 use crate::{Context, JsError, Value};
 
 const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
@@ -35,6 +45,18 @@ impl<T> Coerced<T> {
     }
 }
 
+impl<'ctx> IntoValue<'ctx> for Value<'ctx> {
+    fn into_value(self, _ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        Ok(self)
+    }
+}
+
+impl<'ctx> FromValue<'ctx> for Value<'ctx> {
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        Ok(value)
+    }
+}
+
 impl<'ctx> IntoValue<'ctx> for bool {
     fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
         let raw = (self as JSValue) << JS_TAG_SPECIAL_BITS | (JS_TAG_BOOL as JSValue);
@@ -52,9 +74,7 @@ impl<'ctx> IntoValue<'ctx> for i32 {
     fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
         let raw = unsafe { JS_NewInt32(ctx.raw_ctx().as_ptr(), self) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to convert i32".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert i32"));
         }
         Ok(Value::new(ctx.raw_ctx(), raw))
     }
@@ -70,9 +90,7 @@ impl<'ctx> IntoValue<'ctx> for i64 {
     fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
         let raw = unsafe { JS_NewInt64(ctx.raw_ctx().as_ptr(), self) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to convert i64".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert i64"));
         }
         Ok(Value::new(ctx.raw_ctx(), raw))
     }
@@ -83,9 +101,7 @@ impl<'ctx> FromValue<'ctx> for i64 {
         let number = value.to_f64()?;
         let number = ensure_integer(number, "i64")?;
         if number.abs() > MAX_SAFE_INTEGER {
-            return Err(JsError::Conversion {
-                message: "i64 out of safe JS integer range".to_string(),
-            });
+            return Err(JsError::conversion("i64 out of safe JS integer range"));
         }
         Ok(number as i64)
     }
@@ -98,15 +114,11 @@ impl<'ctx> IntoValue<'ctx> for u64 {
         } else if self as f64 <= MAX_SAFE_INTEGER {
             unsafe { JS_NewFloat64(ctx.raw_ctx().as_ptr(), self as f64) }
         } else {
-            return Err(JsError::Conversion {
-                message: "u64 out of safe JS integer range".to_string(),
-            });
+            return Err(JsError::conversion("u64 out of safe JS integer range"));
         };
 
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to convert u64".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert u64"));
         }
         Ok(Value::new(ctx.raw_ctx(), raw))
     }
@@ -116,10 +128,8 @@ impl<'ctx> FromValue<'ctx> for u64 {
     fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
         let number = value.to_f64()?;
         let number = ensure_integer(number, "u64")?;
-        if number < 0.0 || number > MAX_SAFE_INTEGER {
-            return Err(JsError::Conversion {
-                message: "u64 out of safe JS integer range".to_string(),
-            });
+        if !(0.0..=MAX_SAFE_INTEGER).contains(&number) {
+            return Err(JsError::conversion("u64 out of safe JS integer range"));
         }
         Ok(number as u64)
     }
@@ -134,9 +144,7 @@ impl<'ctx> IntoValue<'ctx> for usize {
 impl<'ctx> FromValue<'ctx> for usize {
     fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
         let number = u64::from_value(value)?;
-        usize::try_from(number).map_err(|_| JsError::Conversion {
-            message: "usize out of range".to_string(),
-        })
+        usize::try_from(number).map_err(|_| JsError::conversion("usize out of range"))
     }
 }
 
@@ -144,9 +152,7 @@ impl<'ctx> IntoValue<'ctx> for f64 {
     fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
         let raw = unsafe { JS_NewFloat64(ctx.raw_ctx().as_ptr(), self) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to convert f64".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert f64"));
         }
         Ok(Value::new(ctx.raw_ctx(), raw))
     }
@@ -163,9 +169,7 @@ impl<'ctx> FromValue<'ctx> for Coerced<i32> {
         let number = coerce_number(value)?;
         let number = ensure_integer(number, "i32")?;
         if number < i32::MIN as f64 || number > i32::MAX as f64 {
-            return Err(JsError::Conversion {
-                message: "i32 out of range".to_string(),
-            });
+            return Err(JsError::conversion("i32 out of range"));
         }
         Ok(Coerced(number as i32))
     }
@@ -176,9 +180,7 @@ impl<'ctx> FromValue<'ctx> for Coerced<i64> {
         let number = coerce_number(value)?;
         let number = ensure_integer(number, "i64")?;
         if number.abs() > MAX_SAFE_INTEGER {
-            return Err(JsError::Conversion {
-                message: "i64 out of safe JS integer range".to_string(),
-            });
+            return Err(JsError::conversion("i64 out of safe JS integer range"));
         }
         Ok(Coerced(number as i64))
     }
@@ -188,10 +190,8 @@ impl<'ctx> FromValue<'ctx> for Coerced<u64> {
     fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
         let number = coerce_number(value)?;
         let number = ensure_integer(number, "u64")?;
-        if number < 0.0 || number > MAX_SAFE_INTEGER {
-            return Err(JsError::Conversion {
-                message: "u64 out of safe JS integer range".to_string(),
-            });
+        if !(0.0..=MAX_SAFE_INTEGER).contains(&number) {
+            return Err(JsError::conversion("u64 out of safe JS integer range"));
         }
         Ok(Coerced(number as u64))
     }
@@ -200,9 +200,8 @@ impl<'ctx> FromValue<'ctx> for Coerced<u64> {
 impl<'ctx> FromValue<'ctx> for Coerced<usize> {
     fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
         let number = Coerced::<u64>::from_value(value)?.0;
-        let converted = usize::try_from(number).map_err(|_| JsError::Conversion {
-            message: "usize out of range".to_string(),
-        })?;
+        let converted =
+            usize::try_from(number).map_err(|_| JsError::conversion("usize out of range"))?;
         Ok(Coerced(converted))
     }
 }
@@ -219,9 +218,7 @@ impl<'ctx> FromValue<'ctx> for Coerced<String> {
         let ctx = value.ctx();
         let string_value = unsafe { JS_ToString(ctx.as_ptr(), value.raw()) };
         if is_exception(string_value) {
-            return Err(JsError::Conversion {
-                message: "failed to coerce string".to_string(),
-            });
+            return Err(JsError::conversion("failed to coerce string"));
         }
         Ok(Coerced(string_from_js(ctx.as_ptr(), string_value)?))
     }
@@ -244,9 +241,7 @@ impl<'ctx> IntoValue<'ctx> for &str {
             )
         };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to convert string".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert string"));
         }
         Ok(Value::new(ctx.raw_ctx(), raw))
     }
@@ -290,9 +285,7 @@ where
         let raw_ctx = ctx.raw_ctx();
         let raw = unsafe { JS_NewArray(raw_ctx.as_ptr(), self.len() as i32) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to create array".to_string(),
-            });
+            return Err(JsError::conversion("failed to create array"));
         }
 
         for (index, item) in self.into_iter().enumerate() {
@@ -301,9 +294,7 @@ where
                 JS_SetPropertyUint32(raw_ctx.as_ptr(), raw, index as u32, value.raw())
             };
             if is_exception(result) {
-                return Err(JsError::Conversion {
-                    message: format!("failed to set array element {index}"),
-                });
+                return Err(JsError::conversion(format!("failed to set array element {index}")));
             }
         }
 
@@ -323,12 +314,10 @@ where
         for index in 0..length {
             let elem_raw = unsafe { JS_GetPropertyUint32(raw_ctx.as_ptr(), value.raw(), index) };
             if is_exception(elem_raw) {
-                return Err(JsError::Conversion {
-                    message: format!("failed to read array element {index}"),
-                });
+                return Err(JsError::conversion(format!("failed to read array element {index}")));
             }
             let elem = Value::new(raw_ctx, elem_raw);
-            out.push(T::from_value(elem)?);
+            out.push(T::from_value(elem).map_err(|err| err.at_index(index as usize))?);
         }
 
         Ok(out)
@@ -355,6 +344,9 @@ where
     }
 }
 
+/// Converts into a plain object; wrap the map in [`AsMap`](crate::AsMap) to
+/// get a `Map`.
+#[cfg(feature = "std")]
 impl<'ctx, T> IntoValue<'ctx> for HashMap<String, T>
 where
     T: IntoValue<'ctx>,
@@ -363,23 +355,18 @@ where
         let raw_ctx = ctx.raw_ctx();
         let raw = unsafe { JS_NewObject(raw_ctx.as_ptr()) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to create object".to_string(),
-            });
+            return Err(JsError::conversion("failed to create object"));
         }
 
         for (key, value) in self {
-            let name = CString::new(key).map_err(|_| JsError::Conversion {
-                message: "object key contains null byte".to_string(),
-            })?;
+            let name = CString::new(key)
+                .map_err(|_| JsError::conversion("object key contains null byte"))?;
             let value = value.into_value(ctx)?;
             let result = unsafe {
                 JS_SetPropertyStr(raw_ctx.as_ptr(), raw, name.as_ptr(), value.raw())
             };
             if is_exception(result) {
-                return Err(JsError::Conversion {
-                    message: "failed to set object property".to_string(),
-                });
+                return Err(JsError::conversion("failed to set object property"));
             }
         }
 
@@ -387,29 +374,31 @@ where
     }
 }
 
+/// Converts from a plain object or a `Map` with string keys.
+#[cfg(feature = "std")]
 impl<'ctx, T> FromValue<'ctx> for HashMap<String, T>
 where
     T: FromValue<'ctx>,
 {
     fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        if let Some(entries) = map_entries(value) {
+            return entries.map(|entries| entries.into_iter().collect());
+        }
         let raw_ctx = value.ctx();
         let keys = object_keys(raw_ctx, value)?;
         let mut out = HashMap::with_capacity(keys.len());
 
         for key in keys {
-            let name = CString::new(key.clone()).map_err(|_| JsError::Conversion {
-                message: "object key contains null byte".to_string(),
-            })?;
+            let name = CString::new(key.clone())
+                .map_err(|_| JsError::conversion("object key contains null byte"))?;
             let raw = unsafe {
                 JS_GetPropertyStr(raw_ctx.as_ptr(), value.raw(), name.as_ptr())
             };
             if is_exception(raw) {
-                return Err(JsError::Conversion {
-                    message: format!("failed to read property '{key}'"),
-                });
+                return Err(JsError::conversion(format!("failed to read property '{key}'")));
             }
             let item = Value::new(raw_ctx, raw);
-            let converted = T::from_value(item)?;
+            let converted = T::from_value(item).map_err(|err| err.at_key(key.as_str()))?;
             out.insert(key, converted);
         }
 
@@ -426,9 +415,7 @@ where
         let raw_ctx = ctx.raw_ctx();
         let raw = unsafe { JS_NewArray(raw_ctx.as_ptr(), 2) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to create tuple array".to_string(),
-            });
+            return Err(JsError::conversion("failed to create tuple array"));
         }
 
         let first = self.0.into_value(ctx)?;
@@ -436,16 +423,12 @@ where
 
         let result = unsafe { JS_SetPropertyUint32(raw_ctx.as_ptr(), raw, 0, first.raw()) };
         if is_exception(result) {
-            return Err(JsError::Conversion {
-                message: "failed to set tuple element 0".to_string(),
-            });
+            return Err(JsError::conversion("failed to set tuple element 0"));
         }
 
         let result = unsafe { JS_SetPropertyUint32(raw_ctx.as_ptr(), raw, 1, second.raw()) };
         if is_exception(result) {
-            return Err(JsError::Conversion {
-                message: "failed to set tuple element 1".to_string(),
-            });
+            return Err(JsError::conversion("failed to set tuple element 1"));
         }
 
         Ok(Value::new(raw_ctx, raw))
@@ -461,31 +444,27 @@ where
         let raw_ctx = value.ctx();
         let length = array_length(raw_ctx, value.raw())?;
         if length != 2 {
-            return Err(JsError::Conversion {
-                message: "expected array of length 2".to_string(),
-            });
+            return Err(JsError::conversion("expected array of length 2"));
         }
 
         let first_raw = unsafe { JS_GetPropertyUint32(raw_ctx.as_ptr(), value.raw(), 0) };
         if is_exception(first_raw) {
-            return Err(JsError::Conversion {
-                message: "failed to read tuple element 0".to_string(),
-            });
+            return Err(JsError::conversion("failed to read tuple element 0"));
         }
         let second_raw = unsafe { JS_GetPropertyUint32(raw_ctx.as_ptr(), value.raw(), 1) };
         if is_exception(second_raw) {
-            return Err(JsError::Conversion {
-                message: "failed to read tuple element 1".to_string(),
-            });
+            return Err(JsError::conversion("failed to read tuple element 1"));
         }
 
-        let first = A::from_value(Value::new(raw_ctx, first_raw))?;
-        let second = B::from_value(Value::new(raw_ctx, second_raw))?;
+        let first =
+            A::from_value(Value::new(raw_ctx, first_raw)).map_err(|err| err.at_index(0))?;
+        let second =
+            B::from_value(Value::new(raw_ctx, second_raw)).map_err(|err| err.at_index(1))?;
         Ok((first, second))
     }
 }
 
-fn is_exception(value: JSValue) -> bool {
+pub(crate) fn is_exception(value: JSValue) -> bool {
     value == js_exception_value()
 }
 
@@ -497,52 +476,66 @@ fn js_special_value(tag: u32, payload: u64) -> JSValue {
     ((payload << JS_TAG_SPECIAL_BITS) as JSValue) | (tag as JSValue)
 }
 
-fn js_null_value() -> JSValue {
-    js_special_value(JS_TAG_NULL as u32, 0)
+pub(crate) fn js_null_value() -> JSValue {
+    js_special_value(JS_TAG_NULL, 0)
+}
+
+pub(crate) fn boolean(value: bool) -> JSValue {
+    ((value as JSValue) << JS_TAG_SPECIAL_BITS) | JS_TAG_BOOL as JSValue
+}
+
+pub(crate) fn undefined() -> JSValue {
+    JS_TAG_UNDEFINED as JSValue
+}
+
+pub(crate) fn exception() -> JSValue {
+    JS_TAG_EXCEPTION as JSValue
 }
 
 fn is_null_or_undefined(value: JSValue) -> bool {
     let tag = value_tag(value);
-    tag == JS_TAG_NULL as u32 || tag == JS_TAG_UNDEFINED as u32
+    tag == JS_TAG_NULL || tag == JS_TAG_UNDEFINED
 }
 
-fn value_tag(value: JSValue) -> u32 {
+pub(crate) fn value_tag(value: JSValue) -> u32 {
     let mask = (1u64 << JS_TAG_SPECIAL_BITS) - 1;
-    (value as u64 & mask) as u32
+    (value & mask) as u32
 }
 
-fn array_length(raw_ctx: NonNull<JSContext>, value: JSValue) -> Result<u32, JsError> {
+pub(crate) fn array_length(raw_ctx: NonNull<JSContext>, value: JSValue) -> Result<u32, JsError> {
+    if unsafe { JS_GetClassID(raw_ctx.as_ptr(), value) } < 0 {
+        return Err(JsError::type_mismatch(
+            "expected array",
+            Value::new(raw_ctx, value).type_name(),
+        ));
+    }
+
     let length_name = CString::new("length").expect("length contains no nulls");
     let length_raw = unsafe { JS_GetPropertyStr(raw_ctx.as_ptr(), value, length_name.as_ptr()) };
     if is_exception(length_raw) {
-        return Err(JsError::Conversion {
-            message: "failed to read array length".to_string(),
-        });
+        return Err(JsError::conversion("failed to read array length"));
     }
 
     let is_number = unsafe { JS_IsNumber(raw_ctx.as_ptr(), length_raw) };
     if is_number == 0 {
-        return Err(JsError::Conversion {
-            message: "expected array".to_string(),
-        });
+        return Err(JsError::type_mismatch(
+            "expected array",
+            Value::new(raw_ctx, value).type_name(),
+        ));
     }
 
     let mut length = 0u32;
     let status = unsafe { JS_ToUint32(raw_ctx.as_ptr(), &mut length, length_raw) };
     if status != 0 {
-        return Err(JsError::Conversion {
-            message: "failed to convert array length".to_string(),
-        });
+        return Err(JsError::conversion("failed to convert array length"));
     }
 
     Ok(length)
 }
 
 fn ensure_integer(value: f64, name: &str) -> Result<f64, JsError> {
-    if !value.is_finite() || value.fract() != 0.0 {
-        return Err(JsError::Conversion {
-            message: format!("{name} expected integer"),
-        });
+    if !value.is_finite() || value % 1.0 != 0.0 {
+        return Err(JsError::conversion(format!("{name} expected integer")));
     }
     Ok(value)
 }
@@ -552,9 +545,7 @@ fn coerce_number(value: Value<'_>) -> Result<f64, JsError> {
     let mut out = 0f64;
     let status = unsafe { JS_ToNumber(raw_ctx.as_ptr(), &mut out, value.raw()) };
     if status != 0 {
-        return Err(JsError::Conversion {
-            message: "failed to coerce number".to_string(),
-        });
+        return Err(JsError::conversion("failed to coerce number"));
     }
     Ok(out)
 }
@@ -564,26 +555,20 @@ fn string_from_js(ctx: *mut JSContext, value: JSValue) -> Result<String, JsError
     let mut len = 0usize;
     let ptr = unsafe { JS_ToCStringLen(ctx, &mut len, value, &mut buf) };
     if ptr.is_null() {
-        return Err(JsError::Conversion {
-            message: "failed to convert to string".to_string(),
-        });
+        return Err(JsError::conversion("failed to convert to string"));
     }
-    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+    let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, len) };
     Ok(String::from_utf8_lossy(bytes).into_owned())
 }
 
-fn object_keys(raw_ctx: NonNull<JSContext>, value: Value<'_>) -> Result<Vec<String>, JsError> {
+pub(crate) fn object_keys(raw_ctx: NonNull<JSContext>, value: Value<'_>) -> Result<Vec<String>, JsError> {
     if unsafe { JS_StackCheck(raw_ctx.as_ptr(), 3) } != 0 {
-        return Err(JsError::Conversion {
-            message: "stack overflow when reading object keys".to_string(),
-        });
+        return Err(JsError::conversion("stack overflow when reading object keys"));
     }
 
     let global = unsafe { JS_GetGlobalObject(raw_ctx.as_ptr()) };
     if is_exception(global) {
-        return Err(JsError::Conversion {
-            message: "failed to read global object".to_string(),
-        });
+        return Err(JsError::conversion("failed to read global object"));
     }
 
     let object_name = CString::new("Object").expect("Object contains no nulls");
@@ -591,16 +576,12 @@ fn object_keys(raw_ctx: NonNull<JSContext>, value: Value<'_>) -> Result<Vec<Stri
 
     let object_ctor = unsafe { JS_GetPropertyStr(raw_ctx.as_ptr(), global, object_name.as_ptr()) };
     if is_exception(object_ctor) {
-        return Err(JsError::Conversion {
-            message: "failed to read Object constructor".to_string(),
-        });
+        return Err(JsError::conversion("failed to read Object constructor"));
     }
 
     let keys_fn = unsafe { JS_GetPropertyStr(raw_ctx.as_ptr(), object_ctor, keys_name.as_ptr()) };
     if is_exception(keys_fn) {
-        return Err(JsError::Conversion {
-            message: "failed to read Object.keys".to_string(),
-        });
+        return Err(JsError::conversion("failed to read Object.keys"));
     }
 
     unsafe {
@@ -611,9 +592,7 @@ fn object_keys(raw_ctx: NonNull<JSContext>, value: Value<'_>) -> Result<Vec<Stri
 
     let keys_raw = unsafe { JS_Call(raw_ctx.as_ptr(), 1) };
     if is_exception(keys_raw) {
-        return Err(JsError::Conversion {
-            message: "failed to call Object.keys".to_string(),
-        });
+        return Err(JsError::conversion("failed to call Object.keys"));
     }
 
     let keys_value = Value::new(raw_ctx, keys_raw);
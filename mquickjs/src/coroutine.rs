@@ -0,0 +1,185 @@
+//! Scripts run on a separate stack so that suspending host functions can
+//! wait for Rust futures, see [`Context::eval_async`].
+//!
+//! The interpreter keeps its frames in the context heap, so a suspended
+//! script is only a native stack waiting in the host callback. Resuming
+//! returns the future's outcome from the callback and the script goes on.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{self, Poll};
+
+use corosensei::stack::DefaultStack;
+use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
+use mquickjs_sys::JSValue;
+
+use crate::data::JsData;
+use crate::func::{AsyncCallback, catch_panic};
+use crate::state::ContextState;
+use crate::task::HostFuture;
+use crate::{Context, FromValue, JsError, Value};
+
+type Outcome = Result<JsData, JsError>;
+
+/// Whether a script of the context runs on a coroutine.
+#[derive(Clone, Copy, Default)]
+pub(crate) enum Slot {
+    #[default]
+    Idle,
+    /// The coroutine is running; suspending host functions yield to the host.
+    Running {
+        ctx: *const Context,
+        yielder: *const Yielder<Outcome, HostFuture>,
+    },
+    /// The coroutine waits for a future. Code run by the host meanwhile must
+    /// not yield.
+    Suspended,
+    /// The future driving the coroutine was dropped, so the script is run to
+    /// completion and suspending host functions throw.
+    Cancelled,
+}
+
+/// Copy `args` and suspend the running script until the future returned by
+/// `callback` completes.
+pub(crate) fn suspend(
+    state: &ContextState,
+    callback: &AsyncCallback,
+    args: &[Value<'_>],
+) -> Result<JSValue, JsError> {
+    let Slot::Running {
+        ctx: context,
+        yielder,
+    } = state.coroutine.get()
+    else {
+        return Err(JsError::Runtime {
+            message: match state.coroutine.get() {
+                Slot::Cancelled => "script was cancelled".to_string(),
+                _ => {
+                    "suspending functions must be called from eval_async or call_async".to_string()
+                }
+            },
+        });
+    };
+    let args = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| JsData::from_value(*arg).map_err(|err| err.at_index(index)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let future = callback(args);
+
+    state.coroutine.set(Slot::Suspended);
+    // SAFETY: `Running` is only set while the coroutine runs, which is where
+    // this callback was called from, and the context outlives the coroutine.
+    let (yielder, context) = unsafe { (&*yielder, &*context) };
+    let outcome = yielder.suspend(future);
+    if let Slot::Suspended = state.coroutine.get() {
+        state.coroutine.set(Slot::Running {
+            ctx: context,
+            yielder,
+        });
+    }
+    outcome?.to_value(context).map(|value| value.raw())
+}
+
+/// Future running `body` on a coroutine, returned by
+/// [`Context::eval_async`] and [`Function::call_async`](crate::Function::call_async).
+pub(crate) struct Suspendable<'a, 'ctx> {
+    ctx: &'ctx Context,
+    coroutine: ScopedCoroutine<'a, Outcome, HostFuture, Result<JSValue, JsError>, DefaultStack>,
+    pending: Option<HostFuture>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a, 'ctx: 'a> Suspendable<'a, 'ctx> {
+    pub(crate) fn new<F>(ctx: &'ctx Context, body: F) -> Self
+    where
+        F: FnOnce() -> Result<JSValue, JsError> + 'a,
+    {
+        let slot = &ctx.state().coroutine;
+        let coroutine =
+            ScopedCoroutine::new(move |yielder: &Yielder<Outcome, HostFuture>, _: Outcome| {
+                slot.set(Slot::Running { ctx, yielder });
+                let result = body();
+                slot.set(Slot::Idle);
+                result
+            });
+        Self {
+            ctx,
+            coroutine,
+            pending: None,
+            started: false,
+            done: false,
+        }
+    }
+}
+
+impl<'ctx> Future for Suspendable<'_, 'ctx> {
+    type Output = Result<Value<'ctx>, JsError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(Err(JsError::Runtime {
+                message: "script already finished".to_string(),
+            }));
+        }
+        if !this.started {
+            // Scripts share the interpreter stack, so a second coroutine
+            // would resume on top of frames of the first.
+            if !matches!(this.ctx.state().coroutine.get(), Slot::Idle) {
+                this.done = true;
+                return Poll::Ready(Err(JsError::Runtime {
+                    message: "another script is already running on a coroutine".to_string(),
+                }));
+            }
+            this.started = true;
+        }
+        loop {
+            let outcome = match &mut this.pending {
+                Some(future) => {
+                    let poll = catch_panic(|| future.as_mut().poll(cx)).unwrap_or_else(|| {
+                        Poll::Ready(Err(JsError::Callback {
+                            message: "callback panicked".to_string(),
+                        }))
+                    });
+                    match poll {
+                        Poll::Ready(outcome) => outcome,
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                None => Ok(JsData::Undefined),
+            };
+            this.pending = None;
+            match this.coroutine.resume(outcome) {
+                CoroutineResult::Yield(future) => this.pending = Some(future),
+                CoroutineResult::Return(result) => {
+                    this.done = true;
+                    return Poll::Ready(result.map(|raw| Value::new(this.ctx.raw_ctx(), raw)));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Suspendable<'_, '_> {
+    fn drop(&mut self) {
+        if !self.started || self.done {
+            return;
+        }
+        // Unwinding the coroutine would cross the interpreter's C frames, so
+        // the script runs on with every suspending call failing.
+        let slot = &self.ctx.state().coroutine;
+        slot.set(Slot::Cancelled);
+        self.pending = None;
+        let cancelled = || {
+            Err(JsError::Runtime {
+                message: "script was cancelled".to_string(),
+            })
+        };
+        while let CoroutineResult::Yield(_) = self.coroutine.resume(cancelled()) {}
+        slot.set(Slot::Idle);
+    }
+}
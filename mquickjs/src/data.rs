@@ -0,0 +1,314 @@
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+use mquickjs_sys::{
+    JSContext, JSObjectClassEnum_JS_CLASS_UINT8_ARRAY, JSValue, JS_GetArrayBuffer,
+    JS_GetPropertyStr, JS_GetPropertyUint32, JS_GetTypedArrayBuffer, JS_NewArray,
+    JS_NewArrayBufferCopy, JS_NewObject, JS_NewTypedArray, JS_SetPropertyStr,
+    JS_SetPropertyUint32, JS_TAG_UNDEFINED,
+};
+
+use crate::convert::{array_length, is_exception, js_null_value, object_keys};
+use crate::rooted::RootedValue;
+use crate::{Context, FromValue, IntoValue, JsError, Value, ValueKind};
+
+/// Owned copy of a JavaScript value that does not borrow a `Context`.
+///
+/// Extraction copies the value graph into a tree. `ArrayBuffer`,
+/// `Uint8Array` and `Uint8ClampedArray` become `Bytes`, and other typed arrays
+/// become arrays of numbers. `Bytes` is injected back as a `Uint8Array`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsData {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsData>),
+    /// Own enumerable properties in enumeration order.
+    Object(Vec<(String, JsData)>),
+    Bytes(Vec<u8>),
+}
+
+/// Bounds applied while extracting a `JsData` tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLimits {
+    /// Maximum number of nested arrays and objects.
+    pub max_depth: usize,
+    /// Maximum number of values in the extracted tree.
+    pub max_nodes: usize,
+}
+
+impl Default for DataLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_nodes: 1_000_000,
+        }
+    }
+}
+
+impl JsData {
+    /// Extract an owned tree from `value` using the given limits.
+    ///
+    /// Cyclic values, functions and symbols are rejected with a conversion error.
+    pub fn from_value_with_limits(value: Value<'_>, limits: DataLimits) -> Result<Self, JsError> {
+        let mut extractor = Extractor {
+            ctx: value.ctx(),
+            limits,
+            nodes: 0,
+            ancestors: Vec::new(),
+        };
+        extractor.extract(value)
+    }
+
+    /// Create a JavaScript value from this tree in `ctx`.
+    pub fn to_value<'ctx>(&self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        let raw_ctx = ctx.raw_ctx();
+        match self {
+            JsData::Undefined => Ok(Value::new(raw_ctx, JS_TAG_UNDEFINED as JSValue)),
+            JsData::Null => Ok(Value::new(raw_ctx, js_null_value())),
+            JsData::Bool(value) => value.into_value(ctx),
+            JsData::Number(value) => value.into_value(ctx),
+            JsData::String(value) => value.as_str().into_value(ctx),
+            JsData::Array(items) => {
+                let raw = unsafe { JS_NewArray(raw_ctx.as_ptr(), items.len() as i32) };
+                if is_exception(raw) {
+                    return Err(JsError::conversion("failed to create array"));
+                }
+                let array = ctx.root(Value::new(raw_ctx, raw));
+                for (index, item) in items.iter().enumerate() {
+                    let value = item.to_value(ctx)?;
+                    let result = unsafe {
+                        JS_SetPropertyUint32(
+                            raw_ctx.as_ptr(),
+                            array.to_value().raw(),
+                            index as u32,
+                            value.raw(),
+                        )
+                    };
+                    if is_exception(result) {
+                        return Err(JsError::conversion(format!(
+                            "failed to set array element {index}"
+                        )));
+                    }
+                }
+                Ok(array.to_value())
+            }
+            JsData::Object(entries) => {
+                let raw = unsafe { JS_NewObject(raw_ctx.as_ptr()) };
+                if is_exception(raw) {
+                    return Err(JsError::conversion("failed to create object"));
+                }
+                let object = ctx.root(Value::new(raw_ctx, raw));
+                for (key, item) in entries {
+                    let name = CString::new(key.as_str())
+                        .map_err(|_| JsError::conversion("property name contains null byte"))?;
+                    let value = item.to_value(ctx)?;
+                    let result = unsafe {
+                        JS_SetPropertyStr(
+                            raw_ctx.as_ptr(),
+                            object.to_value().raw(),
+                            name.as_ptr(),
+                            value.raw(),
+                        )
+                    };
+                    if is_exception(result) {
+                        return Err(JsError::conversion(format!("failed to set property '{key}'")));
+                    }
+                }
+                Ok(object.to_value())
+            }
+            JsData::Bytes(bytes) => {
+                let buffer = unsafe {
+                    JS_NewArrayBufferCopy(raw_ctx.as_ptr(), bytes.as_ptr(), bytes.len())
+                };
+                if is_exception(buffer) {
+                    return Err(JsError::conversion("failed to create ArrayBuffer"));
+                }
+                let raw = unsafe {
+                    JS_NewTypedArray(
+                        raw_ctx.as_ptr(),
+                        buffer,
+                        JSObjectClassEnum_JS_CLASS_UINT8_ARRAY as i32,
+                        0,
+                        bytes.len(),
+                    )
+                };
+                if is_exception(raw) {
+                    return Err(JsError::conversion("failed to create Uint8Array"));
+                }
+                Ok(Value::new(raw_ctx, raw))
+            }
+        }
+    }
+
+    /// Look up property `key` of an `Object` tree.
+    pub fn get(&self, key: &str) -> Option<&JsData> {
+        match self {
+            JsData::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl<'ctx> FromValue<'ctx> for JsData {
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        JsData::from_value_with_limits(value, DataLimits::default())
+    }
+}
+
+impl<'ctx> IntoValue<'ctx> for JsData {
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        self.to_value(ctx)
+    }
+}
+
+impl<'ctx> IntoValue<'ctx> for &JsData {
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        self.to_value(ctx)
+    }
+}
+
+struct Extractor<'ctx> {
+    ctx: NonNull<JSContext>,
+    limits: DataLimits,
+    nodes: usize,
+    // Containers on the current path, rooted so identity survives a moving GC.
+    ancestors: Vec<RootedValue<'ctx>>,
+}
+
+impl<'ctx> Extractor<'ctx> {
+    fn extract(&mut self, value: Value<'ctx>) -> Result<JsData, JsError> {
+        self.nodes += 1;
+        if self.nodes > self.limits.max_nodes {
+            return Err(JsError::conversion(format!(
+                "value exceeds {} nodes",
+                self.limits.max_nodes
+            )));
+        }
+
+        let kind = value.kind();
+        match kind {
+            ValueKind::Undefined => Ok(JsData::Undefined),
+            ValueKind::Null => Ok(JsData::Null),
+            ValueKind::Bool => Ok(JsData::Bool(value.to_bool()?)),
+            ValueKind::Int | ValueKind::Float => Ok(JsData::Number(value.to_f64()?)),
+            ValueKind::String => Ok(JsData::String(value.to_string()?)),
+            ValueKind::Function | ValueKind::Symbol | ValueKind::Internal => Err(JsError::type_mismatch(
+                format!("cannot extract {}", kind.as_str()),
+                kind.as_str(),
+            )),
+            _ => self.extract_container(value, kind),
+        }
+    }
+
+    fn extract_container(&mut self, value: Value<'ctx>, kind: ValueKind) -> Result<JsData, JsError> {
+        if self.ancestors.len() >= self.limits.max_depth {
+            return Err(JsError::conversion(format!(
+                "value exceeds depth {}",
+                self.limits.max_depth
+            )));
+        }
+        if self
+            .ancestors
+            .iter()
+            .any(|ancestor| ancestor.to_value().raw() == value.raw())
+        {
+            return Err(JsError::conversion("cyclic value"));
+        }
+
+        self.ancestors.push(RootedValue::new(self.ctx, value));
+        let result = match kind {
+            ValueKind::ArrayBuffer => self.buffer_bytes(value).map(JsData::Bytes),
+            ValueKind::Uint8Array | ValueKind::Uint8ClampedArray => {
+                self.typed_array_bytes(value).map(JsData::Bytes)
+            }
+            ValueKind::Array => self.extract_elements().map(JsData::Array),
+            kind if kind.is_typed_array() => self.extract_elements().map(JsData::Array),
+            _ => self.extract_properties().map(JsData::Object),
+        };
+        self.ancestors.pop();
+        result
+    }
+
+    fn current(&self) -> JSValue {
+        self.ancestors
+            .last()
+            .expect("container is on the ancestor stack")
+            .to_value()
+            .raw()
+    }
+
+    fn extract_elements(&mut self) -> Result<Vec<JsData>, JsError> {
+        let length = array_length(self.ctx, self.current())?;
+        let mut items = Vec::with_capacity(length as usize);
+        for index in 0..length {
+            let raw = unsafe { JS_GetPropertyUint32(self.ctx.as_ptr(), self.current(), index) };
+            if is_exception(raw) {
+                return Err(JsError::conversion(format!("failed to read array element {index}")));
+            }
+            let item = self
+                .extract(Value::new(self.ctx, raw))
+                .map_err(|err| err.at_index(index as usize))?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    fn extract_properties(&mut self) -> Result<Vec<(String, JsData)>, JsError> {
+        let keys = object_keys(self.ctx, Value::new(self.ctx, self.current()))?;
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let name = CString::new(key.as_str())
+                .map_err(|_| JsError::conversion("property name contains null byte"))?;
+            let raw = unsafe { JS_GetPropertyStr(self.ctx.as_ptr(), self.current(), name.as_ptr()) };
+            if is_exception(raw) {
+                return Err(JsError::conversion(format!("failed to get property '{key}'")));
+            }
+            let item = self
+                .extract(Value::new(self.ctx, raw))
+                .map_err(|err| err.at_key(key.as_str()))?;
+            entries.push((key, item));
+        }
+        Ok(entries)
+    }
+
+    fn buffer_bytes(&self, value: Value<'_>) -> Result<Vec<u8>, JsError> {
+        let mut size = 0usize;
+        let ptr = unsafe { JS_GetArrayBuffer(self.ctx.as_ptr(), &mut size, value.raw()) };
+        if ptr.is_null() {
+            return Err(JsError::conversion("failed to read ArrayBuffer"));
+        }
+        Ok(unsafe { core::slice::from_raw_parts(ptr, size) }.to_vec())
+    }
+
+    fn typed_array_bytes(&self, value: Value<'_>) -> Result<Vec<u8>, JsError> {
+        let mut offset = 0usize;
+        let mut length = 0usize;
+        let mut element_size = 0usize;
+        let buffer = unsafe {
+            JS_GetTypedArrayBuffer(
+                self.ctx.as_ptr(),
+                value.raw(),
+                &mut offset,
+                &mut length,
+                &mut element_size,
+            )
+        };
+        if is_exception(buffer) {
+            return Err(JsError::conversion("failed to read typed array"));
+        }
+        let bytes = self.buffer_bytes(Value::new(self.ctx, buffer))?;
+        bytes
+            .get(offset..offset + length)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| JsError::conversion("typed array is out of bounds"))
+    }
+}
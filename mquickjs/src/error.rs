@@ -1,3 +1,7 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
 /// Errors returned by the mquickjs safe wrapper.
 #[derive(Debug)]
 pub enum JsError {
@@ -11,13 +15,82 @@ pub enum JsError {
         stack: Option<String>,
     },
     /// Value conversion failures.
-    Conversion { message: String },
+    Conversion {
+        message: String,
+        /// Location of the failing element, outermost segment first.
+        path: Vec<PathSegment>,
+        /// JavaScript type of the value that could not be converted.
+        found: Option<String>,
+    },
     /// Errors raised by registered Rust callbacks.
     Callback { message: String },
+    /// The script exhausted the context memory. The context stays usable once
+    /// the data it was building is unreachable.
+    OutOfMemory {
+        /// Size of the allocation that failed, in bytes.
+        requested: usize,
+        /// Size of the context memory, in bytes.
+        heap_size: usize,
+    },
+}
+
+/// One step into a nested value, recorded by container conversions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// Array or tuple element.
+    Index(usize),
+    /// Object property.
+    Key(String),
+}
+
+impl JsError {
+    /// Create a conversion error without location information.
+    pub fn conversion(message: impl Into<String>) -> Self {
+        JsError::Conversion {
+            message: message.into(),
+            path: Vec::new(),
+            found: None,
+        }
+    }
+
+    /// Record that the error happened inside array element `index`.
+    pub fn at_index(self, index: usize) -> Self {
+        self.at(PathSegment::Index(index))
+    }
+
+    /// Record that the error happened inside object property `key`.
+    pub fn at_key(self, key: impl Into<String>) -> Self {
+        self.at(PathSegment::Key(key.into()))
+    }
+
+    pub(crate) fn type_mismatch(message: impl Into<String>, found: &str) -> Self {
+        JsError::Conversion {
+            message: message.into(),
+            path: Vec::new(),
+            found: Some(found.to_string()),
+        }
+    }
+
+    fn at(mut self, segment: PathSegment) -> Self {
+        if let JsError::Conversion { path, .. } = &mut self {
+            path.insert(0, segment);
+        }
+        self
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+            PathSegment::Key(key) if is_identifier(key) => write!(f, ".{key}"),
+            PathSegment::Key(key) => write!(f, "[{key:?}]"),
+        }
+    }
 }
 
-impl std::fmt::Display for JsError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             JsError::ContextInit { message } => {
                 write!(f, "context init failed: {message}")
@@ -32,14 +105,46 @@ impl std::fmt::Display for JsError {
                 }
                 Ok(())
             }
-            JsError::Conversion { message } => {
-                write!(f, "conversion error: {message}")
+            JsError::Conversion {
+                message,
+                path,
+                found,
+            } => {
+                write!(f, "conversion error: {message}")?;
+                if !path.is_empty() {
+                    write!(f, " at $")?;
+                    for segment in path {
+                        write!(f, "{segment}")?;
+                    }
+                }
+                if let Some(found) = found {
+                    write!(f, " (found {found})")?;
+                }
+                Ok(())
             }
             JsError::Callback { message } => {
                 write!(f, "callback error: {message}")
             }
+            JsError::OutOfMemory {
+                requested,
+                heap_size,
+            } => {
+                write!(
+                    f,
+                    "out of memory: failed to allocate {requested} bytes in a {heap_size} byte heap"
+                )
+            }
         }
     }
 }
 
-impl std::error::Error for JsError {}
+impl core::error::Error for JsError {}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '$' => {}
+        _ => return false,
+    }
+    chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '$')
+}
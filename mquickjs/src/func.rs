@@ -1,75 +1,90 @@
 //! Function binding utilities.
 
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::ffi::CString;
-use std::os::raw::c_int;
-use std::panic::{catch_unwind, AssertUnwindSafe};
-use std::ptr::NonNull;
-use std::sync::Once;
-
-use mquickjs_sys::{JSContext, JSValue, JS_NewString, JS_SetHostCallback, JS_Throw, JS_ToInt32};
-
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::ffi::CString;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::ptr::NonNull;
+use alloc::sync::Arc;
+use core::future::Future;
+
+use mquickjs_sys::{JSContext, JSValue, JS_NewString, JS_Throw, JS_ToInt32};
+
+#[cfg(feature = "coroutine")]
+use crate::coroutine;
+use crate::data::JsData;
 use crate::error::JsError;
+use crate::state::ContextState;
+use crate::task::{self, HostFuture};
 use crate::value::Value;
 
-type Callback = dyn for<'ctx> Fn(&[Value<'ctx>]) -> Result<Value<'ctx>, JsError>;
+pub(crate) type Callback =
+    dyn for<'ctx> Fn(&[Value<'ctx>]) -> Result<Value<'ctx>, JsError> + Send + Sync;
 
-struct Registry {
-    next_id: u32,
-    callbacks: HashMap<u32, Box<Callback>>,
-}
+pub(crate) type AsyncCallback = dyn Fn(Vec<JsData>) -> HostFuture + Send + Sync;
 
-thread_local! {
-    static REGISTRY: RefCell<HashMap<*mut JSContext, Registry>> =
-        RefCell::new(HashMap::new());
+/// A registered host function.
+#[derive(Clone)]
+pub(crate) enum HostFn {
+    Sync(Arc<Callback>),
+    /// Returns a `Promise` settled when the future completes.
+    Async(Arc<AsyncCallback>),
+    /// Suspends the calling coroutine until the future completes.
+    #[cfg(feature = "coroutine")]
+    Suspend(Arc<AsyncCallback>),
 }
 
-static HOST_CALLBACK_INIT: Once = Once::new();
-
-pub(crate) fn register_context(ctx: NonNull<JSContext>) {
-    ensure_host_callback();
-    REGISTRY.with(|registry| {
-        registry
-            .borrow_mut()
-            .entry(ctx.as_ptr())
-            .or_insert_with(|| Registry {
-                next_id: 1,
-                callbacks: HashMap::new(),
-            });
-    });
+/// Box the futures returned by `func`.
+pub(crate) fn async_callback<F, Fut>(func: F) -> Arc<AsyncCallback>
+where
+    F: Fn(Vec<JsData>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<JsData, JsError>> + Send + 'static,
+{
+    Arc::new(move |args| Box::pin(func(args)) as HostFuture)
 }
 
-pub(crate) fn unregister_context(ctx: *mut JSContext) {
-    REGISTRY.with(|registry| {
-        registry.borrow_mut().remove(&ctx);
-    });
+/// Rust callbacks registered on one context, keyed by the id passed to `load`.
+///
+/// Cloning shares the callbacks, so a snapshot keeps the ids it was taken with.
+#[derive(Clone)]
+pub(crate) struct Registry {
+    next_id: u32,
+    callbacks: BTreeMap<u32, HostFn>,
+    globals: Vec<(String, u32)>,
 }
 
-pub(crate) fn register_callback(
-    ctx: NonNull<JSContext>,
-    callback: Box<Callback>,
-) -> u32 {
-    register_context(ctx);
-    REGISTRY.with(|registry| {
-        let mut registry = registry.borrow_mut();
-        let entry = registry
-            .get_mut(&ctx.as_ptr())
-            .expect("context registry missing");
-        let id = entry.next_id;
-        entry.next_id = entry.next_id.wrapping_add(1);
-        entry.callbacks.insert(id, callback);
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_id: 1,
+            callbacks: BTreeMap::new(),
+            globals: Vec::new(),
+        }
+    }
+
+    /// Store the callback for global `name`, reusing the id if `name` was
+    /// registered before.
+    pub(crate) fn insert(&mut self, name: &str, callback: HostFn) -> u32 {
+        if let Some(&(_, id)) = self.globals.iter().find(|(global, _)| global == name) {
+            self.callbacks.insert(id, callback);
+            return id;
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.callbacks.insert(id, callback);
+        self.globals.push((name.to_string(), id));
         id
-    })
-}
+    }
 
-fn ensure_host_callback() {
-    HOST_CALLBACK_INIT.call_once(|| unsafe {
-        JS_SetHostCallback(Some(host_callback));
-    });
+    /// Global names and ids of the registered callbacks, in registration order.
+    pub(crate) fn globals(&self) -> &[(String, u32)] {
+        &self.globals
+    }
 }
 
-unsafe extern "C" fn host_callback(
+pub(crate) unsafe extern "C" fn host_callback(
     ctx_ptr: *mut JSContext,
     _this_val: *mut JSValue,
     argc: c_int,
@@ -79,7 +94,7 @@ unsafe extern "C" fn host_callback(
         return throw_string(ctx_ptr, "missing callback id");
     }
 
-    let args = unsafe { std::slice::from_raw_parts(argv, argc as usize) };
+    let args = unsafe { core::slice::from_raw_parts(argv, argc as usize) };
     let mut id = 0i32;
     if unsafe { JS_ToInt32(ctx_ptr, &mut id, args[0]) } != 0 {
         return throw_string(ctx_ptr, "invalid callback id");
@@ -95,25 +110,42 @@ unsafe extern "C" fn host_callback(
         .map(|raw| Value::new(ctx, *raw))
         .collect();
 
-    let callback = REGISTRY.with(|registry| {
-        let registry = registry.borrow();
-        let entry = registry.get(&ctx.as_ptr())?;
-        let cb = entry.callbacks.get(&(id as u32))?;
-        Some(cb.as_ref() as *const Callback)
+    let state = unsafe { ContextState::from_raw(ctx.as_ptr()) };
+    let callback = state.and_then(|state| {
+        let registry = state.registry.borrow();
+        registry.callbacks.get(&(id as u32)).cloned()
     });
 
-    let Some(callback) = callback else {
+    let (Some(state), Some(callback)) = (state, callback) else {
         return throw_string(ctx.as_ptr(), "unknown callback id");
     };
 
-    let outcome = catch_unwind(AssertUnwindSafe(|| unsafe { (&*callback)(&values) }));
+    let outcome = match callback {
+        HostFn::Sync(callback) => catch_panic(|| callback(&values).map(|value| value.raw())),
+        HostFn::Async(callback) => catch_panic(|| unsafe { task::spawn(ctx, state, &*callback, &values) }),
+        #[cfg(feature = "coroutine")]
+        HostFn::Suspend(callback) => catch_panic(|| coroutine::suspend(state, &*callback, &values)),
+    };
     match outcome {
-        Ok(Ok(value)) => value.raw(),
-        Ok(Err(err)) => throw_string(ctx.as_ptr(), &err.to_string()),
-        Err(_) => throw_string(ctx.as_ptr(), "callback panicked"),
+        Some(Ok(value)) => value,
+        Some(Err(err)) => throw_string(ctx.as_ptr(), &err.to_string()),
+        None => throw_string(ctx.as_ptr(), "callback panicked"),
     }
 }
 
+/// Run `f`, turning a panic into `None`: a panic leaving an `extern "C"`
+/// callback would abort the process.
+#[cfg(feature = "std")]
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> R) -> Option<R> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).ok()
+}
+
+/// Without `std` panics cannot be caught; targets are expected to abort.
+#[cfg(not(feature = "std"))]
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> R) -> Option<R> {
+    Some(f())
+}
+
 fn throw_string(ctx: *mut JSContext, message: &str) -> JSValue {
     let message = CString::new(message).unwrap_or_else(|_| CString::new("error").unwrap());
     unsafe { JS_Throw(ctx, JS_NewString(ctx, message.as_ptr())) }
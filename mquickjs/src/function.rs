@@ -1,9 +1,14 @@
+use alloc::string::ToString;
+#[cfg(feature = "coroutine")]
+use alloc::vec::Vec;
 use mquickjs_sys::{
     JSValue, JS_Call, JS_EX_NORMAL, JS_IsFunction, JS_PushArg, JS_StackCheck,
     JS_TAG_EXCEPTION, JS_TAG_NULL, JS_TAG_SPECIAL_BITS,
 };
 
 use crate::context::exception_error;
+#[cfg(feature = "coroutine")]
+use crate::coroutine::Suspendable;
 use crate::{Context, IntoValue, JsError, Value};
 
 /// Wrapper around a JavaScript function value.
@@ -19,9 +24,7 @@ impl<'ctx> Function<'ctx> {
         ensure_same_context(ctx, value)?;
         let is_function = unsafe { JS_IsFunction(ctx.raw_ctx().as_ptr(), value.raw()) };
         if is_function == 0 {
-            return Err(JsError::Conversion {
-                message: "expected function".to_string(),
-            });
+            return Err(JsError::type_mismatch("expected function", value.type_name()));
         }
         Ok(Self { ctx, value })
     }
@@ -57,6 +60,22 @@ impl<'ctx> Function<'ctx> {
         Ok(Value::new(self.ctx.raw_ctx(), result))
     }
 
+    /// Call the function on a separate stack, so that functions registered
+    /// with [`Context::register_suspend_fn`] can suspend it, as with
+    /// [`Context::eval_async`].
+    #[cfg(feature = "coroutine")]
+    pub async fn call_async(&self, args: &[Value<'ctx>]) -> Result<Value<'ctx>, JsError> {
+        let ctx = self.ctx;
+        let func = ctx.root(self.value);
+        let args: Vec<_> = args.iter().map(|arg| ctx.root(*arg)).collect();
+        Suspendable::new(ctx, move || {
+            let func = Function { ctx, value: func.to_value() };
+            let args: Vec<_> = args.iter().map(|arg| arg.to_value()).collect();
+            func.call(&args).map(|value| value.raw())
+        })
+        .await
+    }
+
     /// Call the function with no arguments.
     pub fn call0(&self) -> Result<Value<'ctx>, JsError> {
         self.call(&[])
@@ -71,9 +90,7 @@ impl<'ctx> Function<'ctx> {
 
 fn ensure_same_context(ctx: &Context, value: Value<'_>) -> Result<(), JsError> {
     if ctx.raw_ctx() != value.ctx() {
-        return Err(JsError::Conversion {
-            message: "value does not belong to context".to_string(),
-        });
+        return Err(JsError::conversion("value does not belong to context"));
     }
     Ok(())
 }
@@ -0,0 +1,139 @@
+//! Memory buffers backing a context.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+use mquickjs_sys::{JSContext, JS_MoveHeap};
+
+/// Growth policy for a context created with [`Context::with_growth`](crate::Context::with_growth).
+///
+/// The context starts in a buffer of `initial_bytes`. When an allocation
+/// does not fit even after a GC, the heap moves to a separate buffer so
+/// that the context memory is multiplied by `factor` (or reaches what the
+/// allocation needs), up to `max_bytes`. The context header and the stack
+/// stay in the initial buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapGrowth {
+    /// Usable memory when the context is created.
+    pub initial_bytes: usize,
+    /// Upper bound for the usable memory.
+    pub max_bytes: usize,
+    /// Multiplier applied to the memory size on each growth step; must be above 1.
+    pub factor: f64,
+    /// Move the heap to a smaller buffer after a GC leaves most of the memory unused.
+    pub shrink: bool,
+}
+
+impl HeapGrowth {
+    /// Grow from `initial_bytes` to at most `max_bytes`, doubling each step
+    /// and never shrinking.
+    pub fn new(initial_bytes: usize, max_bytes: usize) -> Self {
+        Self {
+            initial_bytes,
+            max_bytes,
+            factor: 2.0,
+            shrink: false,
+        }
+    }
+
+    /// Memory size to grow to from `size` when an allocation needs a
+    /// total usage of `needed` bytes.
+    pub(crate) fn grown_size(&self, size: usize, needed: usize) -> usize {
+        let grown = (size as f64 * self.factor) as usize;
+        grown.max(needed).min(self.max_bytes)
+    }
+
+    /// Memory size to shrink to from `size` once a GC leaves `used` bytes
+    /// in use.
+    pub(crate) fn shrunk_size(&self, size: usize, used: usize) -> usize {
+        if !self.shrink {
+            return size;
+        }
+        // Only shrink when the size is `factor` steps above the target,
+        // so a context hovering around one size does not oscillate.
+        let target = ((used as f64 * self.factor) as usize).max(self.initial_bytes);
+        if (target as f64 * self.factor) < size as f64 {
+            target
+        } else {
+            size
+        }
+    }
+}
+
+/// Move the heap of `ctx` to a new buffer of `heap_bytes`, or back into the
+/// context buffer when `heap_bytes` is 0, and keep the new buffer in
+/// `grown`. Returns false, leaving everything in place, if the heap does
+/// not fit.
+///
+/// # Safety
+///
+/// `ctx` must be a live context whose separate heap buffer, if any, is
+/// the one in `grown`.
+pub(crate) unsafe fn move_heap(ctx: *mut JSContext, grown: &mut Option<Heap>, heap_bytes: usize) -> bool {
+    if heap_bytes == 0 {
+        if unsafe { JS_MoveHeap(ctx, ptr::null_mut(), 0) } != 0 {
+            return false;
+        }
+        *grown = None;
+        return true;
+    }
+    let mut heap = Heap::owned(heap_bytes);
+    if unsafe { JS_MoveHeap(ctx, heap.as_mut_ptr().cast(), heap.byte_len()) } != 0 {
+        return false;
+    }
+    // The engine no longer refers to the previous buffer.
+    *grown = Some(heap);
+    true
+}
+
+/// Context memory, either allocated by the crate or supplied by the caller.
+pub(crate) enum Heap {
+    Owned(Vec<usize>),
+    Static(&'static mut [usize]),
+}
+
+impl Heap {
+    /// Allocate a zeroed heap of at least `bytes` bytes.
+    pub(crate) fn owned(bytes: usize) -> Self {
+        Heap::Owned(vec![0usize; bytes.div_ceil(size_of::<usize>())])
+    }
+
+    /// Zero `buffer` and use it as the heap.
+    pub(crate) fn from_static(buffer: &'static mut [MaybeUninit<usize>]) -> Self {
+        for word in buffer.iter_mut() {
+            word.write(0);
+        }
+        // SAFETY: every word was initialized above and `MaybeUninit<usize>`
+        // has the layout of `usize`.
+        let words = unsafe { &mut *(buffer as *mut [MaybeUninit<usize>] as *mut [usize]) };
+        Heap::Static(words)
+    }
+
+    /// Size of the heap in bytes.
+    pub(crate) fn byte_len(&self) -> usize {
+        self.len() * size_of::<usize>()
+    }
+}
+
+impl Deref for Heap {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        match self {
+            Heap::Owned(words) => words,
+            Heap::Static(words) => words,
+        }
+    }
+}
+
+impl DerefMut for Heap {
+    fn deref_mut(&mut self) -> &mut [usize] {
+        match self {
+            Heap::Owned(words) => words,
+            Heap::Static(words) => words,
+        }
+    }
+}
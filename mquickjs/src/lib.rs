@@ -12,22 +12,87 @@
 //! let value = ctx.eval_i32("1 + 2", "example").expect("eval should succeed");
 //! assert_eq!(value, 3);
 //! ```
+//!
+//! # Derive
+//!
+//! With the `derive` feature enabled, `#[derive(FromValue, IntoValue)]`
+//! generates conversions for structs (as objects), newtypes, tuple structs
+//! (as arrays) and externally tagged enums. Fields accept
+//! `#[js(rename = "...")]`, `#[js(default)]` and `#[js(skip)]`.
+
+#![no_std]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod builder;
+mod clock;
+mod clone;
+mod collection;
+mod console;
 mod context;
+#[cfg(feature = "coroutine")]
+mod coroutine;
 mod convert;
+mod data;
 mod error;
 mod func;
 mod function;
+mod heap;
 mod object;
+#[cfg(feature = "std")]
+mod pool;
+mod promise;
+mod quota;
 mod rooted;
 mod runtime;
+mod snapshot;
+mod state;
+mod stats;
+mod symbol;
+mod task;
+mod timer;
 mod value;
+#[cfg(feature = "std")]
+mod worker;
+
+#[doc(hidden)]
+#[path = "private.rs"]
+pub mod __private;
 
+pub use builder::ContextBuilder;
+pub use clone::ClonedValue;
+#[cfg(feature = "std")]
+pub use collection::AsMap;
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use clock::{Clock, ManualClock};
+pub use console::{ConsoleLevel, ConsoleSink};
+#[cfg(feature = "log")]
+pub use console::LogConsole;
+#[cfg(feature = "tracing")]
+pub use console::TracingConsole;
+#[cfg(feature = "std")]
+pub use console::{CaptureConsole, StdoutConsole};
 pub use context::Context;
 pub use convert::{Coerced, FromValue, IntoValue};
-pub use error::JsError;
+pub use data::{DataLimits, JsData};
+#[cfg(feature = "derive")]
+pub use mquickjs_derive::{FromValue, IntoValue};
+pub use error::{JsError, PathSegment};
 pub use function::Function;
+pub use heap::HeapGrowth;
 pub use object::{Array, Object};
+#[cfg(feature = "std")]
+pub use pool::{ContextPool, PooledContext};
+pub use promise::{Promise, PromiseState};
+pub use quota::{MemoryQuota, QuotaThreshold};
 pub use rooted::{Persistent, RootedValue};
 pub use runtime::Runtime;
-pub use value::Value;
+pub use snapshot::HeapImage;
+pub use stats::{ClassStats, MemoryStats};
+pub use symbol::Symbol;
+pub use value::{Value, ValueKind};
+#[cfg(feature = "std")]
+pub use worker::Worker;
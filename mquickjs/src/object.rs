@@ -1,4 +1,6 @@
-use std::ffi::CString;
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::string::ToString;
 
 use mquickjs_sys::{
     JSValue, JS_EX_NORMAL, JS_GetPropertyStr, JS_GetPropertyUint32, JS_IsNumber,
@@ -25,27 +27,23 @@ impl<'ctx> Object<'ctx> {
     /// Get a property and convert it into a Rust value.
     pub fn get<T: FromValue<'ctx>>(&self, name: &str) -> Result<T, JsError> {
         let name_str = name.to_string();
-        let name = CString::new(name).map_err(|_| JsError::Conversion {
-            message: "property name contains null byte".to_string(),
-        })?;
+        let name = CString::new(name)
+            .map_err(|_| JsError::conversion("property name contains null byte"))?;
 
         let raw = unsafe { JS_GetPropertyStr(self.ctx.raw_ctx().as_ptr(), self.value.raw(), name.as_ptr()) };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: format!("failed to get property '{name_str}'"),
-            });
+            return Err(JsError::conversion(format!("failed to get property '{name_str}'")));
         }
 
         let value = Value::new(self.ctx.raw_ctx(), raw);
-        T::from_value(value)
+        T::from_value(value).map_err(|err| err.at_key(name_str))
     }
 
     /// Set a property from a Rust value.
     pub fn set<T: IntoValue<'ctx>>(&self, name: &str, value: T) -> Result<(), JsError> {
         let name_str = name.to_string();
-        let name = CString::new(name).map_err(|_| JsError::Conversion {
-            message: "property name contains null byte".to_string(),
-        })?;
+        let name = CString::new(name)
+            .map_err(|_| JsError::conversion("property name contains null byte"))?;
         let value = value.into_value(self.ctx)?;
         let result = unsafe {
             JS_SetPropertyStr(
@@ -56,9 +54,7 @@ impl<'ctx> Object<'ctx> {
             )
         };
         if is_exception(result) {
-            return Err(JsError::Conversion {
-                message: format!("failed to set property '{name_str}'"),
-            });
+            return Err(JsError::conversion(format!("failed to set property '{name_str}'")));
         }
         Ok(())
     }
@@ -93,9 +89,7 @@ impl<'ctx> Array<'ctx> {
             )
         };
         if is_exception(result) {
-            return Err(JsError::Conversion {
-                message: format!("failed to set array element {index}"),
-            });
+            return Err(JsError::conversion(format!("failed to set array element {index}")));
         }
         Ok(())
     }
@@ -106,12 +100,10 @@ impl<'ctx> Array<'ctx> {
             JS_GetPropertyUint32(self.ctx.raw_ctx().as_ptr(), self.value.raw(), index as u32)
         };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: format!("failed to get array element {index}"),
-            });
+            return Err(JsError::conversion(format!("failed to get array element {index}")));
         }
         let value = Value::new(self.ctx.raw_ctx(), raw);
-        T::from_value(value)
+        T::from_value(value).map_err(|err| err.at_index(index))
     }
 
     fn length(&self) -> Result<u32, JsError> {
@@ -120,24 +112,18 @@ impl<'ctx> Array<'ctx> {
             JS_GetPropertyStr(self.ctx.raw_ctx().as_ptr(), self.value.raw(), name.as_ptr())
         };
         if is_exception(raw) {
-            return Err(JsError::Conversion {
-                message: "failed to read array length".to_string(),
-            });
+            return Err(JsError::conversion("failed to read array length"));
         }
 
         let is_number = unsafe { JS_IsNumber(self.ctx.raw_ctx().as_ptr(), raw) };
         if is_number == 0 {
-            return Err(JsError::Conversion {
-                message: "expected array".to_string(),
-            });
+            return Err(JsError::type_mismatch("expected array", self.value.type_name()));
         }
 
         let mut length = 0u32;
         let status = unsafe { JS_ToUint32(self.ctx.raw_ctx().as_ptr(), &mut length, raw) };
         if status != 0 {
-            return Err(JsError::Conversion {
-                message: "failed to convert array length".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert array length"));
         }
 
         Ok(length)
@@ -146,9 +132,7 @@ impl<'ctx> Array<'ctx> {
 
 fn ensure_same_context(ctx: &Context, value: Value<'_>) -> Result<(), JsError> {
     if ctx.raw_ctx() != value.ctx() {
-        return Err(JsError::Conversion {
-            message: "value does not belong to context".to_string(),
-        });
+        return Err(JsError::conversion("value does not belong to context"));
     }
     Ok(())
 }
@@ -0,0 +1,145 @@
+//! A pool of contexts for running unrelated scripts without paying for a
+//! new heap each time.
+//!
+//! Every context is reset with [`Context::reset`] and set up by the init
+//! hook again when it comes back, so scripts cannot see each other's
+//! globals, patched prototypes, timers or pending jobs. Functions and
+//! configuration added by a borrower, such as a console sink or a memory
+//! quota, are dropped first; only what the init hook sets up carries over.
+
+use std::boxed::Box;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::vec::Vec;
+
+use crate::{Context, JsError};
+
+type InitHook = dyn Fn(&Context) -> Result<(), JsError> + Send + Sync;
+
+/// Pool of pre-initialized contexts handed out through RAII guards.
+///
+/// A context is reset in place and initialized again when it comes back.
+pub struct ContextPool {
+    size: usize,
+    memory_bytes: usize,
+    init: Option<Box<InitHook>>,
+    idle: Mutex<Vec<Context>>,
+}
+
+impl ContextPool {
+    /// Create a pool keeping `size` contexts of `memory_bytes` each.
+    pub fn new(size: usize, memory_bytes: usize) -> Result<Self, JsError> {
+        Self::build(size, memory_bytes, None)
+    }
+
+    /// Create a pool whose contexts are set up by `init`, e.g. to register
+    /// host functions.
+    pub fn with_init<F>(size: usize, memory_bytes: usize, init: F) -> Result<Self, JsError>
+    where
+        F: Fn(&Context) -> Result<(), JsError> + Send + Sync + 'static,
+    {
+        Self::build(size, memory_bytes, Some(Box::new(init)))
+    }
+
+    fn build(size: usize, memory_bytes: usize, init: Option<Box<InitHook>>) -> Result<Self, JsError> {
+        let pool = Self {
+            size,
+            memory_bytes,
+            init,
+            idle: Mutex::new(Vec::with_capacity(size)),
+        };
+        for _ in 0..size {
+            let ctx = pool.create()?;
+            pool.lock().push(ctx);
+        }
+        Ok(pool)
+    }
+
+    /// Take an idle context, creating one if the pool is empty.
+    pub fn get(&self) -> Result<PooledContext<'_>, JsError> {
+        let ctx = self.lock().pop();
+        let ctx = match ctx {
+            Some(ctx) => ctx,
+            None => self.create()?,
+        };
+        Ok(PooledContext {
+            pool: self,
+            ctx: Some(ctx),
+        })
+    }
+
+    /// Number of contexts currently waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn create(&self) -> Result<Context, JsError> {
+        self.prepare(Context::new(self.memory_bytes)?)
+    }
+
+    fn prepare(&self, ctx: Context) -> Result<Context, JsError> {
+        if let Some(init) = &self.init {
+            init(&ctx)?;
+        }
+        Ok(ctx)
+    }
+
+    fn release(&self, mut ctx: Context) {
+        if self.lock().len() >= self.size {
+            return;
+        }
+        ctx.clear_config();
+        let Ok(ctx) = ctx.reset().and_then(|()| self.prepare(ctx)) else {
+            return;
+        };
+        let mut pool = self.lock();
+        if pool.len() < self.size {
+            pool.push(ctx);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Context>> {
+        self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ContextPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextPool")
+            .field("size", &self.size)
+            .field("memory_bytes", &self.memory_bytes)
+            .field("idle", &self.idle())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Context borrowed from a `ContextPool`, returned to it on drop.
+#[derive(Debug)]
+pub struct PooledContext<'pool> {
+    pool: &'pool ContextPool,
+    ctx: Option<Context>,
+}
+
+impl PooledContext<'_> {
+    /// Drop the context instead of returning it to the pool, which creates
+    /// a new one once it runs out.
+    pub fn discard(mut self) {
+        self.ctx.take();
+    }
+}
+
+impl Deref for PooledContext<'_> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        self.ctx.as_ref().expect("pooled context is present until drop")
+    }
+}
+
+impl Drop for PooledContext<'_> {
+    fn drop(&mut self) {
+        if let Some(ctx) = self.ctx.take() {
+            self.pool.release(ctx);
+        }
+    }
+}
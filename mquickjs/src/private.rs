@@ -0,0 +1,137 @@
+//! Support code for `mquickjs-derive`. Not part of the public API.
+
+use alloc::ffi::CString;
+use alloc::vec::Vec;
+
+use mquickjs_sys::{
+    JS_GetClassID, JS_GetPropertyStr, JS_GetPropertyUint32, JS_IsString, JS_NewArray,
+    JS_NewObject, JS_SetPropertyStr, JS_SetPropertyUint32, JS_TAG_UNDEFINED,
+};
+
+use crate::convert::{array_length, is_exception, js_null_value, object_keys, value_tag};
+use crate::{Context, IntoValue, JsError, Value};
+
+// Generated code may run in `no_std` crates, so it names these through here.
+pub use alloc::format;
+pub use alloc::string::String;
+
+/// Create an empty JavaScript object.
+pub fn new_object<'ctx>(ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+    let raw = unsafe { JS_NewObject(ctx.raw_ctx().as_ptr()) };
+    if is_exception(raw) {
+        return Err(JsError::conversion("failed to create object"));
+    }
+    Ok(Value::new(ctx.raw_ctx(), raw))
+}
+
+/// Create a JavaScript array with the given initial length.
+pub fn new_array<'ctx>(ctx: &'ctx Context, length: usize) -> Result<Value<'ctx>, JsError> {
+    let raw = unsafe { JS_NewArray(ctx.raw_ctx().as_ptr(), length as i32) };
+    if is_exception(raw) {
+        return Err(JsError::conversion("failed to create array"));
+    }
+    Ok(Value::new(ctx.raw_ctx(), raw))
+}
+
+/// Return the JavaScript `null` value.
+pub fn null<'ctx>(ctx: &'ctx Context) -> Value<'ctx> {
+    Value::new(ctx.raw_ctx(), js_null_value())
+}
+
+/// Convert `value` and store it as property `name` of `object`.
+pub fn set_property<'ctx, T: IntoValue<'ctx>>(
+    ctx: &'ctx Context,
+    object: Value<'ctx>,
+    name: &str,
+    value: T,
+) -> Result<(), JsError> {
+    let key = CString::new(name)
+        .map_err(|_| JsError::conversion("property name contains null byte"))?;
+    let value = value.into_value(ctx)?;
+    let result = unsafe {
+        JS_SetPropertyStr(ctx.raw_ctx().as_ptr(), object.raw(), key.as_ptr(), value.raw())
+    };
+    if is_exception(result) {
+        return Err(JsError::conversion(format!("failed to set property '{name}'")));
+    }
+    Ok(())
+}
+
+/// Convert `value` and store it at `index` of `array`.
+pub fn set_element<'ctx, T: IntoValue<'ctx>>(
+    ctx: &'ctx Context,
+    array: Value<'ctx>,
+    index: u32,
+    value: T,
+) -> Result<(), JsError> {
+    let value = value.into_value(ctx)?;
+    let result =
+        unsafe { JS_SetPropertyUint32(ctx.raw_ctx().as_ptr(), array.raw(), index, value.raw()) };
+    if is_exception(result) {
+        return Err(JsError::conversion(format!("failed to set array element {index}")));
+    }
+    Ok(())
+}
+
+/// Fail unless `value` is an object, naming `expected` in the error.
+pub fn expect_object(value: Value<'_>, expected: &str) -> Result<(), JsError> {
+    if unsafe { JS_GetClassID(value.ctx().as_ptr(), value.raw()) } < 0 {
+        return Err(JsError::type_mismatch(
+            format!("expected object for {expected}"),
+            value.type_name(),
+        ));
+    }
+    Ok(())
+}
+
+/// Read property `name` of `object`.
+pub fn get_property<'ctx>(object: Value<'ctx>, name: &str) -> Result<Value<'ctx>, JsError> {
+    let key = CString::new(name)
+        .map_err(|_| JsError::conversion("property name contains null byte"))?;
+    let raw = unsafe { JS_GetPropertyStr(object.ctx().as_ptr(), object.raw(), key.as_ptr()) };
+    if is_exception(raw) {
+        return Err(JsError::conversion(format!("failed to get property '{name}'")));
+    }
+    Ok(Value::new(object.ctx(), raw))
+}
+
+/// Read element `index` of `array`.
+pub fn get_element<'ctx>(array: Value<'ctx>, index: u32) -> Result<Value<'ctx>, JsError> {
+    let raw = unsafe { JS_GetPropertyUint32(array.ctx().as_ptr(), array.raw(), index) };
+    if is_exception(raw) {
+        return Err(JsError::conversion(format!("failed to read array element {index}")));
+    }
+    Ok(Value::new(array.ctx(), raw))
+}
+
+/// Read an array and check it has exactly `expected` elements.
+pub fn expect_array_length(value: Value<'_>, expected: u32) -> Result<(), JsError> {
+    if !value.is_array() {
+        return Err(JsError::type_mismatch("expected array", value.type_name()));
+    }
+    let length = array_length(value.ctx(), value.raw())?;
+    if length != expected {
+        return Err(JsError::conversion(format!("expected array of length {expected}")));
+    }
+    Ok(())
+}
+
+/// Return the own enumerable property names of `object`.
+pub fn keys(object: Value<'_>) -> Result<Vec<String>, JsError> {
+    object_keys(object.ctx(), object)
+}
+
+/// Return true if `value` is `undefined`.
+pub fn is_undefined(value: Value<'_>) -> bool {
+    value_tag(value.raw()) == JS_TAG_UNDEFINED
+}
+
+/// Return true if `value` is a string.
+pub fn is_string(value: Value<'_>) -> bool {
+    unsafe { JS_IsString(value.ctx().as_ptr(), value.raw()) != 0 }
+}
+
+/// Build a conversion error with the given message.
+pub fn error(message: String) -> JsError {
+    JsError::conversion(message)
+}
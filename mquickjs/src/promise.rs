@@ -0,0 +1,997 @@
+//! The `Promise` class and its microtask queue, drained with
+//! [`Context::run_jobs`](crate::Context::run_jobs).
+//!
+//! Promise objects are instances of a user class whose opaque pointer is the
+//! id of a [`Record`] in the context's [`PromiseTable`], which holds the
+//! status of the promise and the futures waiting for it. The user value is
+//! the array of pending reactions, `[on_fulfilled, on_rejected, derived,
+//! ...]` with `undefined` for missing ones, until the promise settles and
+//! then the settled value. The GC traces it, so a promise reachable only from
+//! its own handlers or value is still collected.
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::ffi::{CStr, c_int, c_void};
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::task::{self, Poll, Waker};
+
+use mquickjs_sys::{
+    JS_GetClassID, JS_GetException, JS_GetOpaque, JS_GetPropertyStr, JS_GetPropertyUint32,
+    JS_GetUserValue, JS_IsUncatchableException, JS_NewArray, JS_NewCFunctionParams, JS_NewInt32,
+    JS_NewObjectClassUser, JS_SetOpaque, JS_SetUserValue, JS_TAG_BOOL, JS_Throw, JS_ToInt32,
+    JSContext, JSGCRef, JSHostClassEnum_JS_CLASS_PROMISE, JSHostFunctionEnum,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_all_element,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_finally_catch,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_finally_then,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_reject,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_resolve,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_return_value,
+    JSHostFunctionEnum_JS_CFUNCTION_promise_throw_value, JSPromiseMethodEnum,
+    JSPromiseMethodEnum_JS_PROMISE_ALL, JSPromiseMethodEnum_JS_PROMISE_CATCH,
+    JSPromiseMethodEnum_JS_PROMISE_CONSTRUCTOR, JSPromiseMethodEnum_JS_PROMISE_FINALLY,
+    JSPromiseMethodEnum_JS_PROMISE_QUEUE_MICROTASK, JSPromiseMethodEnum_JS_PROMISE_RACE,
+    JSPromiseMethodEnum_JS_PROMISE_REJECT, JSPromiseMethodEnum_JS_PROMISE_RESOLVE,
+    JSPromiseMethodEnum_JS_PROMISE_THEN, JSValue,
+};
+
+use crate::context::exception_error;
+use crate::convert::{array_length, boolean, exception, is_exception, undefined, value_tag};
+use crate::rooted::{
+    Local, RootedValue, add_root, arguments, array_len, call, checked, delete_root, is_function,
+    local, new_array, set_element, throw_type_error,
+};
+use crate::state::{ContextState, context_state};
+use crate::{Context, IntoValue, JsError, Value};
+
+/// Array elements per reaction in the user value of a pending promise.
+const REACTION_WIDTH: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pending,
+    Fulfilled,
+    Rejected,
+}
+
+pub(crate) struct Record {
+    status: Status,
+    /// Set by the first call to one of the promise's own resolving
+    /// functions; later calls are ignored, also while a thenable it was
+    /// resolved with is pending.
+    locked: bool,
+    /// Futures waiting for the promise to settle.
+    wakers: Vec<Waker>,
+}
+
+/// A queued microtask.
+pub(crate) enum Job {
+    /// Run a `then` handler with the settled value, or pass the value on to
+    /// `derived` when there is no handler.
+    Reaction {
+        handler: Option<Box<JSGCRef>>,
+        derived: Option<Box<JSGCRef>>,
+        argument: Box<JSGCRef>,
+        rejected: bool,
+    },
+    /// Call `then` of a thenable that `promise` was resolved with.
+    Thenable {
+        promise: Box<JSGCRef>,
+        thenable: Box<JSGCRef>,
+        then: Box<JSGCRef>,
+    },
+    /// A `queueMicrotask` callback.
+    Microtask { callback: Box<JSGCRef> },
+}
+
+/// Promise records and pending jobs of a context.
+#[derive(Default)]
+pub(crate) struct PromiseTable {
+    next_id: usize,
+    records: BTreeMap<usize, Record>,
+    jobs: VecDeque<Job>,
+}
+
+impl PromiseTable {
+    fn insert(&mut self) -> usize {
+        // Id 0 is the opaque of an object the constructor never finished.
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        while self.records.contains_key(&self.next_id) {
+            self.next_id += 1;
+        }
+        let record = Record {
+            status: Status::Pending,
+            locked: false,
+            wakers: Vec::new(),
+        };
+        self.records.insert(self.next_id, record);
+        self.next_id
+    }
+
+    pub(crate) fn pop_job(&mut self) -> Option<Job> {
+        self.jobs.pop_front()
+    }
+
+    pub(crate) fn has_jobs(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+
+    /// Forget every record and job without touching the context, which is
+    /// gone.
+    pub(crate) fn forget(&mut self) {
+        self.records.clear();
+        self.jobs.clear();
+    }
+}
+
+/// Handle to a JavaScript `Promise`, rooted while the handle exists.
+///
+/// Awaiting the handle drives the context's event loop with
+/// [`Context::poll_event_loop`] on every poll and completes with the
+/// fulfilled value, or with the rejection reason as a [`JsError::Exception`].
+///
+/// ```no_run
+/// use mquickjs_rs::{Context, Object, Promise};
+///
+/// let ctx = Context::new(1024 * 1024).expect("context should initialize");
+/// let promise = Promise::new(&ctx).expect("promise should be created");
+/// let global = ctx.eval("globalThis", "example").expect("eval should succeed");
+/// let global = Object::from_value(&ctx, global).expect("global should be an object");
+/// global.set("pending", promise.to_value()).expect("set should succeed");
+/// ctx.eval("pending.then(function (v) { globalThis.got = v; })", "example")
+///     .expect("eval should succeed");
+///
+/// promise.resolve(42).expect("resolve should succeed");
+/// ctx.run_jobs().expect("jobs should run");
+/// assert_eq!(ctx.eval_i32("got", "example").expect("eval should succeed"), 42);
+/// ```
+#[derive(Debug)]
+pub struct Promise<'ctx> {
+    ctx: &'ctx Context,
+    value: RootedValue<'ctx>,
+}
+
+/// State of a promise, as returned by [`Promise::state`].
+#[derive(Debug, Clone, Copy)]
+pub enum PromiseState<'ctx> {
+    Pending,
+    Fulfilled(Value<'ctx>),
+    Rejected(Value<'ctx>),
+}
+
+impl<'ctx> Promise<'ctx> {
+    /// Create a pending promise to be settled from Rust.
+    pub fn new(ctx: &'ctx Context) -> Result<Self, JsError> {
+        let raw_ctx = ctx.raw_ctx();
+        let state = context_state(raw_ctx)?;
+        let promise = unsafe { new_promise(raw_ctx, state) }
+            .map_err(|()| exception_error(raw_ctx.as_ptr()))?;
+        Ok(Self {
+            ctx,
+            value: RootedValue::from_raw(raw_ctx, promise.raw()),
+        })
+    }
+
+    /// Wrap a `Promise` object.
+    pub fn from_value(ctx: &'ctx Context, value: Value<'ctx>) -> Result<Self, JsError> {
+        if ctx.raw_ctx() != value.ctx() {
+            return Err(JsError::conversion("value does not belong to context"));
+        }
+        if promise_id(ctx.raw_ctx(), value.raw()).is_none() {
+            return Err(JsError::type_mismatch(
+                "expected promise",
+                value.type_name(),
+            ));
+        }
+        Ok(Self {
+            ctx,
+            value: ctx.root(value),
+        })
+    }
+
+    /// Return the promise object.
+    pub fn to_value(&self) -> Value<'ctx> {
+        self.value.to_value()
+    }
+
+    /// Resolve the promise with `value`, adopting its state if it is a
+    /// promise or another thenable.
+    ///
+    /// Like the functions passed to a `Promise` executor, this does nothing
+    /// once the promise was resolved or rejected.
+    pub fn resolve<T: IntoValue<'ctx>>(&self, value: T) -> Result<(), JsError> {
+        self.settle(value, false)
+    }
+
+    /// Reject the promise with `reason`, unless it was already resolved or
+    /// rejected.
+    pub fn reject<T: IntoValue<'ctx>>(&self, reason: T) -> Result<(), JsError> {
+        self.settle(reason, true)
+    }
+
+    fn settle<T: IntoValue<'ctx>>(&self, value: T, rejected: bool) -> Result<(), JsError> {
+        let raw_ctx = self.ctx.raw_ctx();
+        let value = value.into_value(self.ctx)?;
+        if value.ctx() != raw_ctx {
+            return Err(JsError::conversion("value does not belong to context"));
+        }
+        let state = context_state(raw_ctx)?;
+        let value = RootedValue::from_raw(raw_ctx, value.raw());
+        unsafe { resolve_once(raw_ctx, state, &self.value, &value, rejected) }
+            .map_err(|()| exception_error(raw_ctx.as_ptr()))
+    }
+
+    /// Whether the promise is pending, fulfilled or rejected.
+    pub fn state(&self) -> PromiseState<'ctx> {
+        let raw_ctx = self.ctx.raw_ctx();
+        let Some(id) = promise_id(raw_ctx, self.value.raw()) else {
+            return PromiseState::Pending;
+        };
+        let Ok(state) = context_state(raw_ctx) else {
+            return PromiseState::Pending;
+        };
+        let status = state
+            .promises
+            .borrow()
+            .records
+            .get(&id)
+            .map(|record| record.status);
+        let value = || {
+            Value::new(raw_ctx, unsafe {
+                JS_GetUserValue(raw_ctx.as_ptr(), self.value.raw())
+            })
+        };
+        match status {
+            Some(Status::Fulfilled) => PromiseState::Fulfilled(value()),
+            Some(Status::Rejected) => PromiseState::Rejected(value()),
+            _ => PromiseState::Pending,
+        }
+    }
+}
+
+impl<'ctx> Future for Promise<'ctx> {
+    type Output = Result<Value<'ctx>, JsError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        // The loop finishing does not matter; the promise may stay pending.
+        if let Poll::Ready(Err(err)) = self.ctx.poll_event_loop(cx) {
+            return Poll::Ready(Err(err));
+        }
+        let raw_ctx = self.ctx.raw_ctx();
+        match self.state() {
+            PromiseState::Fulfilled(value) => Poll::Ready(Ok(value)),
+            PromiseState::Rejected(reason) => Poll::Ready(Err(unsafe {
+                JS_Throw(raw_ctx.as_ptr(), reason.raw());
+                let error = exception_error(raw_ctx.as_ptr());
+                JS_GetException(raw_ctx.as_ptr());
+                error
+            })),
+            PromiseState::Pending => {
+                if let Some(id) = promise_id(raw_ctx, self.value.raw())
+                    && let Ok(state) = context_state(raw_ctx)
+                    && let Some(record) = state.promises.borrow_mut().records.get_mut(&id)
+                    && !record
+                        .wakers
+                        .iter()
+                        .any(|waker| waker.will_wake(cx.waker()))
+                {
+                    record.wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// `new Promise`, the `Promise` statics and methods, and `queueMicrotask`.
+#[allow(non_upper_case_globals)]
+pub(crate) unsafe extern "C" fn promise_callback(
+    ctx: *mut JSContext,
+    this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+    magic: c_int,
+) -> JSValue {
+    let Some(ctx) = NonNull::new(ctx) else {
+        return undefined();
+    };
+    let Some(state) = (unsafe { ContextState::from_raw(ctx.as_ptr()) }) else {
+        return undefined();
+    };
+    // Root everything first: any allocation below may move the values.
+    let this = local(
+        ctx,
+        if this_val.is_null() {
+            undefined()
+        } else {
+            unsafe { *this_val }
+        },
+    );
+    let args = unsafe { arguments(ctx, argc, argv, 2) };
+    let result = unsafe {
+        match magic as JSPromiseMethodEnum {
+            JSPromiseMethodEnum_JS_PROMISE_CONSTRUCTOR => construct(ctx, state, &args[0]),
+            JSPromiseMethodEnum_JS_PROMISE_THEN => then(ctx, state, &this, &args[0], &args[1]),
+            JSPromiseMethodEnum_JS_PROMISE_CATCH => {
+                then(ctx, state, &this, &local(ctx, undefined()), &args[0])
+            }
+            JSPromiseMethodEnum_JS_PROMISE_FINALLY => finally(ctx, state, &this, &args[0]),
+            JSPromiseMethodEnum_JS_PROMISE_RESOLVE => promise_resolve(ctx, state, &args[0]),
+            JSPromiseMethodEnum_JS_PROMISE_REJECT => new_promise(ctx, state).and_then(|promise| {
+                resolve_once(ctx, state, &promise, &args[0], true)?;
+                Ok(promise)
+            }),
+            JSPromiseMethodEnum_JS_PROMISE_ALL => all(ctx, state, &args[0]),
+            JSPromiseMethodEnum_JS_PROMISE_RACE => race(ctx, state, &args[0]),
+            JSPromiseMethodEnum_JS_PROMISE_QUEUE_MICROTASK => queue_microtask(ctx, state, &args[0]),
+            _ => Ok(local(ctx, undefined())),
+        }
+    };
+    match result {
+        Ok(value) => value.raw(),
+        Err(()) => exception(),
+    }
+}
+
+/// The closures created by the promise methods, told apart by `func_idx`.
+#[allow(non_upper_case_globals)]
+pub(crate) unsafe extern "C" fn promise_function_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+    params: JSValue,
+    func_idx: c_int,
+) -> JSValue {
+    let Some(ctx) = NonNull::new(ctx) else {
+        return undefined();
+    };
+    let Some(state) = (unsafe { ContextState::from_raw(ctx.as_ptr()) }) else {
+        return undefined();
+    };
+    let params = local(ctx, params);
+    let args = unsafe { arguments(ctx, argc, argv, 1) };
+    let result = unsafe {
+        match func_idx as JSHostFunctionEnum {
+            JSHostFunctionEnum_JS_CFUNCTION_promise_resolve => {
+                resolving_function(ctx, state, &params, &args[0], false)
+            }
+            JSHostFunctionEnum_JS_CFUNCTION_promise_reject => {
+                resolving_function(ctx, state, &params, &args[0], true)
+            }
+            JSHostFunctionEnum_JS_CFUNCTION_promise_all_element => {
+                all_element(ctx, state, &params, &args[0])
+            }
+            JSHostFunctionEnum_JS_CFUNCTION_promise_finally_then => {
+                finally_reaction(ctx, state, &params, &args[0], false)
+            }
+            JSHostFunctionEnum_JS_CFUNCTION_promise_finally_catch => {
+                finally_reaction(ctx, state, &params, &args[0], true)
+            }
+            JSHostFunctionEnum_JS_CFUNCTION_promise_return_value => Ok(params),
+            JSHostFunctionEnum_JS_CFUNCTION_promise_throw_value => {
+                return JS_Throw(ctx.as_ptr(), params.raw());
+            }
+            _ => Ok(local(ctx, undefined())),
+        }
+    };
+    match result {
+        Ok(value) => value.raw(),
+        Err(()) => exception(),
+    }
+}
+
+/// Drop the record of a collected promise. Runs during GC, so it must not
+/// call into JavaScript.
+pub(crate) unsafe extern "C" fn promise_finalizer(ctx: *mut JSContext, opaque: *mut c_void) {
+    let id = opaque as usize;
+    let Some(state) = (unsafe { ContextState::from_raw(ctx) }) else {
+        return;
+    };
+    if id == 0 {
+        return;
+    }
+    match state.promises.try_borrow_mut() {
+        Ok(mut table) => {
+            table.records.remove(&id);
+        }
+        Err(_) => {
+            if let Ok(mut collected) = state.collected_promises.try_borrow_mut() {
+                collected.push(id);
+            }
+        }
+    }
+}
+
+/// Run one job. `Err` leaves an exception pending on the context: one thrown
+/// by a `queueMicrotask` callback, or an uncatchable one.
+pub(crate) unsafe fn run_job(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    job: Job,
+) -> Result<(), ()> {
+    match job {
+        Job::Reaction {
+            handler,
+            derived,
+            argument,
+            rejected,
+        } => {
+            let argument = adopt(ctx, argument);
+            let handler = handler.map(|handler| adopt(ctx, handler));
+            let derived = derived.map(|derived| adopt(ctx, derived));
+            let (value, rejected) = match handler {
+                None => (argument, rejected),
+                Some(handler) => match unsafe { call(ctx, &handler, None, &[&argument]) } {
+                    Ok(result) => (result, false),
+                    Err(()) => (unsafe { take_exception(ctx) }.ok_or(())?, true),
+                },
+            };
+            match derived {
+                Some(derived) => unsafe { resolve_once(ctx, state, &derived, &value, rejected) },
+                None => Ok(()),
+            }
+        }
+        Job::Thenable {
+            promise,
+            thenable,
+            then,
+        } => {
+            let promise = adopt(ctx, promise);
+            let thenable = adopt(ctx, thenable);
+            let then = adopt(ctx, then);
+            let (resolve, reject) = unsafe { resolving_functions(ctx, &promise, true) }?;
+            if unsafe { call(ctx, &then, Some(&thenable), &[&resolve, &reject]) }.is_err() {
+                let error = unsafe { take_exception(ctx) }.ok_or(())?;
+                // Ignored if the thenable already settled the promise.
+                unsafe { call(ctx, &reject, None, &[&error]) }?;
+            }
+            Ok(())
+        }
+        Job::Microtask { callback } => {
+            let callback = adopt(ctx, callback);
+            unsafe { call(ctx, &callback, None, &[]) }.map(drop)
+        }
+    }
+}
+
+/// `new Promise(executor)`.
+unsafe fn construct(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    executor: &Local,
+) -> Result<Local, ()> {
+    if !is_function(ctx, executor.raw()) {
+        return throw_type_error(ctx, c"Promise resolver is not a function");
+    }
+    let promise = unsafe { new_promise(ctx, state) }?;
+    let (resolve, reject) = unsafe { resolving_functions(ctx, &promise, false) }?;
+    if unsafe { call(ctx, executor, None, &[&resolve, &reject]) }.is_err() {
+        let error = unsafe { take_exception(ctx) }.ok_or(())?;
+        unsafe { call(ctx, &reject, None, &[&error]) }?;
+    }
+    Ok(promise)
+}
+
+/// `promise.then(on_fulfilled, on_rejected)`.
+unsafe fn then(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &Local,
+    on_fulfilled: &Local,
+    on_rejected: &Local,
+) -> Result<Local, ()> {
+    if promise_id(ctx, promise.raw()).is_none() {
+        return throw_type_error(ctx, c"not a promise");
+    }
+    let derived = unsafe { new_promise(ctx, state) }?;
+    perform_then(
+        ctx,
+        state,
+        promise,
+        on_fulfilled,
+        on_rejected,
+        Some(&derived),
+    )?;
+    Ok(derived)
+}
+
+/// `promise.finally(on_finally)`: call `on_finally` without arguments, wait
+/// for its result, then pass the original outcome on.
+unsafe fn finally(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &Local,
+    on_finally: &Local,
+) -> Result<Local, ()> {
+    if !is_function(ctx, on_finally.raw()) {
+        return unsafe { then(ctx, state, promise, on_finally, on_finally) };
+    }
+    let then_finally = unsafe {
+        new_closure(
+            ctx,
+            JSHostFunctionEnum_JS_CFUNCTION_promise_finally_then,
+            on_finally,
+        )
+    }?;
+    let catch_finally = unsafe {
+        new_closure(
+            ctx,
+            JSHostFunctionEnum_JS_CFUNCTION_promise_finally_catch,
+            on_finally,
+        )
+    }?;
+    unsafe { then(ctx, state, promise, &then_finally, &catch_finally) }
+}
+
+/// The handlers installed by `finally`: `on_finally` is the closure's
+/// params.
+unsafe fn finally_reaction(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    on_finally: &Local,
+    outcome: &Local,
+    rejected: bool,
+) -> Result<Local, ()> {
+    let result = unsafe { call(ctx, on_finally, None, &[]) }?;
+    let result = unsafe { promise_resolve(ctx, state, &result) }?;
+    let thunk = if rejected {
+        JSHostFunctionEnum_JS_CFUNCTION_promise_throw_value
+    } else {
+        JSHostFunctionEnum_JS_CFUNCTION_promise_return_value
+    };
+    let thunk = unsafe { new_closure(ctx, thunk, outcome) }?;
+    let derived = unsafe { new_promise(ctx, state) }?;
+    let undefined = local(ctx, undefined());
+    perform_then(ctx, state, &result, &thunk, &undefined, Some(&derived))?;
+    Ok(derived)
+}
+
+/// `Promise.resolve(value)`: `value` itself if it is a promise.
+unsafe fn promise_resolve(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    value: &Local,
+) -> Result<Local, ()> {
+    if promise_id(ctx, value.raw()).is_some() {
+        return Ok(local(ctx, value.raw()));
+    }
+    let promise = unsafe { new_promise(ctx, state) }?;
+    unsafe { resolve_once(ctx, state, &promise, value, false) }?;
+    Ok(promise)
+}
+
+/// `Promise.all(array)`, fulfilled with the values once every element is.
+///
+/// The closures share a state array `[result, values, remaining]`; each
+/// element handler has params `[index, state, called]`.
+unsafe fn all(ctx: NonNull<JSContext>, state: &ContextState, items: &Local) -> Result<Local, ()> {
+    let result = unsafe { new_promise(ctx, state) }?;
+    let Some(len) = array_len(ctx, items) else {
+        let error = type_error(ctx, c"Promise.all expects an array");
+        return unsafe { reject_with(ctx, state, &result, error) };
+    };
+    let values = checked(ctx, unsafe { JS_NewArray(ctx.as_ptr(), len as c_int) })?;
+    let shared = new_array(ctx, &[&result, &values, &local(ctx, int(ctx, 1))])?;
+    let (_, reject) = unsafe { resolving_functions(ctx, &result, false) }?;
+    for index in 0..len {
+        let item = checked(ctx, unsafe {
+            JS_GetPropertyUint32(ctx.as_ptr(), items.raw(), index)
+        })?;
+        // Arrays have no holes: fill the slot before the element can settle.
+        set_element(ctx, &values, index, undefined())?;
+        let item = unsafe { promise_resolve(ctx, state, &item) }?;
+        let index_value = local(ctx, int(ctx, index as i32));
+        let params = new_array(ctx, &[&index_value, &shared, &local(ctx, boolean(false))])?;
+        let on_fulfilled = unsafe {
+            new_closure(
+                ctx,
+                JSHostFunctionEnum_JS_CFUNCTION_promise_all_element,
+                &params,
+            )
+        }?;
+        add_remaining(ctx, &shared, 1)?;
+        perform_then(ctx, state, &item, &on_fulfilled, &reject, None)?;
+    }
+    if add_remaining(ctx, &shared, -1)? == 0 {
+        unsafe { resolve_once(ctx, state, &result, &values, false) }?;
+    }
+    Ok(result)
+}
+
+/// Handler fulfilling one element of `Promise.all`.
+unsafe fn all_element(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    params: &Local,
+    value: &Local,
+) -> Result<Local, ()> {
+    let undefined = local(ctx, undefined());
+    if unsafe { JS_GetPropertyUint32(ctx.as_ptr(), params.raw(), 2) } == boolean(true) {
+        return Ok(undefined);
+    }
+    set_element(ctx, params, 2, boolean(true))?;
+    let mut index = 0;
+    if unsafe {
+        JS_ToInt32(
+            ctx.as_ptr(),
+            &mut index,
+            JS_GetPropertyUint32(ctx.as_ptr(), params.raw(), 0),
+        )
+    } != 0
+    {
+        return Err(());
+    }
+    let shared = checked(ctx, unsafe {
+        JS_GetPropertyUint32(ctx.as_ptr(), params.raw(), 1)
+    })?;
+    let values = checked(ctx, unsafe {
+        JS_GetPropertyUint32(ctx.as_ptr(), shared.raw(), 1)
+    })?;
+    set_element(ctx, &values, index as u32, value.raw())?;
+    if add_remaining(ctx, &shared, -1)? == 0 {
+        let result = checked(ctx, unsafe {
+            JS_GetPropertyUint32(ctx.as_ptr(), shared.raw(), 0)
+        })?;
+        unsafe { resolve_once(ctx, state, &result, &values, false) }?;
+    }
+    Ok(undefined)
+}
+
+/// Add `delta` to the `remaining` count of a `Promise.all` and return it.
+fn add_remaining(ctx: NonNull<JSContext>, shared: &Local, delta: i32) -> Result<i32, ()> {
+    let mut remaining = 0;
+    let count = unsafe { JS_GetPropertyUint32(ctx.as_ptr(), shared.raw(), 2) };
+    if unsafe { JS_ToInt32(ctx.as_ptr(), &mut remaining, count) } != 0 {
+        return Err(());
+    }
+    remaining += delta;
+    set_element(ctx, shared, 2, int(ctx, remaining))?;
+    Ok(remaining)
+}
+
+/// `Promise.race(array)`, settled like the first element to settle.
+unsafe fn race(ctx: NonNull<JSContext>, state: &ContextState, items: &Local) -> Result<Local, ()> {
+    let result = unsafe { new_promise(ctx, state) }?;
+    let Some(len) = array_len(ctx, items) else {
+        let error = type_error(ctx, c"Promise.race expects an array");
+        return unsafe { reject_with(ctx, state, &result, error) };
+    };
+    let (resolve, reject) = unsafe { resolving_functions(ctx, &result, false) }?;
+    for index in 0..len {
+        let item = checked(ctx, unsafe {
+            JS_GetPropertyUint32(ctx.as_ptr(), items.raw(), index)
+        })?;
+        let item = unsafe { promise_resolve(ctx, state, &item) }?;
+        perform_then(ctx, state, &item, &resolve, &reject, None)?;
+    }
+    Ok(result)
+}
+
+unsafe fn reject_with(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &Local,
+    reason: Local,
+) -> Result<Local, ()> {
+    unsafe { resolve_once(ctx, state, promise, &reason, true) }?;
+    Ok(local(ctx, promise.raw()))
+}
+
+/// `queueMicrotask(callback)`.
+unsafe fn queue_microtask(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    callback: &Local,
+) -> Result<Local, ()> {
+    if !is_function(ctx, callback.raw()) {
+        return throw_type_error(ctx, c"not a function");
+    }
+    let job = Job::Microtask {
+        callback: add_root(ctx.as_ptr(), callback.raw()),
+    };
+    state.promises.borrow_mut().jobs.push_back(job);
+    Ok(local(ctx, undefined()))
+}
+
+/// The `resolve` and `reject` functions passed to an executor or a
+/// thenable. Their params are `[promise, flag]`: `flag` is `null` for the
+/// promise's own functions, which lock its record, and a boolean shared by
+/// the pair made for a thenable.
+unsafe fn resolving_functions(
+    ctx: NonNull<JSContext>,
+    promise: &Local,
+    for_thenable: bool,
+) -> Result<(Local, Local), ()> {
+    let flag = if for_thenable {
+        boolean(false)
+    } else {
+        crate::convert::js_null_value()
+    };
+    let params = new_array(ctx, &[promise, &local(ctx, flag)])?;
+    let resolve = unsafe {
+        new_closure(
+            ctx,
+            JSHostFunctionEnum_JS_CFUNCTION_promise_resolve,
+            &params,
+        )
+    }?;
+    let reject =
+        unsafe { new_closure(ctx, JSHostFunctionEnum_JS_CFUNCTION_promise_reject, &params) }?;
+    Ok((resolve, reject))
+}
+
+unsafe fn resolving_function(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    params: &Local,
+    value: &Local,
+    rejected: bool,
+) -> Result<Local, ()> {
+    let promise = checked(ctx, unsafe {
+        JS_GetPropertyUint32(ctx.as_ptr(), params.raw(), 0)
+    })?;
+    let flag = unsafe { JS_GetPropertyUint32(ctx.as_ptr(), params.raw(), 1) };
+    if value_tag(flag) != JS_TAG_BOOL {
+        unsafe { resolve_once(ctx, state, &promise, value, rejected) }?;
+    } else if flag == boolean(false) {
+        set_element(ctx, params, 1, boolean(true))?;
+        if rejected {
+            settle(ctx, state, promise.raw(), value.raw(), true);
+        } else {
+            unsafe { resolve(ctx, state, &promise, value) }?;
+        }
+    }
+    Ok(local(ctx, undefined()))
+}
+
+/// Resolve or reject `promise` through its own resolving functions: only
+/// the first call has an effect.
+unsafe fn resolve_once(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &RootedValue<'_>,
+    value: &RootedValue<'_>,
+    rejected: bool,
+) -> Result<(), ()> {
+    let Some(id) = promise_id(ctx, promise.raw()) else {
+        return Ok(());
+    };
+    {
+        let mut table = state.promises.borrow_mut();
+        let Some(record) = table.records.get_mut(&id) else {
+            return Ok(());
+        };
+        if record.locked || record.status != Status::Pending {
+            return Ok(());
+        }
+        record.locked = true;
+    }
+    if rejected {
+        settle(ctx, state, promise.raw(), value.raw(), true);
+        Ok(())
+    } else {
+        unsafe { resolve(ctx, state, promise, value) }
+    }
+}
+
+/// Resolve `promise` with `value`: a thenable is adopted from a job, any
+/// other value fulfills the promise.
+unsafe fn resolve(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &RootedValue<'_>,
+    value: &RootedValue<'_>,
+) -> Result<(), ()> {
+    if value.raw() == promise.raw() {
+        let error = type_error(ctx, c"promise resolved with itself");
+        settle(ctx, state, promise.raw(), error.raw(), true);
+        return Ok(());
+    }
+    if unsafe { JS_GetClassID(ctx.as_ptr(), value.raw()) } < 0 {
+        settle(ctx, state, promise.raw(), value.raw(), false);
+        return Ok(());
+    }
+    let then = unsafe { JS_GetPropertyStr(ctx.as_ptr(), value.raw(), c"then".as_ptr()) };
+    if is_exception(then) {
+        let error = unsafe { take_exception(ctx) }.ok_or(())?;
+        settle(ctx, state, promise.raw(), error.raw(), true);
+        return Ok(());
+    }
+    if !is_function(ctx, then) {
+        settle(ctx, state, promise.raw(), value.raw(), false);
+        return Ok(());
+    }
+    let job = Job::Thenable {
+        promise: add_root(ctx.as_ptr(), promise.raw()),
+        thenable: add_root(ctx.as_ptr(), value.raw()),
+        then: add_root(ctx.as_ptr(), then),
+    };
+    state.promises.borrow_mut().jobs.push_back(job);
+    Ok(())
+}
+
+/// Fulfill or reject a pending `promise`, queueing its reactions and waking
+/// the futures waiting for it. Allocates nothing, so `value` cannot move.
+fn settle(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: JSValue,
+    value: JSValue,
+    rejected: bool,
+) {
+    let Some(id) = promise_id(ctx, promise) else {
+        return;
+    };
+    let raw_ctx = ctx.as_ptr();
+    let wakers = {
+        let mut table = state.promises.borrow_mut();
+        let PromiseTable { records, jobs, .. } = &mut *table;
+        let Some(record) = records.get_mut(&id) else {
+            return;
+        };
+        if record.status != Status::Pending {
+            return;
+        }
+        record.status = if rejected {
+            Status::Rejected
+        } else {
+            Status::Fulfilled
+        };
+        let reactions = unsafe { JS_GetUserValue(raw_ctx, promise) };
+        let len = array_length(ctx, reactions).unwrap_or(0);
+        let slot = if rejected { 1 } else { 0 };
+        for start in (0..len).step_by(REACTION_WIDTH as usize) {
+            let element =
+                |offset| unsafe { JS_GetPropertyUint32(raw_ctx, reactions, start + offset) };
+            let handler = element(slot);
+            let derived = element(2);
+            jobs.push_back(Job::Reaction {
+                handler: is_function(ctx, handler).then(|| add_root(raw_ctx, handler)),
+                derived: (derived != undefined()).then(|| add_root(raw_ctx, derived)),
+                argument: add_root(raw_ctx, value),
+                rejected,
+            });
+        }
+        unsafe { JS_SetUserValue(raw_ctx, promise, value) };
+        core::mem::take(&mut record.wakers)
+    };
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+/// Register handlers on `promise`, or queue one at once if it is settled.
+/// Handlers that are not functions pass the value on to `derived`.
+fn perform_then(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    promise: &Local,
+    on_fulfilled: &Local,
+    on_rejected: &Local,
+    derived: Option<&Local>,
+) -> Result<(), ()> {
+    let Some(id) = promise_id(ctx, promise.raw()) else {
+        return Ok(());
+    };
+    let Some(status) = state
+        .promises
+        .borrow()
+        .records
+        .get(&id)
+        .map(|record| record.status)
+    else {
+        return Ok(());
+    };
+    let raw_ctx = ctx.as_ptr();
+    let handler = |value: &Local| is_function(ctx, value.raw()).then(|| value.raw());
+    let rejected = match status {
+        Status::Pending => {
+            let reactions = local(ctx, unsafe { JS_GetUserValue(raw_ctx, promise.raw()) });
+            let len = array_len(ctx, &reactions).unwrap_or(0);
+            // Each element is read only after the previous one is stored,
+            // which may grow the array and move the values.
+            set_element(
+                ctx,
+                &reactions,
+                len,
+                handler(on_fulfilled).unwrap_or(undefined()),
+            )?;
+            set_element(
+                ctx,
+                &reactions,
+                len + 1,
+                handler(on_rejected).unwrap_or(undefined()),
+            )?;
+            let derived = derived.map_or(undefined(), |derived| derived.raw());
+            return set_element(ctx, &reactions, len + 2, derived);
+        }
+        Status::Fulfilled => false,
+        Status::Rejected => true,
+    };
+    let handler = if rejected {
+        handler(on_rejected)
+    } else {
+        handler(on_fulfilled)
+    };
+    let argument = unsafe { JS_GetUserValue(raw_ctx, promise.raw()) };
+    let job = Job::Reaction {
+        handler: handler.map(|handler| add_root(raw_ctx, handler)),
+        derived: derived.map(|derived| add_root(raw_ctx, derived.raw())),
+        argument: add_root(raw_ctx, argument),
+        rejected,
+    };
+    state.promises.borrow_mut().jobs.push_back(job);
+    Ok(())
+}
+
+/// Create a pending promise and its record.
+pub(crate) unsafe fn new_promise(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+) -> Result<Local, ()> {
+    let reactions = checked(ctx, unsafe { JS_NewArray(ctx.as_ptr(), 0) })?;
+    let promise = checked(ctx, unsafe {
+        JS_NewObjectClassUser(ctx.as_ptr(), JSHostClassEnum_JS_CLASS_PROMISE as c_int)
+    })?;
+    let id = {
+        let mut table = state.promises.borrow_mut();
+        for id in state.collected_promises.take() {
+            table.records.remove(&id);
+        }
+        table.insert()
+    };
+    unsafe {
+        JS_SetOpaque(ctx.as_ptr(), promise.raw(), id as *mut c_void);
+        JS_SetUserValue(ctx.as_ptr(), promise.raw(), reactions.raw());
+    }
+    Ok(promise)
+}
+
+/// Record id of `value` if it is a promise.
+fn promise_id(ctx: NonNull<JSContext>, value: JSValue) -> Option<usize> {
+    let class_id = unsafe { JS_GetClassID(ctx.as_ptr(), value) };
+    if class_id != JSHostClassEnum_JS_CLASS_PROMISE as c_int {
+        return None;
+    }
+    let id = unsafe { JS_GetOpaque(ctx.as_ptr(), value) } as usize;
+    (id != 0).then_some(id)
+}
+
+unsafe fn new_closure(
+    ctx: NonNull<JSContext>,
+    func: JSHostFunctionEnum,
+    params: &Local,
+) -> Result<Local, ()> {
+    checked(ctx, unsafe {
+        JS_NewCFunctionParams(ctx.as_ptr(), func as c_int, params.raw())
+    })
+}
+
+/// Take the pending exception, or `None` if it is uncatchable and must
+/// propagate.
+unsafe fn take_exception(ctx: NonNull<JSContext>) -> Option<Local> {
+    if unsafe { JS_IsUncatchableException(ctx.as_ptr()) } != 0 {
+        return None;
+    }
+    Some(local(ctx, unsafe { JS_GetException(ctx.as_ptr()) }))
+}
+
+/// A `TypeError` object, to reject a promise with.
+fn type_error(ctx: NonNull<JSContext>, message: &CStr) -> Local {
+    let _ = throw_type_error::<()>(ctx, message);
+    local(ctx, unsafe { JS_GetException(ctx.as_ptr()) })
+}
+
+/// Move a root held by the table into a `Local`.
+fn adopt(ctx: NonNull<JSContext>, mut gc_ref: Box<JSGCRef>) -> Local {
+    let value = local(ctx, gc_ref.val);
+    delete_root(ctx.as_ptr(), &mut gc_ref);
+    value
+}
+
+fn int(ctx: NonNull<JSContext>, value: i32) -> JSValue {
+    unsafe { JS_NewInt32(ctx.as_ptr(), value) }
+}
@@ -0,0 +1,112 @@
+//! Soft memory quotas enforced by the engine allocator.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Memory quota for a context, set with [`Context::set_memory_quota`](crate::Context::set_memory_quota).
+///
+/// The quota is independent of the memory buffer: usage is counted the same
+/// way, but an allocation that would exceed the quota after a GC throws a
+/// catchable `RangeError` in the script instead of an out-of-memory error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryQuota {
+    /// Bytes the context may use, including its header and stack.
+    pub limit_bytes: usize,
+    /// Usage levels, in percent of `limit_bytes`, reported to the callback
+    /// set with [`Context::on_memory_threshold`](crate::Context::on_memory_threshold).
+    pub thresholds: Vec<u8>,
+}
+
+impl MemoryQuota {
+    /// Quota of `limit_bytes` without thresholds.
+    pub fn new(limit_bytes: usize) -> Self {
+        Self {
+            limit_bytes,
+            thresholds: Vec::new(),
+        }
+    }
+
+    /// Add a threshold at `percent` of the quota.
+    pub fn threshold(mut self, percent: u8) -> Self {
+        self.thresholds.push(percent);
+        self
+    }
+}
+
+/// Threshold crossing reported to the callback of
+/// [`Context::on_memory_threshold`](crate::Context::on_memory_threshold).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaThreshold {
+    /// Threshold that was crossed, in percent of the quota.
+    pub percent: u8,
+    /// Bytes in use when the crossing was detected.
+    pub used_bytes: usize,
+    /// Quota the threshold belongs to.
+    pub limit_bytes: usize,
+}
+
+/// Quota of a context and the next threshold waiting to be crossed.
+///
+/// A threshold fires once, then is armed again when a GC brings usage back
+/// below it.
+#[derive(Clone)]
+pub(crate) struct QuotaState {
+    pub(crate) limit_bytes: usize,
+    percents: Vec<u8>,
+    next: usize,
+}
+
+impl QuotaState {
+    pub(crate) fn new(quota: MemoryQuota, used: usize) -> Self {
+        let mut percents = quota.thresholds;
+        percents.retain(|percent| *percent > 0);
+        percents.sort_unstable();
+        percents.dedup();
+        let mut state = Self {
+            limit_bytes: quota.limit_bytes,
+            percents,
+            next: 0,
+        };
+        state.next = state.crossed_count(used);
+        state
+    }
+
+    /// Byte level the engine should watch for, or 0 once every threshold
+    /// has fired.
+    pub(crate) fn armed_bytes(&self) -> usize {
+        self.percents
+            .get(self.next)
+            .map_or(0, |percent| self.bytes(*percent))
+    }
+
+    /// Advance past the thresholds crossed at `used` and return them.
+    pub(crate) fn cross(&mut self, used: usize) -> Range<usize> {
+        let start = self.next;
+        self.next = self.next.max(self.crossed_count(used));
+        start..self.next
+    }
+
+    /// Re-arm thresholds that usage fell back below.
+    pub(crate) fn rearm(&mut self, used: usize) {
+        self.next = self.next.min(self.crossed_count(used));
+    }
+
+    pub(crate) fn event(&self, index: usize, used: usize) -> QuotaThreshold {
+        QuotaThreshold {
+            percent: self.percents[index],
+            used_bytes: used,
+            limit_bytes: self.limit_bytes,
+        }
+    }
+
+    fn crossed_count(&self, used: usize) -> usize {
+        self.percents
+            .iter()
+            .take_while(|percent| self.bytes(**percent) < used)
+            .count()
+    }
+
+    fn bytes(&self, percent: u8) -> usize {
+        (self.limit_bytes as u128 * percent as u128 / 100) as usize
+    }
+}
@@ -1,8 +1,16 @@
-use std::marker::PhantomData;
-use std::ptr::NonNull;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::{CStr, c_int};
+use core::marker::PhantomData;
+use core::ptr::NonNull;
 
-use mquickjs_sys::{JS_AddGCRef, JSContext, JS_DeleteGCRef, JSGCRef};
+use mquickjs_sys::{
+    JS_AddGCRef, JS_Call, JS_DeleteGCRef, JS_GetClassID, JS_IsFunction, JS_NewArray, JS_PushArg,
+    JS_SetPropertyUint32, JS_StackCheck, JS_ThrowError, JSContext, JSGCRef,
+    JSObjectClassEnum_JS_CLASS_ARRAY, JSObjectClassEnum_JS_CLASS_TYPE_ERROR, JSValue,
+};
 
+use crate::convert::{array_length, is_exception, undefined};
 use crate::{Context, JsError, Value};
 
 /// A GC-rooted JavaScript value tied to a `Context` lifetime.
@@ -15,19 +23,13 @@ pub struct RootedValue<'ctx> {
 
 impl<'ctx> RootedValue<'ctx> {
     pub(crate) fn new(ctx: NonNull<JSContext>, value: Value<'ctx>) -> Self {
-        let mut gc_ref = Box::new(JSGCRef {
-            val: 0,
-            prev: std::ptr::null_mut(),
-        });
-
-        unsafe {
-            let slot = JS_AddGCRef(ctx.as_ptr(), &mut *gc_ref);
-            *slot = value.raw();
-        }
+        Self::from_raw(ctx, value.raw())
+    }
 
+    pub(crate) fn from_raw(ctx: NonNull<JSContext>, raw: JSValue) -> Self {
         Self {
             ctx,
-            gc_ref,
+            gc_ref: add_root(ctx.as_ptr(), raw),
             _marker: PhantomData,
         }
     }
@@ -36,6 +38,31 @@ impl<'ctx> RootedValue<'ctx> {
     pub fn to_value(&self) -> Value<'ctx> {
         Value::new(self.ctx, self.gc_ref.val)
     }
+
+    /// The current raw value, which follows the object when the GC moves it.
+    pub(crate) fn raw(&self) -> JSValue {
+        self.gc_ref.val
+    }
+}
+
+/// Root `raw` until [`delete_root`] unlinks it. Boxed because the context
+/// links to each `JSGCRef` by address.
+pub(crate) fn add_root(ctx: *mut JSContext, raw: JSValue) -> Box<JSGCRef> {
+    let mut gc_ref = Box::new(JSGCRef {
+        val: 0,
+        prev: core::ptr::null_mut(),
+    });
+    unsafe {
+        *JS_AddGCRef(ctx, &mut *gc_ref) = raw;
+    }
+    gc_ref
+}
+
+/// Unlink a root made by [`add_root`] from `ctx`.
+pub(crate) fn delete_root(ctx: *mut JSContext, gc_ref: &mut JSGCRef) {
+    unsafe {
+        JS_DeleteGCRef(ctx, gc_ref);
+    }
 }
 
 impl Drop for RootedValue<'_> {
@@ -56,9 +83,7 @@ impl<'ctx> Persistent<'ctx> {
     /// Create a persistent handle for the given value.
     pub fn new(ctx: &'ctx Context, value: Value<'ctx>) -> Result<Self, JsError> {
         if ctx.raw_ctx() != value.ctx() {
-            return Err(JsError::Conversion {
-                message: "value does not belong to context".to_string(),
-            });
+            return Err(JsError::conversion("value does not belong to context"));
         }
         Ok(Self {
             rooted: RootedValue::new(ctx.raw_ctx(), value),
@@ -79,3 +104,107 @@ impl<'ctx> Clone for Persistent<'ctx> {
         }
     }
 }
+
+/// A value rooted for the duration of a callback.
+pub(crate) type Local = RootedValue<'static>;
+
+/// Call `func` with `this` and `args`. `Err` leaves the exception pending.
+pub(crate) unsafe fn call(
+    ctx: NonNull<JSContext>,
+    func: &Local,
+    this: Option<&Local>,
+    args: &[&Local],
+) -> Result<Local, ()> {
+    let raw_ctx = ctx.as_ptr();
+    // Read the values only after the check, which may run a GC.
+    if unsafe { JS_StackCheck(raw_ctx, (args.len() + 2) as u32) } != 0 {
+        return Err(());
+    }
+    unsafe {
+        for arg in args.iter().rev() {
+            JS_PushArg(raw_ctx, arg.raw());
+        }
+        JS_PushArg(raw_ctx, func.raw());
+        JS_PushArg(raw_ctx, this.map_or(undefined(), |this| this.raw()));
+    }
+    checked(ctx, unsafe { JS_Call(raw_ctx, args.len() as c_int) })
+}
+
+/// Root the first `min` arguments, `undefined` for missing ones, and any
+/// after them.
+pub(crate) unsafe fn arguments(
+    ctx: NonNull<JSContext>,
+    argc: c_int,
+    argv: *mut JSValue,
+    min: usize,
+) -> Vec<Local> {
+    let args = if argv.is_null() || argc <= 0 {
+        &[][..]
+    } else {
+        unsafe { core::slice::from_raw_parts(argv, argc as usize) }
+    };
+    (0..args.len().max(min))
+        .map(|index| local(ctx, args.get(index).copied().unwrap_or(undefined())))
+        .collect()
+}
+
+pub(crate) fn array_len(ctx: NonNull<JSContext>, value: &Local) -> Option<u32> {
+    if unsafe { JS_GetClassID(ctx.as_ptr(), value.raw()) }
+        != JSObjectClassEnum_JS_CLASS_ARRAY as c_int
+    {
+        return None;
+    }
+    array_length(ctx, value.raw()).ok()
+}
+
+/// Create an array of `items`, read only after the array is allocated.
+pub(crate) fn new_array(ctx: NonNull<JSContext>, items: &[&Local]) -> Result<Local, ()> {
+    let array = checked(ctx, unsafe {
+        JS_NewArray(ctx.as_ptr(), items.len() as c_int)
+    })?;
+    for (index, item) in items.iter().enumerate() {
+        set_element(ctx, &array, index as u32, item.raw())?;
+    }
+    Ok(array)
+}
+
+pub(crate) fn set_element(
+    ctx: NonNull<JSContext>,
+    array: &Local,
+    index: u32,
+    value: JSValue,
+) -> Result<(), ()> {
+    let result = unsafe { JS_SetPropertyUint32(ctx.as_ptr(), array.raw(), index, value) };
+    if is_exception(result) {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn checked(ctx: NonNull<JSContext>, raw: JSValue) -> Result<Local, ()> {
+    if is_exception(raw) {
+        Err(())
+    } else {
+        Ok(local(ctx, raw))
+    }
+}
+
+pub(crate) fn throw_type_error<T>(ctx: NonNull<JSContext>, message: &CStr) -> Result<T, ()> {
+    unsafe {
+        JS_ThrowError(
+            ctx.as_ptr(),
+            JSObjectClassEnum_JS_CLASS_TYPE_ERROR,
+            message.as_ptr(),
+        );
+    }
+    Err(())
+}
+
+pub(crate) fn is_function(ctx: NonNull<JSContext>, value: JSValue) -> bool {
+    unsafe { JS_IsFunction(ctx.as_ptr(), value) != 0 }
+}
+
+pub(crate) fn local(ctx: NonNull<JSContext>, raw: JSValue) -> Local {
+    RootedValue::from_raw(ctx, raw)
+}
@@ -1,6 +1,9 @@
 use crate::{Context, JsError};
 
 /// JavaScript runtime configuration for creating contexts.
+///
+/// Only the memory size is configurable; [`ContextBuilder`](crate::ContextBuilder)
+/// covers the full set of options.
 #[derive(Debug, Clone, Copy)]
 pub struct Runtime {
     memory_bytes: usize,
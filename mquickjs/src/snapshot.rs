@@ -0,0 +1,43 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::func::Registry;
+use crate::heap::HeapGrowth;
+use crate::quota::QuotaState;
+
+/// Copy of a context heap taken with [`Context::snapshot`](crate::Context::snapshot).
+///
+/// Besides the heap, the image keeps the registered functions, disabled
+/// globals, heap growth policy, memory quota and random seed of the context.
+/// Callbacks such as the console sink or the clock are not part of it.
+///
+/// Any number of contexts can be created from one image, on any thread.
+#[derive(Clone)]
+pub struct HeapImage {
+    pub(crate) heap: Vec<usize>,
+    pub(crate) base: usize,
+    pub(crate) ctx_offset: usize,
+    /// Heap buffer of a context that outgrew its own buffer, and its address.
+    pub(crate) grown_heap: Option<Vec<usize>>,
+    pub(crate) grown_base: usize,
+    pub(crate) registry: Registry,
+    pub(crate) growth: Option<HeapGrowth>,
+    pub(crate) quota: Option<QuotaState>,
+    pub(crate) random_seed: Option<u64>,
+    pub(crate) disabled_globals: Vec<String>,
+}
+
+impl HeapImage {
+    /// Size of the copied heap in bytes.
+    pub fn byte_len(&self) -> usize {
+        let grown = self.grown_heap.as_ref().map_or(0, Vec::len);
+        (self.heap.len() + grown) * core::mem::size_of::<usize>()
+    }
+}
+
+impl core::fmt::Debug for HeapImage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HeapImage")
+            .field("heap_bytes", &self.byte_len())
+            .finish_non_exhaustive()
+    }
+}
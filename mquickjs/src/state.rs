@@ -0,0 +1,289 @@
+//! Per-context host state stored behind the context opaque pointer.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ffi::c_void;
+use core::ffi::c_int;
+use core::ptr::NonNull;
+use core::task::Waker;
+
+use mquickjs_sys::{
+    JSContext, JSHostHooks, JSMemoryEventEnum, JSMemoryEventEnum_JS_MEM_EVENT_GC,
+    JSMemoryEventEnum_JS_MEM_EVENT_LIMIT, JSMemoryEventEnum_JS_MEM_EVENT_THRESHOLD,
+    JS_GetContextOpaque, JS_GetMemorySize, JS_SetMemoryThreshold,
+};
+
+use crate::clock::{date_now_callback, performance_now_callback, Clock};
+use crate::collection::{collection_callback, Cursors};
+use crate::console::{console_callback, ConsoleSink};
+#[cfg(feature = "coroutine")]
+use crate::coroutine::Slot;
+use crate::error::JsError;
+use crate::func::{catch_panic, host_callback, Registry};
+use crate::heap::{move_heap, Heap, HeapGrowth};
+use crate::promise::{promise_callback, promise_finalizer, promise_function_callback, PromiseTable};
+use crate::quota::{QuotaState, QuotaThreshold};
+use crate::task::TaskQueue;
+use crate::timer::{clear_timer_callback, set_interval_callback, set_timeout_callback, TimerQueue};
+
+pub(crate) type InterruptHandler = dyn FnMut() -> bool + Send;
+pub(crate) type ThresholdHandler = dyn FnMut(QuotaThreshold) + Send;
+pub(crate) type LogSink = dyn FnMut(&str) + Send;
+
+/// State owned by a `Context` and reachable from C through `JS_GetContextOpaque`.
+#[repr(C)]
+pub(crate) struct ContextState {
+    // Must stay the first field: the stdlib stubs read it through the opaque pointer.
+    hooks: JSHostHooks,
+    pub(crate) registry: RefCell<Registry>,
+    pub(crate) interrupt: RefCell<Option<Box<InterruptHandler>>>,
+    /// Growth policy applied by `memory_handler`.
+    pub(crate) growth: Cell<Option<HeapGrowth>>,
+    /// Heap buffer of a context that grew out of its own buffer.
+    pub(crate) grown_heap: RefCell<Option<Heap>>,
+    pub(crate) quota: RefCell<Option<QuotaState>>,
+    pub(crate) on_threshold: RefCell<Option<Box<ThresholdHandler>>>,
+    pub(crate) random_seed: Cell<Option<u64>>,
+    pub(crate) log: RefCell<Option<Box<LogSink>>>,
+    /// Collects the engine's output instead of `log` while a value is formatted.
+    pub(crate) capture: RefCell<Option<String>>,
+    pub(crate) console: RefCell<Option<Box<dyn ConsoleSink>>>,
+    pub(crate) clock: RefCell<Option<Box<dyn Clock>>>,
+    pub(crate) timers: RefCell<TimerQueue>,
+    /// Deadline and waker last handed to `Clock::wake_at`.
+    pub(crate) timer_wake: RefCell<Option<(f64, Waker)>>,
+    pub(crate) promises: RefCell<PromiseTable>,
+    /// Promises collected while `promises` was borrowed, removed from it on
+    /// the next insert.
+    pub(crate) collected_promises: RefCell<Vec<usize>>,
+    /// `forEach` loops over `Map` and `Set` objects.
+    pub(crate) cursors: RefCell<Cursors>,
+    /// Futures of async host function calls.
+    pub(crate) tasks: RefCell<TaskQueue>,
+    #[cfg(feature = "coroutine")]
+    pub(crate) coroutine: Cell<Slot>,
+    /// Start times of `console.time` labels, in milliseconds.
+    pub(crate) console_timers: RefCell<BTreeMap<String, f64>>,
+    /// Globals deleted after the context is created or reset.
+    pub(crate) disabled_globals: RefCell<Vec<String>>,
+}
+
+impl ContextState {
+    pub(crate) fn new() -> Box<Self> {
+        Self::with_registry(Registry::new())
+    }
+
+    pub(crate) fn with_registry(registry: Registry) -> Box<Self> {
+        Box::new(Self {
+            hooks: JSHostHooks {
+                load: Some(host_callback),
+                console: Some(console_callback),
+                date_now: Some(date_now_callback),
+                performance_now: Some(performance_now_callback),
+                set_timeout: Some(set_timeout_callback),
+                set_interval: Some(set_interval_callback),
+                clear_timer: Some(clear_timer_callback),
+                promise: Some(promise_callback),
+                promise_function: Some(promise_function_callback),
+                promise_finalizer: Some(promise_finalizer),
+                collection: Some(collection_callback),
+            },
+            registry: RefCell::new(registry),
+            interrupt: RefCell::new(None),
+            growth: Cell::new(None),
+            grown_heap: RefCell::new(None),
+            quota: RefCell::new(None),
+            on_threshold: RefCell::new(None),
+            random_seed: Cell::new(None),
+            log: RefCell::new(None),
+            capture: RefCell::new(None),
+            console: RefCell::new(None),
+            clock: RefCell::new(default_clock()),
+            timers: RefCell::new(TimerQueue::default()),
+            timer_wake: RefCell::new(None),
+            promises: RefCell::new(PromiseTable::default()),
+            collected_promises: RefCell::new(Vec::new()),
+            cursors: RefCell::new(Cursors::default()),
+            tasks: RefCell::new(TaskQueue::default()),
+            #[cfg(feature = "coroutine")]
+            coroutine: Cell::new(Slot::Idle),
+            console_timers: RefCell::new(BTreeMap::new()),
+            disabled_globals: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Drop the registered functions and every setting made since the state
+    /// was created, keeping what the engine itself tracks.
+    #[cfg(feature = "std")]
+    pub(crate) fn clear_config(&mut self) {
+        *self.registry.get_mut() = Registry::new();
+        *self.interrupt.get_mut() = None;
+        self.growth.set(None);
+        *self.quota.get_mut() = None;
+        *self.on_threshold.get_mut() = None;
+        self.random_seed.set(None);
+        *self.log.get_mut() = None;
+        *self.console.get_mut() = None;
+        *self.clock.get_mut() = default_clock();
+        self.disabled_globals.get_mut().clear();
+    }
+
+    /// Return the state of `ctx`, if it was created by a `Context`.
+    ///
+    /// # Safety
+    ///
+    /// `ctx` must be a live context and the returned reference must not
+    /// outlive it.
+    pub(crate) unsafe fn from_raw<'a>(ctx: *mut JSContext) -> Option<&'a ContextState> {
+        if ctx.is_null() {
+            return None;
+        }
+        let opaque = unsafe { JS_GetContextOpaque(ctx) } as *const ContextState;
+        unsafe { opaque.as_ref() }
+    }
+
+    /// Wall time of the context's clock, if it has one.
+    pub(crate) fn now_ms(&self) -> Option<f64> {
+        let clock = self.clock.try_borrow().ok()?;
+        catch_panic(|| clock.as_ref().map(|clock| clock.now_ms())).flatten()
+    }
+
+    /// Monotonic time of the context's clock, if it has one.
+    pub(crate) fn monotonic_ms(&self) -> Option<f64> {
+        let clock = self.clock.try_borrow().ok()?;
+        catch_panic(|| clock.as_ref().map(|clock| clock.monotonic_ms())).flatten()
+    }
+
+    /// Have the clock wake `waker` at `deadline_ms`, unless it was already
+    /// asked to.
+    pub(crate) fn wake_at(&self, deadline_ms: f64, waker: &Waker) {
+        let mut scheduled = self.timer_wake.borrow_mut();
+        if scheduled
+            .as_ref()
+            .is_some_and(|(deadline, scheduled)| *deadline == deadline_ms && scheduled.will_wake(waker))
+        {
+            return;
+        }
+        if let Some(clock) = self.clock.borrow().as_ref() {
+            clock.wake_at(deadline_ms, waker);
+        }
+        *scheduled = Some((deadline_ms, waker.clone()));
+    }
+}
+
+#[cfg(feature = "std")]
+fn default_clock() -> Option<Box<dyn Clock>> {
+    Some(Box::new(crate::clock::SystemClock::new()))
+}
+
+#[cfg(not(feature = "std"))]
+fn default_clock() -> Option<Box<dyn Clock>> {
+    None
+}
+
+pub(crate) unsafe extern "C" fn interrupt_handler(
+    _ctx: *mut JSContext,
+    opaque: *mut c_void,
+) -> c_int {
+    let Some(state) = (unsafe { (opaque as *const ContextState).as_ref() }) else {
+        return 0;
+    };
+    let Ok(mut handler) = state.interrupt.try_borrow_mut() else {
+        return 0;
+    };
+    match handler.as_mut() {
+        // A panicking handler stops the script like one that asked to.
+        Some(handler) => catch_panic(handler).unwrap_or(true) as c_int,
+        None => 0,
+    }
+}
+
+/// The host state of `ctx`, for callbacks that report errors as `JsError`.
+pub(crate) fn context_state<'a>(ctx: NonNull<JSContext>) -> Result<&'a ContextState, JsError> {
+    unsafe { ContextState::from_raw(ctx.as_ptr()) }
+        .ok_or_else(|| JsError::conversion("context has no host state"))
+}
+
+/// Forward the engine's debug output to the log sink.
+pub(crate) unsafe extern "C" fn log_write(opaque: *mut c_void, buf: *const c_void, buf_len: usize) {
+    let Some(state) = (unsafe { (opaque as *const ContextState).as_ref() }) else {
+        return;
+    };
+    let bytes = unsafe { core::slice::from_raw_parts(buf as *const u8, buf_len) };
+    if let Ok(mut capture) = state.capture.try_borrow_mut()
+        && let Some(capture) = capture.as_mut()
+    {
+        capture.push_str(&String::from_utf8_lossy(bytes));
+        return;
+    }
+    let Ok(mut sink) = state.log.try_borrow_mut() else {
+        return;
+    };
+    if let Some(sink) = sink.as_mut() {
+        catch_panic(|| sink(&String::from_utf8_lossy(bytes)));
+    }
+}
+
+/// Apply the growth policy and report quota thresholds. Called by the
+/// engine after each GC, when an allocation does not fit and when usage
+/// crosses the armed threshold.
+pub(crate) unsafe extern "C" fn memory_handler(
+    ctx: *mut JSContext,
+    opaque: *mut c_void,
+    event: c_int,
+    used: usize,
+    needed: usize,
+) {
+    let Some(state) = (unsafe { (opaque as *const ContextState).as_ref() }) else {
+        return;
+    };
+    let event = event as JSMemoryEventEnum;
+    if (event == JSMemoryEventEnum_JS_MEM_EVENT_GC || event == JSMemoryEventEnum_JS_MEM_EVENT_LIMIT)
+        && let Some(growth) = state.growth.get()
+        && let Ok(mut grown) = state.grown_heap.try_borrow_mut()
+    {
+        let size = unsafe { JS_GetMemorySize(ctx) };
+        // Only the heap moves: the header and the stack keep the context
+        // buffer, and the heap buffer provides the rest of the new size.
+        // `used` and `needed` also count the header and the stack, so a
+        // heap buffer at least that large always fits.
+        let buffer = size - grown.as_ref().map_or(0, |heap| heap.byte_len());
+        let grow = event == JSMemoryEventEnum_JS_MEM_EVENT_LIMIT;
+        let heap_bytes = if grow {
+            let next = growth.grown_size(size, needed);
+            (next - buffer).max(needed).min(growth.max_bytes - buffer)
+        } else {
+            let next = growth.shrunk_size(size, used);
+            if next <= buffer { 0 } else { (next - buffer).max(used) }
+        };
+        let next = buffer + heap_bytes;
+        if (grow && next > size) || (!grow && next < size) {
+            unsafe {
+                move_heap(ctx, &mut grown, heap_bytes);
+            }
+        }
+    }
+    if event == JSMemoryEventEnum_JS_MEM_EVENT_GC || event == JSMemoryEventEnum_JS_MEM_EVENT_THRESHOLD {
+        let Ok(mut quota) = state.quota.try_borrow_mut() else {
+            return;
+        };
+        let Some(quota) = quota.as_mut() else {
+            return;
+        };
+        if event == JSMemoryEventEnum_JS_MEM_EVENT_GC {
+            quota.rearm(used);
+        } else if let Ok(mut callback) = state.on_threshold.try_borrow_mut() {
+            for index in quota.cross(used) {
+                if let Some(callback) = callback.as_mut() {
+                    catch_panic(|| callback(quota.event(index, used)));
+                }
+            }
+        }
+        unsafe {
+            JS_SetMemoryThreshold(ctx, quota.armed_bytes());
+        }
+    }
+}
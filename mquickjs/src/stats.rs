@@ -0,0 +1,153 @@
+//! Heap statistics.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+use mquickjs_sys::{
+    JSClassMemoryUsage, JSContext, JSHostClassEnum_JS_CLASS_MAP, JSHostClassEnum_JS_CLASS_PROMISE,
+    JSHostClassEnum_JS_CLASS_SET, JSMemoryUsage,
+    JSObjectClassEnum_JS_CLASS_USER, JS_ComputeMemoryUsage, JS_GetClassCount,
+};
+
+/// Names of the built-in classes, indexed by class id.
+const CLASS_NAMES: [&str; JSObjectClassEnum_JS_CLASS_USER as usize] = [
+    "Object",
+    "Array",
+    "CFunction",
+    "Closure",
+    "Number",
+    "Boolean",
+    "String",
+    "Date",
+    "RegExp",
+    "Error",
+    "EvalError",
+    "RangeError",
+    "ReferenceError",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+    "InternalError",
+    "ArrayBuffer",
+    "TypedArray",
+    "Uint8ClampedArray",
+    "Int8Array",
+    "Uint8Array",
+    "Int16Array",
+    "Uint16Array",
+    "Int32Array",
+    "Uint32Array",
+    "Float32Array",
+    "Float64Array",
+    "Symbol",
+];
+
+/// Snapshot of a context's memory usage, from [`Context::memory_stats`](crate::Context::memory_stats).
+///
+/// Sizes are in bytes and include blocks that are unreachable but not yet
+/// collected; run [`Context::gc`](crate::Context::gc) first to count live data only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemoryStats {
+    /// Size of the context memory buffer, including the separate heap
+    /// buffer of a growable context that outgrew it.
+    pub heap_size: usize,
+    /// Bytes in use by the context header, heap blocks and stack.
+    pub used_bytes: usize,
+    /// Bytes available for new allocations.
+    pub free_bytes: usize,
+    /// Bytes used by the JavaScript stack.
+    pub stack_bytes: usize,
+    /// Number of objects, all classes included.
+    pub object_count: usize,
+    /// Bytes used by object blocks, excluding their properties.
+    pub object_bytes: usize,
+    /// Number of heap strings.
+    pub string_count: usize,
+    /// Bytes used by heap strings.
+    pub string_bytes: usize,
+    /// Number of compiled functions.
+    pub bytecode_count: usize,
+    /// Bytes used by compiled functions and their bytecode.
+    pub bytecode_bytes: usize,
+    /// Objects per class, for classes with at least one object.
+    pub classes: Vec<ClassStats>,
+}
+
+/// Object count and size for one class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClassStats {
+    /// Engine class id.
+    pub class_id: u32,
+    /// Class name, or `"user"` for user classes other than `Promise`, `Map`
+    /// and `Set`.
+    pub name: &'static str,
+    /// Number of objects.
+    pub count: usize,
+    /// Bytes used by the object blocks.
+    pub bytes: usize,
+}
+
+impl MemoryStats {
+    pub(crate) fn collect(ctx: NonNull<JSContext>) -> Self {
+        let class_count = unsafe { JS_GetClassCount(ctx.as_ptr()) };
+        let mut classes = vec![JSClassMemoryUsage { count: 0, size: 0 }; class_count as usize];
+        let mut usage = JSMemoryUsage {
+            memory_size: 0,
+            free_size: 0,
+            stack_size: 0,
+            object_count: 0,
+            object_size: 0,
+            string_count: 0,
+            string_size: 0,
+            bytecode_count: 0,
+            bytecode_size: 0,
+        };
+        unsafe {
+            JS_ComputeMemoryUsage(ctx.as_ptr(), &mut usage, classes.as_mut_ptr(), class_count);
+        }
+
+        let classes = classes
+            .iter()
+            .enumerate()
+            .filter(|(_, class)| class.count > 0)
+            .map(|(class_id, class)| ClassStats {
+                class_id: class_id as u32,
+                name: class_name(class_id),
+                count: class.count,
+                bytes: class.size,
+            })
+            .collect();
+
+        Self {
+            heap_size: usage.memory_size,
+            used_bytes: usage.memory_size - usage.free_size,
+            free_bytes: usage.free_size,
+            stack_bytes: usage.stack_size,
+            object_count: usage.object_count,
+            object_bytes: usage.object_size,
+            string_count: usage.string_count,
+            string_bytes: usage.string_size,
+            bytecode_count: usage.bytecode_count,
+            bytecode_bytes: usage.bytecode_size,
+            classes,
+        }
+    }
+
+    /// Statistics for the class called `name`, if it has objects.
+    pub fn class(&self, name: &str) -> Option<&ClassStats> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+}
+
+fn class_name(class_id: usize) -> &'static str {
+    match CLASS_NAMES.get(class_id) {
+        Some(name) => name,
+        None if class_id == JSHostClassEnum_JS_CLASS_PROMISE as usize => "Promise",
+        None if class_id == JSHostClassEnum_JS_CLASS_MAP as usize => "Map",
+        None if class_id == JSHostClassEnum_JS_CLASS_SET as usize => "Set",
+        None => "user",
+    }
+}
@@ -0,0 +1,90 @@
+//! Conversions between JavaScript symbols and their descriptions.
+
+use alloc::string::String;
+use core::ffi::c_char;
+
+use mquickjs_sys::{JS_GetPropertyStr, JS_NewStringLen, JS_NewSymbol, JS_TAG_UNDEFINED, JSValue};
+
+use crate::convert::is_exception;
+use crate::{Context, FromValue, IntoValue, JsError, Value, ValueKind};
+
+/// Description of a JavaScript symbol.
+///
+/// Symbols are unique, so converting a `Symbol` into a value creates a new
+/// symbol each time, distinct from every other one even with the same
+/// description. Converting a symbol value back only reads its description.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    description: Option<String>,
+}
+
+impl Symbol {
+    /// Symbol with the given description, like `Symbol(description)`.
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: Some(description.into()),
+        }
+    }
+
+    /// Description of the symbol, `None` for `Symbol()`.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(description: &str) -> Self {
+        Self::new(description)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(description: String) -> Self {
+        Self::new(description)
+    }
+}
+
+/// Creates a new unique symbol.
+impl<'ctx> IntoValue<'ctx> for Symbol {
+    fn into_value(self, ctx: &'ctx Context) -> Result<Value<'ctx>, JsError> {
+        let raw_ctx = ctx.raw_ctx().as_ptr();
+        let description = match &self.description {
+            Some(description) => {
+                let raw = unsafe {
+                    JS_NewStringLen(
+                        raw_ctx,
+                        description.as_ptr() as *const c_char,
+                        description.len(),
+                    )
+                };
+                if is_exception(raw) {
+                    return Err(JsError::conversion("failed to convert string"));
+                }
+                raw
+            }
+            None => JS_TAG_UNDEFINED as JSValue,
+        };
+        let raw = unsafe { JS_NewSymbol(raw_ctx, description) };
+        if is_exception(raw) {
+            return Err(JsError::conversion("failed to create symbol"));
+        }
+        Ok(Value::new(ctx.raw_ctx(), raw))
+    }
+}
+
+/// Reads the description of a symbol.
+impl<'ctx> FromValue<'ctx> for Symbol {
+    fn from_value(value: Value<'ctx>) -> Result<Self, JsError> {
+        if value.kind() != ValueKind::Symbol {
+            return Err(JsError::type_mismatch("expected symbol", value.type_name()));
+        }
+        let raw = unsafe {
+            JS_GetPropertyStr(value.ctx().as_ptr(), value.raw(), c"description".as_ptr())
+        };
+        if is_exception(raw) {
+            return Err(JsError::conversion("failed to get property 'description'"));
+        }
+        let description = Option::<String>::from_value(Value::new(value.ctx(), raw))?;
+        Ok(Self { description })
+    }
+}
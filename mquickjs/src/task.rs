@@ -0,0 +1,132 @@
+//! Futures of async host functions, driven by
+//! [`Context::poll_event_loop`](crate::Context::poll_event_loop).
+//!
+//! Calling an async host function starts a task: the boxed future and the
+//! `Promise` returned to the script, rooted until the future completes.
+
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::task::{self, Poll};
+
+use mquickjs_sys::{JSContext, JSGCRef, JSValue};
+
+use crate::context::exception_error;
+use crate::data::JsData;
+use crate::func::{catch_panic, AsyncCallback};
+use crate::promise::new_promise;
+use crate::rooted::{add_root, delete_root};
+use crate::state::ContextState;
+use crate::{Context, FromValue, JsError, Promise, Value};
+
+pub(crate) type HostFuture = Pin<Box<dyn Future<Output = Result<JsData, JsError>> + Send>>;
+
+/// A running async host function call.
+pub(crate) struct HostTask {
+    future: HostFuture,
+    promise: Box<JSGCRef>,
+}
+
+/// Pending tasks of a context, in the order they were started.
+#[derive(Default)]
+pub(crate) struct TaskQueue {
+    tasks: Vec<HostTask>,
+}
+
+impl TaskQueue {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Drop every task without touching the context, which is gone.
+    pub(crate) fn forget(&mut self) {
+        self.tasks.clear();
+    }
+}
+
+/// Call `callback` with copies of `args` and return the promise settled by
+/// its future.
+///
+/// # Safety
+///
+/// `ctx` must be the live context that owns `state`.
+pub(crate) unsafe fn spawn(
+    ctx: NonNull<JSContext>,
+    state: &ContextState,
+    callback: &AsyncCallback,
+    args: &[Value<'_>],
+) -> Result<JSValue, JsError> {
+    // Copy the arguments before allocating the promise moves them.
+    let args = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| JsData::from_value(*arg).map_err(|err| err.at_index(index)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let future = callback(args);
+    let promise = unsafe { new_promise(ctx, state) }.map_err(|()| exception_error(ctx.as_ptr()))?;
+    state.tasks.borrow_mut().tasks.push(HostTask {
+        future,
+        promise: add_root(ctx.as_ptr(), promise.raw()),
+    });
+    Ok(promise.raw())
+}
+
+/// Poll every task once and settle the promises of those that completed.
+/// Returns how many completed.
+pub(crate) fn poll_tasks(
+    ctx: &Context,
+    state: &ContextState,
+    cx: &mut task::Context<'_>,
+) -> Result<usize, JsError> {
+    // Taken out so the futures are polled without the queue borrowed.
+    let tasks = core::mem::take(&mut state.tasks.borrow_mut().tasks);
+    let mut pending = Vec::with_capacity(tasks.len());
+    let mut finished = Vec::new();
+    for mut task in tasks {
+        let poll = catch_panic(|| task.future.as_mut().poll(cx)).unwrap_or_else(|| {
+            Poll::Ready(Err(JsError::Callback {
+                message: "callback panicked".to_string(),
+            }))
+        });
+        match poll {
+            Poll::Ready(outcome) => finished.push((task.promise, outcome)),
+            Poll::Pending => pending.push(task),
+        }
+    }
+    {
+        let mut queue = state.tasks.borrow_mut();
+        pending.append(&mut queue.tasks);
+        queue.tasks = pending;
+    }
+
+    let count = finished.len();
+    let mut result = Ok(count);
+    for (promise, outcome) in finished {
+        if let Err(err) = settle(ctx, promise, outcome)
+            && result.is_ok()
+        {
+            result = Err(err);
+        }
+    }
+    result
+}
+
+/// Fulfill the promise of a completed task with its data, or reject it with
+/// the error message as host functions throw it.
+fn settle(
+    ctx: &Context,
+    mut promise: Box<JSGCRef>,
+    outcome: Result<JsData, JsError>,
+) -> Result<(), JsError> {
+    let value = Value::new(ctx.raw_ctx(), promise.val);
+    let promise_handle = Promise::from_value(ctx, value);
+    delete_root(ctx.raw_ctx().as_ptr(), &mut promise);
+    let promise = promise_handle?;
+    match outcome.and_then(|data| data.to_value(ctx)) {
+        Ok(value) => promise.resolve(value),
+        Err(err) => promise.reject(err.to_string().as_str()),
+    }
+}
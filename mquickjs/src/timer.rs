@@ -0,0 +1,168 @@
+//! `setTimeout`/`setInterval` timers, run from Rust with
+//! [`Context::run_timers`](crate::Context::run_timers).
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+
+use mquickjs_sys::{
+    JSContext, JSGCRef, JSObjectClassEnum_JS_CLASS_TYPE_ERROR, JSValue, JS_IsFunction,
+    JS_NewInt32, JS_ThrowError, JS_ToInt32, JS_ToNumber, JS_TAG_EXCEPTION, JS_TAG_UNDEFINED,
+};
+
+use crate::rooted::{add_root, delete_root};
+use crate::state::ContextState;
+
+/// A scheduled callback. The function and its extra arguments are GC roots
+/// until the timer is removed.
+pub(crate) struct Timer {
+    pub(crate) deadline: f64,
+    pub(crate) interval: Option<f64>,
+    pub(crate) func: Box<JSGCRef>,
+    // Boxed because the context links to each `JSGCRef` by address.
+    #[allow(clippy::vec_box)]
+    pub(crate) args: Vec<Box<JSGCRef>>,
+}
+
+impl Timer {
+    /// Unlink the GC roots from `ctx`.
+    pub(crate) fn release(mut self, ctx: *mut JSContext) {
+        for arg in self.args.iter_mut().rev() {
+            delete_root(ctx, arg);
+        }
+        delete_root(ctx, &mut self.func);
+    }
+}
+
+/// Pending timers of a context, by id.
+#[derive(Default)]
+pub(crate) struct TimerQueue {
+    next_id: i32,
+    timers: BTreeMap<i32, Timer>,
+}
+
+impl TimerQueue {
+    fn insert(&mut self, timer: Timer) -> i32 {
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        while self.timers.contains_key(&self.next_id) {
+            self.next_id += 1;
+        }
+        self.timers.insert(self.next_id, timer);
+        self.next_id
+    }
+
+    pub(crate) fn remove(&mut self, id: i32) -> Option<Timer> {
+        self.timers.remove(&id)
+    }
+
+    /// Remove timer `id` and unlink its roots from `ctx`.
+    pub(crate) fn clear(&mut self, ctx: *mut JSContext, id: i32) {
+        if let Some(timer) = self.timers.remove(&id) {
+            timer.release(ctx);
+        }
+    }
+
+    /// Put an interval timer back after it fired.
+    pub(crate) fn reschedule(&mut self, id: i32, timer: Timer) {
+        self.timers.insert(id, timer);
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<f64> {
+        self.timers.values().map(|timer| timer.deadline).reduce(f64::min)
+    }
+
+    /// Ids of the timers due at `now`, earliest deadline first.
+    pub(crate) fn due(&self, now: f64) -> Vec<i32> {
+        let mut due: Vec<(f64, i32)> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .map(|(id, timer)| (timer.deadline, *id))
+            .collect();
+        due.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        due.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Forget every timer without touching the context, which is gone.
+    pub(crate) fn forget(&mut self) {
+        self.timers.clear();
+    }
+}
+
+pub(crate) unsafe extern "C" fn set_timeout_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+) -> JSValue {
+    unsafe { schedule(ctx, argc, argv, false) }
+}
+
+pub(crate) unsafe extern "C" fn set_interval_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+) -> JSValue {
+    unsafe { schedule(ctx, argc, argv, true) }
+}
+
+pub(crate) unsafe extern "C" fn clear_timer_callback(
+    ctx: *mut JSContext,
+    _this_val: *mut JSValue,
+    argc: c_int,
+    argv: *mut JSValue,
+) -> JSValue {
+    let undefined = JS_TAG_UNDEFINED as JSValue;
+    let Some(state) = (unsafe { ContextState::from_raw(ctx) }) else {
+        return undefined;
+    };
+    let mut id = 0;
+    if argc > 0 && unsafe { JS_ToInt32(ctx, &mut id, *argv) } == 0
+        && let Ok(mut timers) = state.timers.try_borrow_mut()
+    {
+        timers.clear(ctx, id);
+    }
+    undefined
+}
+
+/// `setTimeout(func, delay, ...args)` and `setInterval`: root `func` and the
+/// extra arguments and return the new timer id.
+unsafe fn schedule(ctx: *mut JSContext, argc: c_int, argv: *mut JSValue, repeat: bool) -> JSValue {
+    let Some(state) = (unsafe { ContextState::from_raw(ctx) }) else {
+        return JS_TAG_UNDEFINED as JSValue;
+    };
+    let args = if argv.is_null() || argc <= 0 {
+        &[][..]
+    } else {
+        unsafe { core::slice::from_raw_parts(argv, argc as usize) }
+    };
+    if args.first().is_none_or(|func| unsafe { JS_IsFunction(ctx, *func) } == 0) {
+        return unsafe { JS_ThrowError(ctx, JSObjectClassEnum_JS_CLASS_TYPE_ERROR, c"not a function".as_ptr()) };
+    }
+    let mut delay = 0f64;
+    if let Some(value) = args.get(1)
+        && unsafe { JS_ToNumber(ctx, &mut delay, *value) } != 0
+    {
+        return JS_TAG_EXCEPTION as JSValue;
+    }
+    // Like browsers, negative and NaN delays mean "as soon as possible",
+    // but an interval waits at least 1ms so it cannot run back to back.
+    let min = if repeat { 1.0 } else { 0.0 };
+    let delay = if delay > min { delay } else { min };
+
+    // Read `func` only now: converting the delay may run a GC that moves it.
+    let timer = Timer {
+        deadline: state.monotonic_ms().unwrap_or(0.0) + delay,
+        interval: repeat.then_some(delay),
+        func: add_root(ctx, args[0]),
+        args: args.iter().skip(2).map(|arg| add_root(ctx, *arg)).collect(),
+    };
+    let Ok(mut timers) = state.timers.try_borrow_mut() else {
+        timer.release(ctx);
+        return JS_TAG_UNDEFINED as JSValue;
+    };
+    let id = timers.insert(timer);
+    unsafe { JS_NewInt32(ctx, id) }
+}
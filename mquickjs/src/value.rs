@@ -1,12 +1,166 @@
-use std::marker::PhantomData;
-use std::ptr::NonNull;
+use alloc::string::String;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
 
 use mquickjs_sys::{
-    JSCStringBuf, JSContext, JSValue, JS_IsNumber, JS_IsString, JS_TAG_BOOL,
-    JS_TAG_SPECIAL_BITS, JS_ToCStringLen, JS_ToInt32, JS_ToNumber,
+    JSCStringBuf, JSContext, JSValue, JSObjectClassEnum, JSObjectClassEnum_JS_CLASS_ARRAY,
+    JSObjectClassEnum_JS_CLASS_ARRAY_BUFFER, JSObjectClassEnum_JS_CLASS_CLOSURE,
+    JSObjectClassEnum_JS_CLASS_C_FUNCTION, JSObjectClassEnum_JS_CLASS_DATE,
+    JSObjectClassEnum_JS_CLASS_ERROR, JSObjectClassEnum_JS_CLASS_FLOAT32_ARRAY,
+    JSObjectClassEnum_JS_CLASS_FLOAT64_ARRAY, JSObjectClassEnum_JS_CLASS_INT16_ARRAY,
+    JSObjectClassEnum_JS_CLASS_INT32_ARRAY, JSObjectClassEnum_JS_CLASS_INT8_ARRAY,
+    JSObjectClassEnum_JS_CLASS_INTERNAL_ERROR, JSObjectClassEnum_JS_CLASS_REGEXP,
+    JSObjectClassEnum_JS_CLASS_SYMBOL,
+    JSObjectClassEnum_JS_CLASS_UINT16_ARRAY, JSObjectClassEnum_JS_CLASS_UINT32_ARRAY,
+    JSObjectClassEnum_JS_CLASS_UINT8C_ARRAY, JSObjectClassEnum_JS_CLASS_UINT8_ARRAY,
+    JS_GetClassID, JS_IsFunction, JS_IsNumber, JS_IsString, JS_TAG_BOOL, JS_TAG_NULL,
+    JS_TAG_SPECIAL_BITS, JS_TAG_UNDEFINED, JS_ToCStringLen, JS_ToInt32, JS_ToNumber,
 };
 
+use crate::clone::ClonedValue;
 use crate::error::JsError;
+use crate::Context;
+
+/// Runtime type of a JavaScript value.
+///
+/// Primitives are distinguished by their tag bits and objects by their engine
+/// class. Classes without a dedicated kind, including user classes, report `Object`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValueKind {
+    Undefined,
+    Null,
+    Bool,
+    /// Number stored as a small integer.
+    Int,
+    /// Number stored as a floating point value.
+    Float,
+    String,
+    /// Unique symbol created by `Symbol()`, see [`Symbol`](crate::Symbol).
+    Symbol,
+    /// Plain object or instance of a class without a dedicated kind.
+    Object,
+    Array,
+    Function,
+    /// Instance of `Error` or one of its subclasses.
+    Error,
+    Date,
+    RegExp,
+    ArrayBuffer,
+    Uint8ClampedArray,
+    Int8Array,
+    Uint8Array,
+    Int16Array,
+    Uint16Array,
+    Int32Array,
+    Uint32Array,
+    Float32Array,
+    Float64Array,
+    /// Engine-internal value that scripts never see, such as the exception
+    /// marker.
+    Internal,
+}
+
+impl ValueKind {
+    /// Return true for `Int` and `Float`.
+    pub fn is_number(self) -> bool {
+        matches!(self, ValueKind::Int | ValueKind::Float)
+    }
+
+    /// Return true for every kind backed by a JS object, including arrays and
+    /// functions.
+    pub fn is_object(self) -> bool {
+        !matches!(
+            self,
+            ValueKind::Undefined
+                | ValueKind::Null
+                | ValueKind::Bool
+                | ValueKind::Int
+                | ValueKind::Float
+                | ValueKind::String
+                | ValueKind::Symbol
+                | ValueKind::Internal
+        )
+    }
+
+    /// Return true for the typed array kinds.
+    pub fn is_typed_array(self) -> bool {
+        matches!(
+            self,
+            ValueKind::Uint8ClampedArray
+                | ValueKind::Int8Array
+                | ValueKind::Uint8Array
+                | ValueKind::Int16Array
+                | ValueKind::Uint16Array
+                | ValueKind::Int32Array
+                | ValueKind::Uint32Array
+                | ValueKind::Float32Array
+                | ValueKind::Float64Array
+        )
+    }
+
+    /// Short name of the kind, as used in conversion errors.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ValueKind::Undefined => "undefined",
+            ValueKind::Null => "null",
+            ValueKind::Bool => "boolean",
+            ValueKind::Int | ValueKind::Float => "number",
+            ValueKind::String => "string",
+            ValueKind::Symbol => "symbol",
+            ValueKind::Object => "object",
+            ValueKind::Array => "array",
+            ValueKind::Function => "function",
+            ValueKind::Error => "error",
+            ValueKind::Date => "date",
+            ValueKind::RegExp => "regexp",
+            ValueKind::ArrayBuffer => "ArrayBuffer",
+            ValueKind::Uint8ClampedArray => "Uint8ClampedArray",
+            ValueKind::Int8Array => "Int8Array",
+            ValueKind::Uint8Array => "Uint8Array",
+            ValueKind::Int16Array => "Int16Array",
+            ValueKind::Uint16Array => "Uint16Array",
+            ValueKind::Int32Array => "Int32Array",
+            ValueKind::Uint32Array => "Uint32Array",
+            ValueKind::Float32Array => "Float32Array",
+            ValueKind::Float64Array => "Float64Array",
+            ValueKind::Internal => "internal",
+        }
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn from_class_id(class_id: JSObjectClassEnum) -> Self {
+        match class_id {
+            JSObjectClassEnum_JS_CLASS_ARRAY => ValueKind::Array,
+            JSObjectClassEnum_JS_CLASS_C_FUNCTION | JSObjectClassEnum_JS_CLASS_CLOSURE => {
+                ValueKind::Function
+            }
+            JSObjectClassEnum_JS_CLASS_DATE => ValueKind::Date,
+            JSObjectClassEnum_JS_CLASS_REGEXP => ValueKind::RegExp,
+            JSObjectClassEnum_JS_CLASS_SYMBOL => ValueKind::Symbol,
+            JSObjectClassEnum_JS_CLASS_ERROR..=JSObjectClassEnum_JS_CLASS_INTERNAL_ERROR => {
+                ValueKind::Error
+            }
+            JSObjectClassEnum_JS_CLASS_ARRAY_BUFFER => ValueKind::ArrayBuffer,
+            JSObjectClassEnum_JS_CLASS_UINT8C_ARRAY => ValueKind::Uint8ClampedArray,
+            JSObjectClassEnum_JS_CLASS_INT8_ARRAY => ValueKind::Int8Array,
+            JSObjectClassEnum_JS_CLASS_UINT8_ARRAY => ValueKind::Uint8Array,
+            JSObjectClassEnum_JS_CLASS_INT16_ARRAY => ValueKind::Int16Array,
+            JSObjectClassEnum_JS_CLASS_UINT16_ARRAY => ValueKind::Uint16Array,
+            JSObjectClassEnum_JS_CLASS_INT32_ARRAY => ValueKind::Int32Array,
+            JSObjectClassEnum_JS_CLASS_UINT32_ARRAY => ValueKind::Uint32Array,
+            JSObjectClassEnum_JS_CLASS_FLOAT32_ARRAY => ValueKind::Float32Array,
+            JSObjectClassEnum_JS_CLASS_FLOAT64_ARRAY => ValueKind::Float64Array,
+            _ => ValueKind::Object,
+        }
+    }
+}
+
+impl core::fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 /// Opaque handle to a JavaScript value tied to a `Context`.
 #[derive(Debug, Clone, Copy)]
@@ -38,17 +192,13 @@ impl<'ctx> Value<'ctx> {
         let ctx = self.ctx.as_ptr();
         let is_number = unsafe { JS_IsNumber(ctx, self.raw) };
         if is_number == 0 {
-            return Err(JsError::Conversion {
-                message: "expected number".to_string(),
-            });
+            return Err(JsError::type_mismatch("expected number", self.type_name()));
         }
 
         let mut out = 0i32;
         let status = unsafe { JS_ToInt32(ctx, &mut out, self.raw) };
         if status != 0 {
-            return Err(JsError::Conversion {
-                message: "failed to convert to i32".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert to i32"));
         }
         Ok(out)
     }
@@ -57,10 +207,8 @@ impl<'ctx> Value<'ctx> {
     pub fn to_bool(&self) -> Result<bool, JsError> {
         let tag_mask = (1u64 << JS_TAG_SPECIAL_BITS) - 1;
         let tag = (self.raw & tag_mask) as u32;
-        if tag != JS_TAG_BOOL as u32 {
-            return Err(JsError::Conversion {
-                message: "expected bool".to_string(),
-            });
+        if tag != JS_TAG_BOOL {
+            return Err(JsError::type_mismatch("expected bool", self.type_name()));
         }
 
         Ok((self.raw >> JS_TAG_SPECIAL_BITS) != 0)
@@ -71,17 +219,13 @@ impl<'ctx> Value<'ctx> {
         let ctx = self.ctx.as_ptr();
         let is_number = unsafe { JS_IsNumber(ctx, self.raw) };
         if is_number == 0 {
-            return Err(JsError::Conversion {
-                message: "expected number".to_string(),
-            });
+            return Err(JsError::type_mismatch("expected number", self.type_name()));
         }
 
         let mut out = 0f64;
         let status = unsafe { JS_ToNumber(ctx, &mut out, self.raw) };
         if status != 0 {
-            return Err(JsError::Conversion {
-                message: "failed to convert to f64".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert to f64"));
         }
         Ok(out)
     }
@@ -91,21 +235,108 @@ impl<'ctx> Value<'ctx> {
         let ctx = self.ctx.as_ptr();
         let is_string = unsafe { JS_IsString(ctx, self.raw) };
         if is_string == 0 {
-            return Err(JsError::Conversion {
-                message: "expected string".to_string(),
-            });
+            return Err(JsError::type_mismatch("expected string", self.type_name()));
         }
 
         let mut buf = JSCStringBuf { buf: [0u8; 5] };
         let mut len = 0usize;
         let ptr = unsafe { JS_ToCStringLen(ctx, &mut len, self.raw, &mut buf) };
         if ptr.is_null() {
-            return Err(JsError::Conversion {
-                message: "failed to convert to string".to_string(),
-            });
+            return Err(JsError::conversion("failed to convert to string"));
         }
 
-        let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+        let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, len) };
         Ok(String::from_utf8_lossy(bytes).into_owned())
     }
+
+    /// Return the runtime type of the value.
+    pub fn kind(&self) -> ValueKind {
+        let ctx = self.ctx.as_ptr();
+        if self.raw & 1 == 0 {
+            return ValueKind::Int;
+        }
+        let tag_mask = (1u64 << JS_TAG_SPECIAL_BITS) - 1;
+        let tag = (self.raw & tag_mask) as u32;
+        if tag == JS_TAG_BOOL {
+            ValueKind::Bool
+        } else if tag == JS_TAG_NULL {
+            ValueKind::Null
+        } else if tag == JS_TAG_UNDEFINED {
+            ValueKind::Undefined
+        } else if unsafe { JS_IsNumber(ctx, self.raw) } != 0 {
+            ValueKind::Float
+        } else if unsafe { JS_IsString(ctx, self.raw) } != 0 {
+            ValueKind::String
+        } else if unsafe { JS_IsFunction(ctx, self.raw) } != 0 {
+            ValueKind::Function
+        } else {
+            match unsafe { JS_GetClassID(ctx, self.raw) } {
+                id if id >= 0 => ValueKind::from_class_id(id as JSObjectClassEnum),
+                _ => ValueKind::Internal,
+            }
+        }
+    }
+
+    /// Return true if the value is `undefined`.
+    pub fn is_undefined(&self) -> bool {
+        self.kind() == ValueKind::Undefined
+    }
+
+    /// Return true if the value is `null`.
+    pub fn is_null(&self) -> bool {
+        self.kind() == ValueKind::Null
+    }
+
+    /// Return true if the value is a boolean.
+    pub fn is_bool(&self) -> bool {
+        self.kind() == ValueKind::Bool
+    }
+
+    /// Return true if the value is a number.
+    pub fn is_number(&self) -> bool {
+        self.kind().is_number()
+    }
+
+    /// Return true if the value is a string.
+    pub fn is_string(&self) -> bool {
+        self.kind() == ValueKind::String
+    }
+
+    /// Return true if the value is a symbol.
+    pub fn is_symbol(&self) -> bool {
+        self.kind() == ValueKind::Symbol
+    }
+
+    /// Return true if the value is any kind of object.
+    pub fn is_object(&self) -> bool {
+        self.kind().is_object()
+    }
+
+    /// Return true if the value is an array.
+    pub fn is_array(&self) -> bool {
+        self.kind() == ValueKind::Array
+    }
+
+    /// Return true if the value is a function.
+    pub fn is_function(&self) -> bool {
+        self.kind() == ValueKind::Function
+    }
+
+    /// Return true if the value is an `Error` instance.
+    pub fn is_error(&self) -> bool {
+        self.kind() == ValueKind::Error
+    }
+
+    /// Copy this value into `ctx` using the structured clone algorithm.
+    ///
+    /// Shared references and cycles are preserved. Functions and symbols fail
+    /// with a conversion error.
+    pub fn clone_into<'a>(&self, ctx: &'a Context) -> Result<Value<'a>, JsError> {
+        ClonedValue::from_value(*self)?.to_value(ctx)
+    }
+
+    /// Name of the value's JavaScript type, as used in conversion errors.
+    pub(crate) fn type_name(&self) -> &'static str {
+        self.kind().as_str()
+    }
 }
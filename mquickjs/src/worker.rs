@@ -0,0 +1,256 @@
+use std::boxed::Box;
+use std::format;
+use std::string::ToString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{ClonedValue, Context, Function, JsError, Value};
+
+const PRELUDE_SOURCE: &str = "globalThis.onmessage = null;\nglobalThis.postMessage = function (message) { __postMessage(message); };";
+
+const DISPATCH_SOURCE: &str = "(function (message) {\n  if (typeof onmessage === 'function') onmessage({ data: message });\n})";
+
+type MessageHandler = Box<dyn FnMut(ClonedValue) + Send>;
+
+enum Command {
+    Message(ClonedValue),
+    Terminate,
+}
+
+/// Context running a script on its own OS thread.
+///
+/// Messages are structured clones. Inside the worker, `postMessage(value)`
+/// sends to the owner and `onmessage` receives `{ data }` events.
+pub struct Worker {
+    commands: Sender<Command>,
+    events: Receiver<Result<ClonedValue, JsError>>,
+    handler: Arc<Mutex<Option<MessageHandler>>>,
+    interrupted: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Spawn a worker with the default memory size (1 MiB) running `script`.
+    pub fn new(script: &str) -> Result<Self, JsError> {
+        Self::with_memory(1024 * 1024, script)
+    }
+
+    /// Spawn a worker with a custom memory size running `script`.
+    ///
+    /// Exceptions thrown by the script or by `onmessage` are delivered as
+    /// errors from [`Worker::recv`]. A failing script stops the worker.
+    pub fn with_memory(memory_bytes: usize, script: &str) -> Result<Self, JsError> {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let handler: Arc<Mutex<Option<MessageHandler>>> = Arc::new(Mutex::new(None));
+        let interrupted = Arc::new(AtomicBool::new(false));
+
+        let script = script.to_string();
+        let worker_handler = Arc::clone(&handler);
+        let worker_interrupted = Arc::clone(&interrupted);
+        let thread = thread::Builder::new()
+            .name("mquickjs-worker".to_string())
+            .spawn(move || {
+                let ctx = match Context::new(memory_bytes) {
+                    Ok(ctx) => ctx,
+                    Err(err) => {
+                        let _ = ready_tx.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+                run_worker(
+                    &ctx,
+                    &script,
+                    command_rx,
+                    event_tx,
+                    worker_handler,
+                    worker_interrupted,
+                );
+            })
+            .map_err(|err| JsError::Runtime {
+                message: format!("failed to spawn worker thread: {err}"),
+            })?;
+
+        let ready = ready_rx.recv().map_err(|_| JsError::Runtime {
+            message: "worker thread exited during startup".to_string(),
+        })?;
+        if let Err(err) = ready {
+            let _ = thread.join();
+            return Err(err);
+        }
+
+        Ok(Self {
+            commands: command_tx,
+            events: event_rx,
+            handler,
+            interrupted,
+            thread: Some(thread),
+        })
+    }
+
+    /// Send a message to the worker's `onmessage` handler.
+    pub fn post_message(&self, message: ClonedValue) -> Result<(), JsError> {
+        self.commands
+            .send(Command::Message(message))
+            .map_err(|_| worker_exited())
+    }
+
+    /// Handle messages posted by the worker on the worker thread.
+    ///
+    /// Once a handler is installed, messages are no longer queued for
+    /// [`Worker::recv`]. Errors are still queued.
+    pub fn on_message<F>(&self, handler: F)
+    where
+        F: FnMut(ClonedValue) + Send + 'static,
+    {
+        *lock(&self.handler) = Some(Box::new(handler));
+    }
+
+    /// Block until the worker posts a message or reports an error.
+    pub fn recv(&self) -> Result<ClonedValue, JsError> {
+        self.events.recv().map_err(|_| worker_exited())?
+    }
+
+    /// Return the next queued message without blocking.
+    pub fn try_recv(&self) -> Option<Result<ClonedValue, JsError>> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(worker_exited())),
+        }
+    }
+
+    /// Wait up to `timeout` for the next message.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Result<ClonedValue, JsError>> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(worker_exited())),
+        }
+    }
+
+    /// Interrupt any running script and wait for the worker thread to exit.
+    pub fn terminate(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+        let _ = self.commands.send(Command::Terminate);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl std::fmt::Debug for Worker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Worker")
+            .field("interrupted", &self.interrupted.load(Ordering::SeqCst))
+            .finish_non_exhaustive()
+    }
+}
+
+fn run_worker(
+    ctx: &Context,
+    script: &str,
+    commands: Receiver<Command>,
+    events: Sender<Result<ClonedValue, JsError>>,
+    handler: Arc<Mutex<Option<MessageHandler>>>,
+    interrupted: Arc<AtomicBool>,
+) {
+    let flag = Arc::clone(&interrupted);
+    ctx.set_interrupt_handler(move || flag.load(Ordering::SeqCst));
+
+    let post_events = events.clone();
+    let registered = ctx.register_fn("__postMessage", move |args: &[Value<'_>]| {
+        let message = ClonedValue::from_value(args[0])?;
+        // Run the handler without holding the lock so that it can replace
+        // itself through `Worker::on_message`.
+        let Some(callback) = lock(&handler).take() else {
+            let _ = post_events.send(Ok(message));
+            return Ok(args[0]);
+        };
+        let mut restore = RestoreHandler {
+            slot: &handler,
+            callback: Some(callback),
+        };
+        if let Some(callback) = restore.callback.as_mut() {
+            callback(message);
+        }
+        Ok(args[0])
+    });
+    if let Err(err) = registered {
+        let _ = events.send(Err(err));
+        return;
+    }
+
+    let dispatch = match ctx
+        .eval(PRELUDE_SOURCE, "<worker>")
+        .and_then(|_| ctx.eval(DISPATCH_SOURCE, "<worker>"))
+    {
+        Ok(dispatch) => ctx.root(dispatch),
+        Err(err) => {
+            let _ = events.send(Err(err));
+            return;
+        }
+    };
+
+    if let Err(err) = ctx.eval(script, "<worker>") {
+        if !interrupted.load(Ordering::SeqCst) {
+            let _ = events.send(Err(err));
+        }
+        return;
+    }
+
+    while let Ok(Command::Message(message)) = commands.recv() {
+        let result = message.to_value(ctx).and_then(|message| {
+            Function::from_value(ctx, dispatch.to_value())?.call(&[message])
+        });
+        if let Err(err) = result {
+            if interrupted.load(Ordering::SeqCst) {
+                return;
+            }
+            let _ = events.send(Err(err));
+        }
+    }
+}
+
+/// Puts a handler taken out for a call back in its slot, even when the call
+/// panics, unless a new handler was installed meanwhile.
+struct RestoreHandler<'a> {
+    slot: &'a Mutex<Option<MessageHandler>>,
+    callback: Option<MessageHandler>,
+}
+
+impl Drop for RestoreHandler<'_> {
+    fn drop(&mut self) {
+        let mut slot = lock(self.slot);
+        if slot.is_none() {
+            *slot = self.callback.take();
+        }
+    }
+}
+
+/// Lock the handler slot. It holds no invariant a panic could break, so a
+/// poisoned lock is still usable.
+fn lock(handler: &Mutex<Option<MessageHandler>>) -> MutexGuard<'_, Option<MessageHandler>> {
+    handler.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn worker_exited() -> JsError {
+    JsError::Runtime {
+        message: "worker has exited".to_string(),
+    }
+}
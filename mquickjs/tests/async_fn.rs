@@ -0,0 +1,240 @@
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+
+use mquickjs_rs::{Clock, Context, ContextBuilder, JsData, JsError, ManualClock, Promise};
+
+fn context() -> Context {
+    Context::new(256 * 1024).expect("context should initialize")
+}
+
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Poll `future` until it completes.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(CountingWaker::default()));
+    let mut cx = TaskContext::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// A future completed from the test with `Gate::open`.
+#[derive(Clone, Default)]
+struct Gate(Arc<Mutex<(Option<JsData>, Option<Waker>)>>);
+
+impl Gate {
+    fn open(&self, data: JsData) {
+        let mut inner = self.0.lock().unwrap();
+        inner.0 = Some(data);
+        if let Some(waker) = inner.1.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Future for Gate {
+    type Output = Result<JsData, JsError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let mut inner = self.0.lock().unwrap();
+        match inner.0.take() {
+            Some(data) => Poll::Ready(Ok(data)),
+            None => {
+                inner.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[test]
+fn async_functions_return_promises_settled_by_the_event_loop() {
+    let ctx = context();
+    ctx.register_async_fn("sum", |args: Vec<JsData>| async move {
+        let mut total = 0.0;
+        for arg in &args {
+            match arg.get("n") {
+                Some(JsData::Number(n)) => total += n,
+                _ => return Err(JsError::conversion("expected { n }")),
+            }
+        }
+        Ok(JsData::Number(total))
+    })
+    .expect("register should succeed");
+    ctx.eval(
+        "var log = [];\nvar p = sum({ n: 1 }, { n: 2 });\nlog.push(p instanceof Promise);\np.then(function (v) { log.push(v); });\nsum(1).catch(function (e) { log.push(e); });",
+        "test",
+    )
+    .expect("eval should succeed");
+
+    block_on(ctx.run_async()).expect("event loop should run");
+    assert_eq!(
+        ctx.eval_string("log.join()", "test").expect("eval should succeed"),
+        "true,3,conversion error: expected { n }"
+    );
+}
+
+#[test]
+fn pending_futures_wake_the_event_loop() {
+    let ctx = context();
+    let gate = Gate::default();
+    let future = gate.clone();
+    ctx.register_async_fn("fetch", move |_args: Vec<JsData>| future.clone())
+        .expect("register should succeed");
+    ctx.eval("var got; fetch().then(function (v) { got = v; });", "test")
+        .expect("eval should succeed");
+
+    let wakes = Arc::new(CountingWaker::default());
+    let waker = Waker::from(wakes.clone());
+    let mut cx = TaskContext::from_waker(&waker);
+    assert!(ctx.poll_event_loop(&mut cx).is_pending());
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+    gate.open(JsData::String("row".to_string()));
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    assert!(matches!(ctx.poll_event_loop(&mut cx), Poll::Ready(Ok(()))));
+    assert_eq!(
+        ctx.eval_string("got", "test").expect("eval should succeed"),
+        "row"
+    );
+}
+
+/// A clock stuck at zero that records the deadlines it is asked to wake at.
+#[derive(Clone, Default)]
+struct StoppedClock(Arc<Mutex<Vec<f64>>>);
+
+impl Clock for StoppedClock {
+    fn now_ms(&self) -> f64 {
+        0.0
+    }
+
+    fn monotonic_ms(&self) -> f64 {
+        0.0
+    }
+
+    fn wake_at(&self, deadline_ms: f64, _waker: &Waker) {
+        self.0.lock().unwrap().push(deadline_ms);
+    }
+}
+
+#[test]
+fn waiting_timers_wake_through_the_clock() {
+    let clock = StoppedClock::default();
+    let ctx = ContextBuilder::new()
+        .clock(clock.clone())
+        .build()
+        .expect("context should initialize");
+    ctx.eval("setTimeout(function () {}, 25);", "test")
+        .expect("eval should succeed");
+
+    let wakes = Arc::new(CountingWaker::default());
+    let waker = Waker::from(wakes.clone());
+    let mut cx = TaskContext::from_waker(&waker);
+    assert!(ctx.poll_event_loop(&mut cx).is_pending());
+    assert!(ctx.poll_event_loop(&mut cx).is_pending());
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+    assert_eq!(*clock.0.lock().unwrap(), [25.0]);
+}
+
+#[test]
+fn run_async_waits_for_timers() {
+    let clock = ManualClock::new(0.0);
+    let ctx = ContextBuilder::new()
+        .clock(clock.clone())
+        .build()
+        .expect("context should initialize");
+    ctx.eval("var fired = false;\nsetTimeout(function () { fired = true; }, 50);", "test")
+        .expect("eval should succeed");
+
+    block_on(ctx.run_async()).expect("event loop should run");
+    assert!(ctx.eval_bool("fired", "test").expect("eval should succeed"));
+    assert_eq!(clock.monotonic_ms(), 50.0);
+}
+
+#[test]
+fn timers_and_host_calls_run_together() {
+    let clock = ManualClock::new(0.0);
+    let ctx = ContextBuilder::new()
+        .clock(clock.clone())
+        .async_function("echo", |args: Vec<JsData>| async move {
+            Ok(args.into_iter().next().unwrap_or(JsData::Undefined))
+        })
+        .build()
+        .expect("context should initialize");
+    ctx.eval(
+        "var log = [];\nsetTimeout(function () {\n  echo('late').then(function (v) { log.push(v); });\n}, 10);\necho('early').then(function (v) { log.push(v); });",
+        "test",
+    )
+    .expect("eval should succeed");
+
+    let waker = Waker::from(Arc::new(CountingWaker::default()));
+    let mut cx = TaskContext::from_waker(&waker);
+    assert!(ctx.poll_event_loop(&mut cx).is_pending());
+    assert_eq!(
+        ctx.eval_string("log.join()", "test").expect("eval should succeed"),
+        "early"
+    );
+
+    clock.advance(10.0);
+    block_on(ctx.run_async()).expect("event loop should run");
+    assert_eq!(
+        ctx.eval_string("log.join()", "test").expect("eval should succeed"),
+        "early,late"
+    );
+}
+
+#[test]
+fn awaiting_a_promise_drives_host_calls() {
+    let ctx = context();
+    ctx.register_async_fn("double", |args: Vec<JsData>| async move {
+        match args.first() {
+            Some(JsData::Number(n)) => Ok(JsData::Number(n * 2.0)),
+            _ => Ok(JsData::Null),
+        }
+    })
+    .expect("register should succeed");
+    let value = ctx
+        .eval("double(20).then(function (v) { return v + 2; })", "test")
+        .expect("eval should succeed");
+    let promise = Promise::from_value(&ctx, value).expect("value should be a promise");
+    let value = block_on(promise).expect("promise should fulfill");
+    assert_eq!(value.to_i32().expect("number"), 42);
+}
+
+#[test]
+fn unconvertible_arguments_throw() {
+    let ctx = context();
+    ctx.register_async_fn("store", |_args: Vec<JsData>| async { Ok(JsData::Undefined) })
+        .expect("register should succeed");
+    let err = ctx
+        .eval("store(1, function () {})", "test")
+        .expect_err("functions cannot be copied");
+    assert!(err.to_string().contains("[1]"), "unexpected error {err}");
+}
+
+#[test]
+fn reset_drops_pending_host_calls() {
+    let mut ctx = context();
+    ctx.register_async_fn("wait", |_args: Vec<JsData>| Gate::default())
+        .expect("register should succeed");
+    ctx.eval("wait();", "test").expect("eval should succeed");
+    ctx.reset().expect("reset should succeed");
+
+    let waker = Waker::from(Arc::new(CountingWaker::default()));
+    let mut cx = TaskContext::from_waker(&waker);
+    assert!(matches!(ctx.poll_event_loop(&mut cx), Poll::Ready(Ok(()))));
+    assert!(ctx.eval_bool("typeof wait === 'function'", "test").expect("eval should succeed"));
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use mquickjs_rs::{ContextBuilder, HeapGrowth, JsError, MemoryQuota, Value};
+
+#[test]
+fn builder_applies_memory_and_functions() {
+    let ctx = ContextBuilder::new()
+        .growth(HeapGrowth::new(64 * 1024, 1024 * 1024))
+        .memory_quota(MemoryQuota::new(512 * 1024))
+        .function("echo", |args: &[Value<'_>]| Ok(args[0]))
+        .build()
+        .expect("context should initialize");
+
+    assert_eq!(ctx.eval_i32("echo(5)", "test").expect("eval should succeed"), 5);
+    assert_eq!(ctx.memory_stats().heap_size, 64 * 1024);
+    assert_eq!(ctx.memory_quota(), Some(512 * 1024));
+}
+
+#[test]
+fn random_seed_makes_math_random_reproducible() {
+    let sequence = |seed| {
+        let ctx = ContextBuilder::new()
+            .memory_bytes(128 * 1024)
+            .random_seed(seed)
+            .build()
+            .expect("context should initialize");
+        ctx.eval_string("[Math.random(), Math.random()].join()", "test")
+            .expect("eval should succeed")
+    };
+    assert_eq!(sequence(42), sequence(42));
+    assert_ne!(sequence(42), sequence(43));
+}
+
+#[test]
+fn disabled_globals_stay_removed_after_reset() {
+    let mut ctx = ContextBuilder::new()
+        .disable_global("eval")
+        .build()
+        .expect("context should initialize");
+    assert_eq!(ctx.eval_string("typeof eval", "test").expect("eval should succeed"), "undefined");
+    ctx.reset().expect("reset should succeed");
+    assert_eq!(ctx.eval_string("typeof eval", "test").expect("eval should succeed"), "undefined");
+}
+
+#[test]
+fn interrupt_handler_stops_long_running_scripts() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let counter = polls.clone();
+    let ctx = ContextBuilder::new()
+        .interrupt_handler(move || counter.fetch_add(1, Ordering::SeqCst) > 10)
+        .build()
+        .expect("context should initialize");
+
+    let err = ctx.eval("for (;;) {}", "test").expect_err("script should be interrupted");
+    assert!(matches!(err, JsError::Exception { .. }));
+    assert!(polls.load(Ordering::SeqCst) > 10);
+}
+
+#[test]
+fn panicking_interrupt_handlers_stop_the_script() {
+    let ctx = ContextBuilder::new()
+        .interrupt_handler(|| panic!("interrupt handler failed"))
+        .build()
+        .expect("context should initialize");
+
+    let err = ctx.eval("for (;;) {}", "test").expect_err("script should be interrupted");
+    assert!(matches!(err, JsError::Exception { .. }));
+}
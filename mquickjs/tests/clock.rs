@@ -0,0 +1,97 @@
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Wake, Waker};
+use std::time::Duration;
+
+use mquickjs_rs::{CaptureConsole, Clock, Context, ContextBuilder, ManualClock, SystemClock};
+
+struct Tagged(Mutex<Sender<&'static str>>, &'static str);
+
+impl Wake for Tagged {
+    fn wake(self: Arc<Self>) {
+        let _ = self.0.lock().unwrap().send(self.1);
+    }
+}
+
+struct PanickingClock;
+
+impl Clock for PanickingClock {
+    fn now_ms(&self) -> f64 {
+        panic!("wall clock failed")
+    }
+
+    fn monotonic_ms(&self) -> f64 {
+        panic!("monotonic clock failed")
+    }
+}
+
+#[test]
+fn system_clock_is_the_default() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    let now = ctx.eval("Date.now()", "test").expect("eval should succeed");
+    let millis = now.to_f64().expect("Date.now() should be a number");
+    assert!(millis > 1.5e12, "{millis}");
+    assert_eq!(ctx.eval_string("typeof performance.now()", "test").expect("eval should succeed"), "number");
+}
+
+#[test]
+fn manual_clock_moves_only_when_advanced() {
+    let clock = ManualClock::new(1_700_000_000_000.5);
+    let ctx = ContextBuilder::new()
+        .clock(clock.clone())
+        .build()
+        .expect("context should initialize");
+
+    let read = |code: &str| ctx.eval(code, "test").and_then(|v| v.to_f64()).expect("eval should succeed");
+    assert_eq!(read("Date.now()"), 1_700_000_000_000.0);
+    assert_eq!(read("performance.now()"), 0.0);
+
+    clock.advance(250.5);
+    assert_eq!(read("Date.now()"), 1_700_000_000_251.0);
+    assert_eq!(read("performance.now()"), 250.5);
+
+    clock.set_now(0.0);
+    assert_eq!(read("Date.now()"), 0.0);
+    assert_eq!(read("performance.now()"), 250.5);
+}
+
+#[test]
+fn console_time_uses_the_context_clock() {
+    let clock = ManualClock::new(0.0);
+    let capture = CaptureConsole::new();
+    let mut ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_clock(clock.clone());
+    ctx.set_console(capture.clone());
+
+    ctx.eval("console.time('step')", "test").expect("eval should succeed");
+    clock.advance(12.25);
+    ctx.eval("console.timeEnd('step')", "test").expect("eval should succeed");
+    assert_eq!(capture.take(), ["step: 12.250ms"]);
+
+    ctx.reset().expect("reset should succeed");
+    clock.advance(1.0);
+    let elapsed = ctx.eval("performance.now()", "test").and_then(|v| v.to_f64());
+    assert_eq!(elapsed.expect("eval should succeed"), 13.25);
+}
+
+#[test]
+fn panicking_clocks_read_as_undefined() {
+    let ctx = ContextBuilder::new().clock(PanickingClock).build().expect("context should initialize");
+    let kinds = ctx.eval_string("typeof Date.now() + ' ' + typeof performance.now()", "test");
+    assert_eq!(kinds.expect("eval should succeed"), "undefined undefined");
+}
+
+#[test]
+fn system_clock_wakes_in_deadline_order() {
+    let clock = SystemClock::new();
+    let (sender, woken) = channel();
+    let waker = |tag| Waker::from(Arc::new(Tagged(Mutex::new(sender.clone()), tag)));
+    let now = clock.monotonic_ms();
+    clock.wake_at(now + 60.0, &waker("late"));
+    clock.wake_at(now + 20.0, &waker("early"));
+
+    let timeout = Duration::from_secs(5);
+    assert_eq!(woken.recv_timeout(timeout), Ok("early"));
+    assert_eq!(woken.recv_timeout(timeout), Ok("late"));
+    assert!(clock.monotonic_ms() >= now + 60.0);
+}
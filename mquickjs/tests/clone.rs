@@ -0,0 +1,141 @@
+use mquickjs_rs::{ClonedValue, Context, Object, Value};
+
+fn expose(ctx: &Context, name: &str, value: Value<'_>) {
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(ctx, global).expect("global should wrap");
+    global.set(name, value).expect("set should succeed");
+}
+
+#[test]
+fn clone_into_copies_plain_data() {
+    let source = Context::new(1024 * 1024).expect("context should initialize");
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = source
+        .eval("({ name: 'tenant', ids: [1, 2.5, null, undefined], nested: { ok: true } })", "test")
+        .expect("eval should succeed");
+    let cloned = value.clone_into(&target).expect("clone should succeed");
+    expose(&target, "v", cloned);
+
+    let summary = target
+        .eval_string(
+            "v.name + ':' + v.ids.length + ':' + v.ids[1] + ':' + v.ids[2] + ':' + \
+             typeof v.ids[3] + ':' + v.nested.ok",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(summary, "tenant:4:2.5:null:undefined:true");
+}
+
+#[test]
+fn clone_preserves_shared_references_and_cycles() {
+    let source = Context::new(1024 * 1024).expect("context should initialize");
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = source
+        .eval("var s = { n: 1 }; var o = { a: s, b: s, list: [s] }; o.self = o; o", "test")
+        .expect("eval should succeed");
+    let cloned = value.clone_into(&target).expect("clone should succeed");
+    expose(&target, "v", cloned);
+
+    let shared = target
+        .eval_bool("v.a === v.b && v.list[0] === v.a && v.self === v", "test")
+        .expect("eval should succeed");
+    assert!(shared);
+}
+
+#[test]
+fn clone_keeps_buffers_typed_arrays_regexps_and_errors() {
+    let source = Context::new(1024 * 1024).expect("context should initialize");
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = source
+        .eval(
+            "var buf = new ArrayBuffer(8);\n\
+             var bytes = new Uint8Array(buf); bytes[2] = 42;\n\
+             ({ buf: buf, bytes: bytes, words: new Int16Array(buf, 2, 2), re: /a+b/g,\n\
+                err: new RangeError('too big') })",
+            "test",
+        )
+        .expect("eval should succeed");
+    let cloned = value.clone_into(&target).expect("clone should succeed");
+    expose(&target, "v", cloned);
+
+    let summary = target
+        .eval_string(
+            "(v.bytes.buffer === v.buf) + ':' + (v.words.buffer === v.buf) + ':' + \
+             v.bytes[2] + ':' + v.words.length + ':' + (v.words instanceof Int16Array) + ':' + \
+             v.re.source + '/' + v.re.flags + ':' + v.re.test('xaab') + ':' + \
+             (v.err instanceof RangeError) + ':' + v.err.message",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(summary, "true:true:42:2:true:a+b/g:true:true:too big");
+}
+
+#[test]
+fn cloned_errors_only_take_error_names() {
+    let source = Context::new(1024 * 1024).expect("context should initialize");
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = source
+        .eval(
+            "var fake = new Error('x'); fake.name = 'RegExp';\n\
+             var odd = new TypeError('y'); odd.name = 42;\n\
+             Object.defineProperty(odd, 'message', { value: { text: 'y' } });\n\
+             [fake, odd]",
+            "test",
+        )
+        .expect("eval should succeed");
+    let cloned = value.clone_into(&target).expect("clone should succeed");
+    expose(&target, "v", cloned);
+
+    let summary = target
+        .eval_string(
+            "(v[0] instanceof RegExp) + ':' + v[0].name + ':' + v[0].message + ':' + \
+             v[1].name + ':' + JSON.stringify(v[1].message)",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(summary, "false:Error:x:Error:\"\"");
+}
+
+#[test]
+fn clone_rejects_functions() {
+    let source = Context::new(1024 * 1024).expect("context should initialize");
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = source
+        .eval("({ handlers: [function () {}] })", "test")
+        .expect("eval should succeed");
+    let err = value.clone_into(&target).expect_err("function should fail");
+    assert_eq!(
+        err.to_string(),
+        "conversion error: cannot clone function at $.handlers[0] (found function)"
+    );
+}
+
+#[test]
+fn cloned_value_survives_gc_and_context_drop() {
+    let cloned = {
+        let source = Context::new(64 * 1024).expect("context should initialize");
+        let value = source
+            .eval(
+                "var items = []; for (var i = 0; i < 200; i++) items.push({ i: i, peer: null });\n\
+                 for (var i = 0; i < 200; i++) items[i].peer = items[(i + 1) % 200]; items",
+                "test",
+            )
+            .expect("eval should succeed");
+        let cloned = ClonedValue::from_value(value).expect("clone should succeed");
+        source.gc();
+        cloned
+    };
+
+    let target = Context::new(1024 * 1024).expect("context should initialize");
+    let value = cloned.to_value(&target).expect("value should materialize");
+    expose(&target, "v", value);
+    let ok = target
+        .eval_bool("v.length === 200 && v[199].peer === v[0] && v[5].peer.i === 6", "test")
+        .expect("eval should succeed");
+    assert!(ok);
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use mquickjs_rs::{AsMap, Context, FromValue, IntoValue, JsError, Object};
+
+fn context() -> Context {
+    Context::new(256 * 1024).expect("context should initialize")
+}
+
+#[test]
+fn maps_keep_insertion_order_and_compare_keys_by_same_value_zero() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var m = new Map();\nvar key = {};\nm.set('b', 1).set('a', 2).set(NaN, 3).set(-0, 4).set(key, 5);\nm.set('b', 6);\n[m.size, m.get('b'), m.get(NaN), m.get(0), m.has(+0), m.get(key), m.get({}), m.keys().join()].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "5,6,3,4,true,5,,b,a,NaN,0,[object Object]");
+}
+
+#[test]
+fn proto_is_an_ordinary_key() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var m = new Map([['__proto__', 1], ['constructor', 2]]);\nvar s = new Set(['__proto__']);\n[m.get('__proto__'), m.get('constructor'), s.has('__proto__'), s.has('toString')].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "1,2,true,false");
+}
+
+#[test]
+fn sets_ignore_duplicates_and_list_their_values() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var s = new Set([3, 1, 3, '3', 1]);\ns.add(2).add(1);\nvar removed = s.delete('3');\n[s.size, removed, s.delete('3'), s.values().join(), JSON.stringify(s.entries())].join(' ')",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "3 true false 3,1,2 [[3,3],[1,1],[2,2]]");
+}
+
+#[test]
+fn for_each_sees_changes_made_during_the_loop() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var m = new Map([['a', 1], ['b', 2], ['c', 3]]);\nvar seen = [];\nm.forEach(function (v, k, map) {\n  seen.push(k + v);\n  if (k === 'a') { map.delete('a'); map.delete('b'); map.set('d', 4); }\n});\nvar s = new Set([1]);\nvar count = 0;\ns.forEach(function (v) { if (++count < 5) { s.clear(); s.add(v + 1); } });\nseen.join() + ' ' + s.values().join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "a1,c3,d4 5");
+}
+
+#[test]
+fn constructors_copy_collections_and_reject_other_values() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var m = new Map([[1, 'one'], [2, 'two']]);\nvar copy = new Map(m);\ncopy.set(3, 'three');\nvar pairs = new Set(m);\n[m.size, copy.size, JSON.stringify(pairs.values()), new Set(new Set([1, 2])).size].join(' ')",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "2 3 [[1,\"one\"],[2,\"two\"]] 2");
+
+    for script in [
+        "new Map(1)",
+        "new Map([1])",
+        "Map()",
+        "Map.prototype.get.call(new Set(), 1)",
+        "Set.prototype.add.call({}, 1)",
+    ] {
+        let err = ctx.eval(script, "test").expect_err("script should throw");
+        assert!(err.to_string().contains("TypeError"), "{script}: {err}");
+    }
+}
+
+#[test]
+fn collections_holding_themselves_are_collected() {
+    let ctx = context();
+    ctx.eval(
+        "for (var i = 0; i < 2000; i++) {\n  var m = new Map();\n  m.set(m, new Set([m, 'x' + i]));\n}\nm = null;",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.gc();
+    let stats = ctx.memory_stats();
+    assert!(stats.class("Map").is_none_or(|class| class.count <= 1));
+    assert!(stats.class("Set").is_none_or(|class| class.count <= 1));
+}
+
+#[test]
+fn rust_maps_and_sets_convert_to_collections() {
+    let ctx = context();
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(&ctx, global).expect("global should be an object");
+
+    let map = BTreeMap::from([(2, "two".to_string()), (1, "one".to_string())]);
+    global.set("map", map).expect("set should succeed");
+    let set = HashSet::from(["x".to_string()]);
+    global.set("set", set).expect("set should succeed");
+    let ordered = BTreeSet::from([3, 1]);
+    global.set("ordered", ordered).expect("set should succeed");
+
+    let value = ctx
+        .eval_string(
+            "[map instanceof Map, map.keys().join(), map.get(2), set instanceof Set, set.has('x'), ordered.values().join()].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "true,1,2,two,true,true,1,3");
+}
+
+#[test]
+fn collections_convert_to_rust_maps_and_sets() {
+    let ctx = context();
+    let value = ctx
+        .eval("new Map([[3, 'c'], [1, 'a']])", "test")
+        .expect("eval should succeed");
+    let map = BTreeMap::<i32, String>::from_value(value).expect("map should convert");
+    assert_eq!(
+        map,
+        BTreeMap::from([(1, "a".to_string()), (3, "c".to_string())])
+    );
+
+    let value = ctx
+        .eval("new Map([['a', 1], ['b', 2]])", "test")
+        .expect("eval should succeed");
+    let map = HashMap::<String, i32>::from_value(value).expect("map should convert");
+    assert_eq!(
+        map,
+        HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+    );
+
+    let value = ctx
+        .eval("new Set(['x', 'y', 'x'])", "test")
+        .expect("eval should succeed");
+    let set = HashSet::<String>::from_value(value).expect("set should convert");
+    assert_eq!(set, HashSet::from(["x".to_string(), "y".to_string()]));
+    let set = BTreeSet::<String>::from_value(value).expect("set should convert");
+    assert_eq!(set.into_iter().collect::<Vec<_>>(), ["x", "y"]);
+
+    let value = ctx.eval("({ a: 1 })", "test").expect("eval should succeed");
+    let err = BTreeMap::<String, i32>::from_value(value).expect_err("objects are not maps");
+    assert!(matches!(err, JsError::Conversion { .. }), "{err}");
+
+    let value = ctx
+        .eval("new Map([['a', 1], ['b', 'x']])", "test")
+        .expect("eval should succeed");
+    let err = BTreeMap::<String, i32>::from_value(value).expect_err("values must be numbers");
+    assert!(err.to_string().contains("[1]"), "{err}");
+}
+
+#[test]
+fn rust_values_round_trip_through_collections() {
+    let ctx = context();
+    let input = BTreeMap::from([("k".to_string(), vec![1, 2])]);
+    let value = input.clone().into_value(&ctx).expect("map should convert");
+    let output = BTreeMap::<String, Vec<i32>>::from_value(value).expect("map should convert");
+    assert_eq!(output, input);
+}
+
+#[test]
+fn wrapped_hash_maps_convert_to_and_from_maps() {
+    let ctx = context();
+    let input = HashMap::from([(1, "one".to_string()), (2, "two".to_string())]);
+    let value = AsMap(input.clone()).into_value(&ctx).expect("map should convert");
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(&ctx, global).expect("global should be an object");
+    global.set("map", value).expect("set should succeed");
+    let is_map = ctx
+        .eval_bool("map instanceof Map && map.get(2) === 'two'", "test")
+        .expect("eval should succeed");
+    assert!(is_map);
+    let output = AsMap::<i32, String>::from_value(value).expect("map should convert");
+    assert_eq!(output.into_inner(), input);
+
+    let plain = HashMap::from([("a".to_string(), 1)]).into_value(&ctx).expect("object should convert");
+    let err = AsMap::<String, i32>::from_value(plain).expect_err("plain objects are not maps");
+    assert!(matches!(err, JsError::Conversion { .. }), "{err}");
+}
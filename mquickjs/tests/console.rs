@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+
+use mquickjs_rs::{CaptureConsole, ConsoleLevel, Context, ContextBuilder};
+
+#[test]
+fn console_log_and_print_reach_the_sink() {
+    let capture = CaptureConsole::new();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(capture.clone());
+
+    ctx.eval("console.log('hello', 'world'); print('second');", "test")
+        .expect("eval should succeed");
+    assert_eq!(capture.take(), ["hello world", "second"]);
+    assert!(capture.lines().is_empty());
+}
+
+#[test]
+fn non_string_arguments_are_formatted_by_the_engine() {
+    let capture = CaptureConsole::new();
+    let ctx = ContextBuilder::new()
+        .console(capture.clone())
+        .build()
+        .expect("context should initialize");
+
+    ctx.eval("console.log(1, 2.5, true, null, undefined, [1, 2], { a: 'x' })", "test")
+        .expect("eval should succeed");
+    assert_eq!(capture.lines(), ["1 2.5 true null undefined [ 1, 2 ] { a: \"x\" }"]);
+}
+
+#[test]
+fn closures_are_console_sinks() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = lines.clone();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(move |_level, line: &str| sink.lock().unwrap().push(line.to_string()));
+
+    ctx.eval("console.log()", "test").expect("eval should succeed");
+    assert_eq!(*lines.lock().unwrap(), [""]);
+}
+
+#[test]
+fn output_is_discarded_without_a_sink() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    let result = ctx.eval("console.log('dropped')", "test").expect("eval should succeed");
+    assert!(result.is_undefined());
+}
+
+#[test]
+fn console_methods_tag_lines_with_levels() {
+    let capture = CaptureConsole::new();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(capture.clone());
+
+    ctx.eval(
+        "console.debug('d'); console.info('i', 1); console.warn('w'); console.error('e');",
+        "test",
+    )
+    .expect("eval should succeed");
+    assert_eq!(
+        capture.entries(),
+        [
+            (ConsoleLevel::Debug, "d".to_string()),
+            (ConsoleLevel::Info, "i 1".to_string()),
+            (ConsoleLevel::Warn, "w".to_string()),
+            (ConsoleLevel::Error, "e".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn assert_reports_only_falsy_conditions() {
+    let capture = CaptureConsole::new();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(capture.clone());
+
+    ctx.eval(
+        "console.assert(1, 'kept'); console.assert('x'); console.assert(0, 'bad', 2); console.assert('');",
+        "test",
+    )
+    .expect("eval should succeed");
+    assert_eq!(
+        capture.entries(),
+        [
+            (ConsoleLevel::Error, "Assertion failed: bad 2".to_string()),
+            (ConsoleLevel::Error, "Assertion failed".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn time_end_reports_elapsed_milliseconds() {
+    let capture = CaptureConsole::new();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(capture.clone());
+
+    ctx.eval("console.time('load'); console.time('load'); console.timeEnd('load'); console.timeEnd();", "test")
+        .expect("eval should succeed");
+    let entries = capture.entries();
+    assert_eq!(entries[0], (ConsoleLevel::Warn, "Timer 'load' already exists".to_string()));
+    assert_eq!(entries[1].0, ConsoleLevel::Log);
+    assert!(entries[1].1.starts_with("load: ") && entries[1].1.ends_with("ms"), "{}", entries[1].1);
+    assert_eq!(entries[2], (ConsoleLevel::Warn, "Timer 'default' does not exist".to_string()));
+}
+
+#[test]
+fn table_draws_rows_and_columns() {
+    let capture = CaptureConsole::new();
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(capture.clone());
+
+    ctx.eval("console.table([{ a: 1, b: 'x' }, { a: 22 }, 3])", "test")
+        .expect("eval should succeed");
+    let expected = [
+        "┌─────────┬────┬─────┬────────┐",
+        "│ (index) │ a  │ b   │ Values │",
+        "├─────────┼────┼─────┼────────┤",
+        "│ 0       │ 1  │ \"x\" │        │",
+        "│ 1       │ 22 │     │        │",
+        "│ 2       │    │     │ 3      │",
+        "└─────────┴────┴─────┴────────┘",
+    ];
+    assert_eq!(capture.lines(), [expected.join("\n")]);
+
+    ctx.eval("console.table({ r: { a: 1, b: 2 } }, ['b']); console.table('plain')", "test")
+        .expect("eval should succeed");
+    let lines = capture.lines();
+    assert_eq!(lines[1], "┌─────────┬───┐\n│ (index) │ b │\n├─────────┼───┤\n│ r       │ 2 │\n└─────────┴───┘");
+    assert_eq!(lines[2], "plain");
+}
+
+#[test]
+fn panicking_sinks_drop_the_line() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.set_console(|_level, line: &str| panic!("sink panicked on {line}"));
+
+    let value = ctx
+        .eval_i32("console.log('dropped'); 1 + 1", "test")
+        .expect("eval should continue after the sink panics");
+    assert_eq!(value, 2);
+}
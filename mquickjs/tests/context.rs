@@ -1,4 +1,8 @@
-use mquickjs_rs::{Context, JsError};
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use mquickjs_rs::{Context, JsError, Value};
 
 #[test]
 fn context_requires_minimum_memory() {
@@ -11,3 +15,95 @@ fn context_new_creates_and_drops() {
     let ctx = Context::new(1024 * 1024).expect("context should initialize");
     drop(ctx);
 }
+
+#[test]
+fn context_keeps_callbacks_when_moved_between_threads() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Context>();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    ctx.register_fn("echo", move |args: &[Value<'_>]| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Ok(args[0])
+    })
+    .expect("register should succeed");
+    ctx.eval("var total = echo(1);", "test").expect("eval should succeed");
+
+    let ctx = std::thread::spawn(move || {
+        ctx.eval("total += echo(10);", "test").expect("eval should succeed");
+        ctx
+    })
+    .join()
+    .expect("thread should finish");
+
+    let total = ctx.eval_i32("total + echo(100)", "test").expect("eval should succeed");
+    assert_eq!(total, 111);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn contexts_on_one_thread_keep_separate_callbacks() {
+    let first = Context::new(1024 * 1024).expect("context should initialize");
+    let second = Context::new(1024 * 1024).expect("context should initialize");
+    first
+        .register_fn("which", |_args: &[Value<'_>]| Err(JsError::Callback {
+            message: "first".to_string(),
+        }))
+        .expect("register should succeed");
+    second
+        .register_fn("which", |_args: &[Value<'_>]| Err(JsError::Callback {
+            message: "second".to_string(),
+        }))
+        .expect("register should succeed");
+
+    let err = second.eval("which()", "test").expect_err("callback should fail");
+    assert!(err.to_string().contains("second"));
+    let err = first.eval("which()", "test").expect_err("callback should fail");
+    assert!(err.to_string().contains("first"));
+}
+
+#[test]
+fn context_reset_clears_globals_and_keeps_callbacks() {
+    let mut ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.register_fn("echo", |args: &[Value<'_>]| Ok(args[0]))
+        .expect("register should succeed");
+    ctx.eval("var leaked = { items: [1, 2, 3] }; Object.prototype.polluted = 1;", "test")
+        .expect("eval should succeed");
+
+    ctx.reset().expect("reset should succeed");
+    let leaked = ctx
+        .eval_string("typeof leaked + ',' + typeof ({}).polluted", "test")
+        .expect("eval should succeed");
+    assert_eq!(leaked, "undefined,undefined");
+    assert_eq!(ctx.eval_i32("echo(5)", "test").expect("eval should succeed"), 5);
+}
+
+#[test]
+fn context_reset_replaces_callbacks_registered_again() {
+    let mut ctx = Context::new(256 * 1024).expect("context should initialize");
+    for round in 0..3 {
+        ctx.reset().expect("reset should succeed");
+        ctx.register_fn("fail", move |_args: &[Value<'_>]| {
+            Err(JsError::Callback {
+                message: format!("round {round}"),
+            })
+        })
+        .expect("register should succeed");
+        let err = ctx.eval("fail()", "test").expect_err("callback should throw");
+        assert!(err.to_string().contains(&format!("round {round}")));
+    }
+}
+
+#[test]
+fn context_runs_in_caller_supplied_buffer() {
+    let words = 256 * 1024 / std::mem::size_of::<usize>();
+    let buffer = Box::leak(vec![MaybeUninit::<usize>::uninit(); words].into_boxed_slice());
+    let ctx = Context::with_buffer(buffer).expect("context should initialize");
+    assert_eq!(ctx.eval_i32("[1, 2, 3].length", "test").expect("eval should succeed"), 3);
+
+    let small = Box::leak(vec![MaybeUninit::<usize>::uninit(); 16].into_boxed_slice());
+    let err = Context::with_buffer(small).expect_err("expected error for too-small buffer");
+    assert!(matches!(err, JsError::ContextInit { .. }));
+}
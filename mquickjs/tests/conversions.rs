@@ -34,7 +34,7 @@ fn into_value_roundtrips_primitives() {
     let ctx = Context::new(1024 * 1024).expect("context should initialize");
 
     let value = true.into_value(&ctx).expect("bool should convert");
-    assert_eq!(value.to_bool().expect("bool should read"), true);
+    assert!(value.to_bool().expect("bool should read"));
 
     let value = 7i32.into_value(&ctx).expect("i32 should convert");
     assert_eq!(value.to_i32().expect("i32 should read"), 7);
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, VecDeque};
 
-use mquickjs_rs::{Coerced, Context, FromValue, IntoValue};
+use mquickjs_rs::{Coerced, Context, FromValue, IntoValue, JsError, PathSegment};
 
 #[test]
 fn option_conversions_handle_null_and_some() {
@@ -108,3 +108,46 @@ fn coerced_numeric_and_string() {
         .expect("coerced should convert");
     assert_eq!(coerced.0, "123");
 }
+
+#[test]
+fn nested_conversion_error_reports_path_and_type() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx
+        .eval("({ a: [[1, 'x'], [2, 'y']], b: [[3, 'z'], ['oops', 'w']] })", "test")
+        .expect("eval should succeed");
+
+    let err = <HashMap<String, Vec<(i32, String)>> as FromValue>::from_value(value)
+        .expect_err("expected conversion error");
+    match err {
+        JsError::Conversion {
+            message,
+            path,
+            found,
+        } => {
+            assert_eq!(message, "expected number");
+            assert_eq!(
+                path,
+                vec![
+                    PathSegment::Key("b".to_string()),
+                    PathSegment::Index(1),
+                    PathSegment::Index(0),
+                ]
+            );
+            assert_eq!(found.as_deref(), Some("string"));
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn option_and_vecdeque_keep_inner_path() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx.eval("[1, null, true]", "test").expect("eval should succeed");
+
+    let err = <VecDeque<Option<i32>> as FromValue>::from_value(value)
+        .expect_err("expected conversion error");
+    assert_eq!(
+        err.to_string(),
+        "conversion error: expected number at $[2] (found boolean)"
+    );
+}
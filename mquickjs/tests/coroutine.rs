@@ -0,0 +1,201 @@
+#![cfg(feature = "coroutine")]
+
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+
+use mquickjs_rs::{Context, ContextBuilder, Function, JsData, JsError};
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+fn task_context() -> TaskContext<'static> {
+    let waker = Box::leak(Box::new(Waker::from(Arc::new(NoopWaker))));
+    TaskContext::from_waker(waker)
+}
+
+/// Poll `future` until it completes.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut cx = task_context();
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+/// Futures completed from the test, in call order.
+#[derive(Clone, Default)]
+struct Gates(Arc<Mutex<Vec<Option<JsData>>>>);
+
+impl Gates {
+    fn wait(&self) -> Gate {
+        let mut gates = self.0.lock().unwrap();
+        gates.push(None);
+        Gate(self.clone(), gates.len() - 1)
+    }
+
+    fn open(&self, index: usize, data: JsData) {
+        self.0.lock().unwrap()[index] = Some(data);
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+struct Gate(Gates, usize);
+
+impl Future for Gate {
+    type Output = Result<JsData, JsError>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        match self.0.0.lock().unwrap()[self.1].take() {
+            Some(data) => Poll::Ready(Ok(data)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn gated_context() -> (Context, Gates) {
+    let gates = Gates::default();
+    let waiting = gates.clone();
+    let ctx = ContextBuilder::new()
+        .memory_bytes(256 * 1024)
+        .suspend_function("get", move |_args: Vec<JsData>| waiting.wait())
+        .build()
+        .expect("context should initialize");
+    (ctx, gates)
+}
+
+#[test]
+fn scripts_resume_when_the_future_completes() {
+    let (ctx, gates) = gated_context();
+    let mut cx = task_context();
+    let mut script = pin!(ctx.eval_async(
+        "var items = [];\nfor (var i = 0; i < 2; i++) { items.push(get(i)); }\nitems.join()",
+        "test",
+    ));
+
+    assert!(script.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(gates.len(), 1);
+    assert!(script.as_mut().poll(&mut cx).is_pending());
+
+    gates.open(0, JsData::String("a".to_string()));
+    assert!(script.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(gates.len(), 2);
+
+    gates.open(1, JsData::String("b".to_string()));
+    match script.as_mut().poll(&mut cx) {
+        Poll::Ready(Ok(value)) => assert_eq!(value.to_string().expect("string"), "a,b"),
+        other => panic!("unexpected poll {other:?}"),
+    }
+}
+
+#[test]
+fn future_errors_are_thrown_into_the_script() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.register_suspend_fn("fail", |_args: Vec<JsData>| async {
+        Err(JsError::conversion("no such key"))
+    })
+    .expect("register should succeed");
+    let value = block_on(ctx.eval_async(
+        "var caught; try { fail(); } catch (e) { caught = e; } caught",
+        "test",
+    ))
+    .expect("eval should succeed");
+    assert_eq!(
+        value.to_string().expect("string"),
+        "conversion error: no such key"
+    );
+}
+
+#[test]
+fn suspending_outside_a_coroutine_throws() {
+    let (ctx, gates) = gated_context();
+    let err = ctx.eval("get(1)", "test").expect_err("call should throw");
+    assert!(
+        err.to_string().contains("eval_async"),
+        "unexpected error {err}"
+    );
+    assert_eq!(gates.len(), 0);
+}
+
+#[test]
+fn functions_can_be_called_on_a_coroutine() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.register_suspend_fn("double", |args: Vec<JsData>| async move {
+        match args.first() {
+            Some(JsData::Number(n)) => Ok(JsData::Number(n * 2.0)),
+            _ => Err(JsError::conversion("expected a number")),
+        }
+    })
+    .expect("register should succeed");
+    let func = ctx
+        .eval(
+            "(function (a, b) { return double(a) + double(b); })",
+            "test",
+        )
+        .expect("eval should succeed");
+    let func = Function::from_value(&ctx, func).expect("value should be a function");
+    let a = ctx.eval("1", "test").expect("eval should succeed");
+    let b = ctx.eval("20", "test").expect("eval should succeed");
+    let value = block_on(func.call_async(&[a, b])).expect("call should succeed");
+    assert_eq!(value.to_i32().expect("number"), 42);
+}
+
+#[test]
+fn host_code_runs_while_a_script_is_suspended() {
+    let (ctx, gates) = gated_context();
+    let mut cx = task_context();
+    let mut script = pin!(ctx.eval_async(
+        "var kept = { list: [1, 2, 3] };\nvar got = get();\nkept.list.join() + got",
+        "test",
+    ));
+    assert!(script.as_mut().poll(&mut cx).is_pending());
+
+    ctx.gc();
+    assert_eq!(
+        ctx.eval_i32("1 + 1", "test").expect("eval should succeed"),
+        2
+    );
+    let mut other = pin!(ctx.eval_async("1", "test"));
+    match other.as_mut().poll(&mut cx) {
+        Poll::Ready(Err(err)) => assert!(err.to_string().contains("already running")),
+        other => panic!("unexpected poll {other:?}"),
+    }
+
+    gates.open(0, JsData::String("!".to_string()));
+    match script.as_mut().poll(&mut cx) {
+        Poll::Ready(Ok(value)) => assert_eq!(value.to_string().expect("string"), "1,2,3!"),
+        other => panic!("unexpected poll {other:?}"),
+    }
+}
+
+#[test]
+fn dropped_scripts_run_to_completion_with_calls_failing() {
+    let (ctx, gates) = gated_context();
+    let mut cx = task_context();
+    {
+        let mut script = pin!(ctx.eval_async(
+            "var log = [];\ntry { get(); } catch (e) { log.push('first'); }\ntry { get(); } catch (err) { log.push('second'); }",
+            "test",
+        ));
+        assert!(script.as_mut().poll(&mut cx).is_pending());
+    }
+    assert_eq!(gates.len(), 1);
+    assert_eq!(
+        ctx.eval_string("log.join()", "test")
+            .expect("eval should succeed"),
+        "first,second"
+    );
+
+    gates.open(0, JsData::Number(1.0));
+    let value = block_on(ctx.eval_async("typeof get", "test")).expect("eval should succeed");
+    assert_eq!(value.to_string().expect("string"), "function");
+}
@@ -0,0 +1,107 @@
+use mquickjs_rs::{Context, DataLimits, FromValue, JsData, JsError, Object};
+
+fn extract(source: &str) -> Result<JsData, JsError> {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx.eval(source, "test").expect("eval should succeed");
+    JsData::from_value(value)
+}
+
+#[test]
+fn extracted_data_outlives_context() {
+    let data = extract("({ name: 'mq', tags: ['a', 'b'], size: 1.5, ok: true, none: null })")
+        .expect("data should extract");
+    assert_eq!(
+        data,
+        JsData::Object(vec![
+            ("name".to_string(), JsData::String("mq".to_string())),
+            (
+                "tags".to_string(),
+                JsData::Array(vec![
+                    JsData::String("a".to_string()),
+                    JsData::String("b".to_string()),
+                ])
+            ),
+            ("size".to_string(), JsData::Number(1.5)),
+            ("ok".to_string(), JsData::Bool(true)),
+            ("none".to_string(), JsData::Null),
+        ])
+    );
+    assert_eq!(data.get("size"), Some(&JsData::Number(1.5)));
+}
+
+#[test]
+fn bytes_come_from_buffers_and_uint8_arrays() {
+    let data = extract("var a = new Uint8Array(4); a[1] = 7; a[3] = 255; a")
+        .expect("data should extract");
+    assert_eq!(data, JsData::Bytes(vec![0, 7, 0, 255]));
+
+    let data = extract("var b = new ArrayBuffer(2); new Uint8Array(b)[0] = 9; b")
+        .expect("data should extract");
+    assert_eq!(data, JsData::Bytes(vec![9, 0]));
+
+    let data = extract("new Uint8Array(new ArrayBuffer(4), 1, 2)").expect("data should extract");
+    assert_eq!(data, JsData::Bytes(vec![0, 0]));
+
+    let data = extract("var f = new Int16Array(2); f[0] = -3; f").expect("data should extract");
+    assert_eq!(data, JsData::Array(vec![JsData::Number(-3.0), JsData::Number(0.0)]));
+}
+
+#[test]
+fn shared_references_are_copied_but_cycles_fail() {
+    let data = extract("var s = [1]; [s, s]").expect("data should extract");
+    let shared = JsData::Array(vec![JsData::Number(1.0)]);
+    assert_eq!(data, JsData::Array(vec![shared.clone(), shared]));
+
+    let err = extract("var o = { child: {} }; o.child.parent = o; o")
+        .expect_err("cycle should fail");
+    assert_eq!(err.to_string(), "conversion error: cyclic value at $.child.parent");
+}
+
+#[test]
+fn functions_are_rejected() {
+    let err = extract("({ run: function () {} })").expect_err("function should fail");
+    assert_eq!(
+        err.to_string(),
+        "conversion error: cannot extract function at $.run (found function)"
+    );
+}
+
+#[test]
+fn limits_bound_depth_and_size() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx.eval("[[[1]]]", "test").expect("eval should succeed");
+    let limits = DataLimits {
+        max_depth: 2,
+        ..DataLimits::default()
+    };
+    let err = JsData::from_value_with_limits(value, limits).expect_err("depth should fail");
+    assert_eq!(err.to_string(), "conversion error: value exceeds depth 2 at $[0][0]");
+
+    let value = ctx.eval("[1, 2, 3, 4]", "test").expect("eval should succeed");
+    let limits = DataLimits {
+        max_nodes: 3,
+        ..DataLimits::default()
+    };
+    let err = JsData::from_value_with_limits(value, limits).expect_err("size should fail");
+    assert_eq!(err.to_string(), "conversion error: value exceeds 3 nodes at $[2]");
+}
+
+#[test]
+fn data_injects_into_another_context() {
+    let data = extract("({ list: [1, 'two', undefined], bytes: new Uint8Array(2) })")
+        .expect("data should extract");
+
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = data.to_value(&ctx).expect("data should inject");
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(&ctx, global).expect("global should wrap");
+    global.set("v", value).expect("set should succeed");
+    let summary = ctx
+        .eval_string(
+            "typeof v.list[2] + ':' + v.list[1] + ':' + \
+             (v.bytes instanceof Uint8Array) + ':' + v.bytes.length",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(summary, "undefined:two:true:2");
+}
@@ -0,0 +1,183 @@
+use mquickjs_rs::{Context, FromValue, IntoValue, JsError, Object};
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+struct User {
+    name: String,
+    #[js(rename = "userAge")]
+    age: i32,
+    #[js(default)]
+    tags: Vec<String>,
+    #[js(skip)]
+    cached: Option<String>,
+}
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+enum Shape {
+    Empty,
+    #[js(rename = "circle")]
+    Circle(f64),
+    Line(Point, Point),
+    Rect { width: i32, height: i32 },
+}
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+enum Level {
+    Low,
+    High,
+}
+
+#[derive(Debug, PartialEq, mquickjs_derive::FromValue, mquickjs_derive::IntoValue)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[test]
+fn struct_roundtrips_as_object() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let user = User {
+        name: "ada".to_string(),
+        age: 36,
+        tags: vec!["admin".to_string()],
+        cached: Some("ignored".to_string()),
+    };
+
+    let value = user.into_value(&ctx).expect("struct should convert");
+    let object = Object::from_value(&ctx, value).expect("object should wrap");
+    let age: i32 = object.get("userAge").expect("renamed field");
+    assert_eq!(age, 36);
+    let cached: Option<String> = object.get("cached").expect("skipped field");
+    assert!(cached.is_none());
+
+    let back = User::from_value(value).expect("struct should convert back");
+    assert_eq!(
+        back,
+        User {
+            name: "ada".to_string(),
+            age: 36,
+            tags: vec!["admin".to_string()],
+            cached: None,
+        }
+    );
+}
+
+#[test]
+fn struct_default_fills_missing_field() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx
+        .eval("({ name: 'bob', userAge: 7 })", "test")
+        .expect("eval should succeed");
+    let user = User::from_value(value).expect("struct should convert");
+    assert!(user.tags.is_empty());
+}
+
+#[test]
+fn struct_missing_required_field_fails() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx.eval("({ name: 'bob' })", "test").expect("eval should succeed");
+    let err = User::from_value(value).expect_err("expected conversion error");
+    assert!(matches!(err, JsError::Conversion { .. }));
+}
+
+#[test]
+fn struct_field_errors_report_js_name() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = ctx
+        .eval("({ name: 'bob', userAge: 'old' })", "test")
+        .expect("eval should succeed");
+    let err = User::from_value(value).expect_err("expected conversion error");
+    assert_eq!(
+        err.to_string(),
+        "conversion error: expected number at $.userAge (found string)"
+    );
+
+    let value = ctx
+        .eval("({ Line: [[0, 0], [3, 'x']] })", "test")
+        .expect("eval should succeed");
+    let err = Shape::from_value(value).expect_err("expected conversion error");
+    assert_eq!(
+        err.to_string(),
+        "conversion error: expected number at $.Line[1][1] (found string)"
+    );
+}
+
+#[test]
+fn newtype_and_tuple_structs() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = Meters(2.5).into_value(&ctx).expect("newtype should convert");
+    assert!((value.to_f64().expect("number") - 2.5).abs() < f64::EPSILON);
+    assert_eq!(Meters::from_value(value).expect("newtype back"), Meters(2.5));
+
+    let value = Point(1, 2).into_value(&ctx).expect("tuple should convert");
+    let raw = <Vec<i32> as FromValue>::from_value(value).expect("array");
+    assert_eq!(raw, vec![1, 2]);
+    assert_eq!(Point::from_value(value).expect("tuple back"), Point(1, 2));
+
+    let value = ctx.eval("({ 0: 1, 1: 2, length: 2 })", "test").expect("eval should succeed");
+    let err = Point::from_value(value).expect_err("array-like objects are not arrays");
+    assert_eq!(err.to_string(), "conversion error: expected array (found object)");
+}
+
+#[test]
+fn enums_are_externally_tagged() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = Shape::Empty.into_value(&ctx).expect("unit variant");
+    assert_eq!(value.to_string().expect("string"), "Empty");
+
+    let value = ctx.eval("({ circle: 1.5 })", "test").expect("eval should succeed");
+    assert_eq!(Shape::from_value(value).expect("newtype variant"), Shape::Circle(1.5));
+
+    let value = ctx
+        .eval("({ Line: [[0, 0], [3, 4]] })", "test")
+        .expect("eval should succeed");
+    assert_eq!(
+        Shape::from_value(value).expect("tuple variant"),
+        Shape::Line(Point(0, 0), Point(3, 4))
+    );
+
+    let shape = Shape::Rect {
+        width: 2,
+        height: 3,
+    };
+    let value = shape.into_value(&ctx).expect("struct variant");
+    assert_eq!(
+        Shape::from_value(value).expect("struct variant back"),
+        Shape::Rect {
+            width: 2,
+            height: 3
+        }
+    );
+}
+
+#[test]
+fn enums_reject_unknown_variants() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = ctx.eval("'Medium'", "test").expect("eval should succeed");
+    let err = Level::from_value(value).expect_err("expected conversion error");
+    assert!(matches!(err, JsError::Conversion { .. }));
+
+    let value = ctx.eval("({ a: 1, b: 2 })", "test").expect("eval should succeed");
+    let err = Shape::from_value(value).expect_err("expected conversion error");
+    assert!(matches!(err, JsError::Conversion { .. }));
+
+    let value = ctx.eval("'High'", "test").expect("eval should succeed");
+    assert_eq!(Level::from_value(value).expect("unit enum"), Level::High);
+}
+
+#[test]
+fn generic_struct_roundtrips() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = Wrapper { inner: vec![1i32, 2] }
+        .into_value(&ctx)
+        .expect("generic should convert");
+    let back = Wrapper::<Vec<i32>>::from_value(value).expect("generic back");
+    assert_eq!(back.inner, vec![1, 2]);
+}
@@ -1,4 +1,4 @@
-use mquickjs_rs::{Context, JsError};
+use mquickjs_rs::{Context, JsError, PathSegment};
 
 #[test]
 fn context_init_error_formats() {
@@ -30,10 +30,43 @@ fn exception_error_formats_with_stack() {
 fn conversion_error_formats() {
     let err = JsError::Conversion {
         message: "bad type".to_string(),
+        path: Vec::new(),
+        found: None,
     };
     assert_eq!(format!("{err}"), "conversion error: bad type");
 }
 
+#[test]
+fn conversion_error_formats_path_and_found() {
+    let err = JsError::Conversion {
+        message: "expected number".to_string(),
+        path: vec![
+            PathSegment::Key("items".to_string()),
+            PathSegment::Index(2),
+            PathSegment::Key("first name".to_string()),
+        ],
+        found: Some("string".to_string()),
+    };
+    assert_eq!(
+        format!("{err}"),
+        "conversion error: expected number at $.items[2][\"first name\"] (found string)"
+    );
+}
+
+#[test]
+fn conversion_error_path_is_built_outermost_first() {
+    let err = JsError::conversion("bad").at_index(1).at_key("list");
+    match err {
+        JsError::Conversion { path, .. } => {
+            assert_eq!(
+                path,
+                vec![PathSegment::Key("list".to_string()), PathSegment::Index(1)]
+            );
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
 #[test]
 fn callback_error_formats() {
     let err = JsError::Callback {
@@ -74,3 +107,15 @@ fn eval_error_without_stack_for_non_error() {
         other => panic!("unexpected error: {other:?}"),
     }
 }
+
+#[test]
+fn out_of_memory_error_formats() {
+    let err = JsError::OutOfMemory {
+        requested: 64,
+        heap_size: 1024,
+    };
+    assert_eq!(
+        err.to_string(),
+        "out of memory: failed to allocate 64 bytes in a 1024 byte heap"
+    );
+}
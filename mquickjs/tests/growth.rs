@@ -0,0 +1,103 @@
+use mquickjs_rs::{Context, HeapGrowth, JsError, Object};
+
+const INITIAL: usize = 64 * 1024;
+const MAX: usize = 1024 * 1024;
+const FILL: &str = "var items = [];\nfor (var i = 0; i < 10000; i++) items.push('item ' + i);\nitems.length";
+
+#[test]
+fn heap_grows_past_its_initial_size() {
+    let fixed = Context::new(INITIAL).expect("context should initialize");
+    let err = fixed.eval(FILL, "test").expect_err("fixed heap should run out of memory");
+    assert!(matches!(err, JsError::OutOfMemory { .. }));
+
+    let ctx = Context::with_growth(HeapGrowth::new(INITIAL, MAX)).expect("context should initialize");
+    assert_eq!(ctx.buffer_bytes(), INITIAL);
+    assert_eq!(ctx.eval_i32(FILL, "test").expect("eval should succeed"), 10000);
+    let grown = ctx.buffer_bytes();
+    assert!(grown > INITIAL && grown <= MAX);
+    assert_eq!(ctx.memory_stats().heap_size, grown);
+}
+
+#[test]
+fn values_survive_heap_moves() {
+    let ctx = Context::with_growth(HeapGrowth::new(INITIAL, MAX)).expect("context should initialize");
+    let value = ctx.eval("({ name: 'kept', list: [1, 2, 3] })", "test").expect("eval should succeed");
+    let rooted = ctx.root(value);
+    ctx.eval("function total(list) { return list[0] + list[1] + list[2]; }", "test")
+        .expect("eval should succeed");
+    ctx.eval(FILL, "test").expect("eval should succeed");
+    assert!(ctx.buffer_bytes() > INITIAL);
+
+    let object = Object::from_value(&ctx, rooted.to_value()).expect("object");
+    assert_eq!(object.get::<String>("name").expect("name"), "kept");
+    assert_eq!(object.get::<Vec<i32>>("list").expect("list"), vec![1, 2, 3]);
+    let total = ctx.eval_i32("total([1, 2, 3]) + items.length", "test").expect("eval should succeed");
+    assert_eq!(total, 10006);
+}
+
+#[test]
+fn growth_stops_at_the_maximum() {
+    let ctx = Context::with_growth(HeapGrowth::new(INITIAL, 256 * 1024))
+        .expect("context should initialize");
+    let err = ctx
+        .eval("var items = [];\nfor (var i = 0; ; i++) items.push('item ' + i);", "test")
+        .expect_err("script should run out of memory");
+    match err {
+        JsError::OutOfMemory { heap_size, .. } => assert_eq!(heap_size, 256 * 1024),
+        other => panic!("expected out of memory, got {other:?}"),
+    }
+}
+
+#[test]
+fn heap_shrinks_after_gc_when_enabled() {
+    let growth = HeapGrowth {
+        shrink: true,
+        ..HeapGrowth::new(INITIAL, MAX)
+    };
+    let ctx = Context::with_growth(growth).expect("context should initialize");
+    ctx.eval(FILL, "test").expect("eval should succeed");
+    let grown = ctx.buffer_bytes();
+    assert!(grown > INITIAL);
+
+    ctx.eval("items = null;", "test").expect("eval should succeed");
+    ctx.gc();
+    assert!(ctx.buffer_bytes() < grown);
+    assert_eq!(ctx.eval_i32("1 + 1", "test").expect("eval should succeed"), 2);
+}
+
+#[test]
+fn grown_contexts_can_be_snapshotted() {
+    let ctx = Context::with_growth(HeapGrowth::new(INITIAL, MAX)).expect("context should initialize");
+    ctx.eval(FILL, "test").expect("eval should succeed");
+    let image = ctx.snapshot().expect("snapshot should succeed");
+    assert_eq!(image.byte_len(), ctx.buffer_bytes());
+
+    let clone = Context::from_snapshot(&image).expect("clone should initialize");
+    assert_eq!(clone.buffer_bytes(), ctx.buffer_bytes());
+    assert_eq!(clone.eval_i32("items.length", "test").expect("eval should succeed"), 10000);
+    let length = clone
+        .eval_i32("for (var i = 0; i < 10000; i++) items.push(i); items.length", "test")
+        .expect("clone should keep growing");
+    assert_eq!(length, 20000);
+}
+
+#[test]
+fn reset_returns_to_the_initial_size() {
+    let mut ctx = Context::with_growth(HeapGrowth::new(INITIAL, MAX)).expect("context should initialize");
+    ctx.eval(FILL, "test").expect("eval should succeed");
+    ctx.reset().expect("reset should succeed");
+    assert_eq!(ctx.buffer_bytes(), INITIAL);
+}
+
+#[test]
+fn invalid_growth_policies_are_rejected() {
+    let err = Context::with_growth(HeapGrowth::new(MAX, INITIAL)).expect_err("max below initial");
+    assert!(matches!(err, JsError::ContextInit { .. }));
+
+    let growth = HeapGrowth {
+        factor: 1.0,
+        ..HeapGrowth::new(INITIAL, MAX)
+    };
+    let err = Context::with_growth(growth).expect_err("factor must be above 1");
+    assert!(matches!(err, JsError::ContextInit { .. }));
+}
@@ -0,0 +1,74 @@
+use mquickjs_rs::{Context, JsError};
+
+const FILL_LOCAL: &str = "(function () {\n  var items = [];\n  for (var i = 0; ; i++) items.push('item ' + i);\n})()";
+
+#[test]
+fn exhausting_the_heap_reports_out_of_memory() {
+    let ctx = Context::new(128 * 1024).expect("context should initialize");
+    let err = ctx.eval(FILL_LOCAL, "test").expect_err("script should run out of memory");
+    match err {
+        JsError::OutOfMemory {
+            requested,
+            heap_size,
+        } => {
+            assert!(requested > 0);
+            assert!(heap_size > 120 * 1024 && heap_size <= 128 * 1024);
+        }
+        other => panic!("expected out of memory, got {other:?}"),
+    }
+}
+
+#[test]
+fn context_is_usable_after_out_of_memory() {
+    let ctx = Context::new(128 * 1024).expect("context should initialize");
+    for _ in 0..3 {
+        let err = ctx.eval(FILL_LOCAL, "test").expect_err("script should run out of memory");
+        assert!(matches!(err, JsError::OutOfMemory { .. }));
+        ctx.gc();
+        assert_eq!(
+            ctx.eval_i32("[1, 2, 3].map(function (x) { return x * 2; })[2]", "test")
+                .expect("eval should succeed"),
+            6
+        );
+    }
+
+    ctx.eval("var kept = [];", "test").expect("eval should succeed");
+    let err = ctx
+        .eval("for (var i = 0; ; i++) kept.push('kept ' + i);", "test")
+        .expect_err("script should run out of memory");
+    assert!(matches!(err, JsError::OutOfMemory { .. }));
+    ctx.eval("kept = null;", "test").expect("eval should succeed");
+    ctx.gc();
+    let stats = ctx.memory_stats();
+    assert!(stats.free_bytes > stats.heap_size / 2);
+}
+
+#[test]
+fn other_internal_errors_stay_exceptions() {
+    let ctx = Context::new(128 * 1024).expect("context should initialize");
+    let _ = ctx.eval(FILL_LOCAL, "test");
+    let err = ctx
+        .eval("throw new Error('out of memory')", "test")
+        .expect_err("script should throw");
+    assert!(matches!(err, JsError::Exception { .. }));
+}
+
+#[test]
+fn caught_out_of_memory_does_not_mark_later_exceptions() {
+    let ctx = Context::new(128 * 1024).expect("context should initialize");
+    let script = "var items = [];\n\
+                  try { for (var i = 0; ; i++) items.push('item ' + i); } catch (e) { items = null; }\n\
+                  throw null;";
+    let err = ctx.eval(script, "test").expect_err("script should throw");
+    assert!(matches!(err, JsError::Exception { .. }), "{err:?}");
+}
+
+#[test]
+fn out_of_memory_is_reported_again_after_reset() {
+    let mut ctx = Context::new(128 * 1024).expect("context should initialize");
+    let err = ctx.eval(FILL_LOCAL, "test").expect_err("script should run out of memory");
+    assert!(matches!(err, JsError::OutOfMemory { .. }), "{err:?}");
+    ctx.reset().expect("reset should succeed");
+    let err = ctx.eval(FILL_LOCAL, "test").expect_err("script should run out of memory");
+    assert!(matches!(err, JsError::OutOfMemory { .. }), "{err:?}");
+}
@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use mquickjs_rs::{ContextPool, MemoryQuota, Value};
+
+#[test]
+fn pool_prewarms_and_reuses_contexts() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&created);
+    let pool = ContextPool::with_init(2, 256 * 1024, move |ctx| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        ctx.register_fn("echo", |args: &[Value<'_>]| Ok(args[0]))
+    })
+    .expect("pool should initialize");
+    assert_eq!(created.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.idle(), 2);
+
+    {
+        let ctx = pool.get().expect("context should be available");
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(ctx.eval_i32("echo(7)", "test").expect("eval should succeed"), 7);
+    }
+    assert_eq!(pool.idle(), 2);
+    // The returned context is reset and initialized again, not replaced.
+    assert_eq!(created.load(Ordering::SeqCst), 3);
+    let ctx = pool.get().expect("context should be available");
+    assert_eq!(ctx.eval_i32("echo(8)", "test").expect("eval should succeed"), 8);
+}
+
+#[test]
+fn pool_replaces_contexts_that_leak_globals() {
+    let pool = ContextPool::new(1, 256 * 1024).expect("pool should initialize");
+    {
+        let ctx = pool.get().expect("context should be available");
+        ctx.eval("var leaked = 1;", "test").expect("eval should succeed");
+    }
+    let ctx = pool.get().expect("context should be available");
+    let leaked = ctx
+        .eval_string("typeof leaked", "test")
+        .expect("eval should succeed");
+    assert_eq!(leaked, "undefined");
+}
+
+#[test]
+fn patched_prototypes_do_not_reach_the_next_borrower() {
+    let pool = ContextPool::new(1, 256 * 1024).expect("pool should initialize");
+    {
+        let ctx = pool.get().expect("context should be available");
+        ctx.eval("Array.prototype.join = function () { return 'patched'; };", "test")
+            .expect("eval should succeed");
+    }
+    let ctx = pool.get().expect("context should be available");
+    let joined = ctx
+        .eval_string("[1, 2].join()", "test")
+        .expect("eval should succeed");
+    assert_eq!(joined, "1,2");
+}
+
+#[test]
+fn leftover_timers_and_jobs_are_dropped() {
+    let pool = ContextPool::new(1, 256 * 1024).expect("pool should initialize");
+    {
+        let ctx = pool.get().expect("context should be available");
+        ctx.eval(
+            "setInterval(function () {}, 10);\nPromise.resolve().then(function () {});",
+            "test",
+        )
+        .expect("eval should succeed");
+        assert!(ctx.next_timer_deadline().is_some());
+        assert!(ctx.has_pending_jobs());
+    }
+    let ctx = pool.get().expect("context should be available");
+    assert_eq!(ctx.next_timer_deadline(), None);
+    assert!(!ctx.has_pending_jobs());
+}
+
+#[test]
+fn borrower_functions_and_settings_do_not_reach_the_next_borrower() {
+    let pool = ContextPool::with_init(1, 256 * 1024, |ctx| {
+        ctx.register_fn("echo", |args: &[Value<'_>]| Ok(args[0]))
+    })
+    .expect("pool should initialize");
+    {
+        let ctx = pool.get().expect("context should be available");
+        ctx.register_fn("extra", |args: &[Value<'_>]| Ok(args[0]))
+            .expect("register should succeed");
+        ctx.set_memory_quota(MemoryQuota::new(64 * 1024));
+    }
+    let ctx = pool.get().expect("context should be available");
+    let kinds = ctx
+        .eval_string("typeof extra + ' ' + typeof echo", "test")
+        .expect("eval should succeed");
+    assert_eq!(kinds, "undefined function");
+    assert_eq!(ctx.memory_quota(), None);
+}
+
+#[test]
+fn discarded_contexts_are_replaced() {
+    let pool = ContextPool::new(1, 256 * 1024).expect("pool should initialize");
+    let ctx = pool.get().expect("context should be available");
+    ctx.eval("Math.answer = 42;", "test").expect("eval should succeed");
+    ctx.discard();
+    assert_eq!(pool.idle(), 0);
+
+    let ctx = pool.get().expect("context should be available");
+    let answer = ctx
+        .eval_string("typeof Math.answer", "test")
+        .expect("eval should succeed");
+    assert_eq!(answer, "undefined");
+}
+
+#[test]
+fn pool_grows_on_demand_and_is_shared_across_threads() {
+    let pool = Arc::new(ContextPool::new(1, 256 * 1024).expect("pool should initialize"));
+    let first = pool.get().expect("context should be available");
+    let second = pool.get().expect("extra context should be created");
+    drop(first);
+    drop(second);
+    assert_eq!(pool.idle(), 1);
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || {
+                let ctx = pool.get().expect("context should be available");
+                ctx.eval_i32(&format!("{i} * 2"), "test").expect("eval should succeed")
+            })
+        })
+        .collect();
+    let results: Vec<i32> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("thread should finish"))
+        .collect();
+    assert_eq!(results, vec![0, 2, 4, 6]);
+}
@@ -0,0 +1,257 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+
+use mquickjs_rs::{Context, JsError, Object, Promise, PromiseState};
+
+fn context() -> Context {
+    Context::new(256 * 1024).expect("context should initialize")
+}
+
+fn set_global<'ctx>(ctx: &'ctx Context, name: &str, promise: &Promise<'ctx>) {
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(ctx, global).expect("global should be an object");
+    global
+        .set(name, promise.to_value())
+        .expect("set should succeed");
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Poll `future` until it completes; every poll drains the job queue.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = TaskContext::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn then_callbacks_run_as_jobs_in_order() {
+    let ctx = context();
+    ctx.eval(
+        "var log = [];\nPromise.resolve(1).then(function (v) { log.push('a' + v); return v + 1; })\n  .then(function (v) { log.push('c' + v); });\nqueueMicrotask(function () { log.push('b'); });\nlog.push('sync');",
+        "test",
+    )
+    .expect("eval should succeed");
+    assert_eq!(
+        ctx.eval_string("log.join()", "test")
+            .expect("eval should succeed"),
+        "sync"
+    );
+    assert!(ctx.has_pending_jobs());
+
+    assert_eq!(ctx.run_jobs().expect("jobs should run"), 3);
+    assert_eq!(
+        ctx.eval_string("log.join()", "test")
+            .expect("eval should succeed"),
+        "sync,a1,b,c2"
+    );
+    assert!(!ctx.has_pending_jobs());
+}
+
+#[test]
+fn executor_and_handler_exceptions_reject() {
+    let ctx = context();
+    ctx.eval(
+        "var log = [];\nnew Promise(function () { throw new Error('boom'); })\n  .catch(function (e) { log.push(e.message); throw new TypeError('again'); })\n  .then(null, function (e) { log.push(e.name); });\nnew Promise(function (resolve, reject) { reject('first'); resolve('ignored'); })\n  .catch(function (e) { log.push(e); });",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    assert_eq!(
+        ctx.eval_string("log.join()", "test")
+            .expect("eval should succeed"),
+        "boom,first,TypeError"
+    );
+}
+
+#[test]
+fn thenables_are_adopted() {
+    let ctx = context();
+    ctx.eval(
+        "var result;\nvar thenable = { then: function (resolve) { resolve(7); } };\nnew Promise(function (resolve) { resolve(thenable); })\n  .then(function (v) { return Promise.resolve(v * 2); })\n  .then(function (v) { result = v; });",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    assert_eq!(
+        ctx.eval_i32("result", "test").expect("eval should succeed"),
+        14
+    );
+}
+
+#[test]
+fn finally_passes_the_outcome_through() {
+    let ctx = context();
+    ctx.eval(
+        "var log = [];\nPromise.resolve(1).finally(function () { log.push('f1'); return 99; })\n  .then(function (v) { log.push('v' + v); });\nPromise.reject(2).finally(function () { log.push('f2'); })\n  .catch(function (e) { log.push('e' + e); });",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    assert_eq!(
+        ctx.eval_string("log.join()", "test")
+            .expect("eval should succeed"),
+        "f1,f2,v1,e2"
+    );
+}
+
+#[test]
+fn all_and_race_combine_promises() {
+    let ctx = context();
+    ctx.eval(
+        "var all, race, failed, empty;\nvar slow = new Promise(function (resolve) { queueMicrotask(function () { resolve('slow'); }); });\nPromise.all([slow, 2, Promise.resolve(3)]).then(function (v) { all = v.join(); });\nPromise.race([slow, Promise.resolve('fast')]).then(function (v) { race = v; });\nPromise.all([1, Promise.reject('no')]).catch(function (e) { failed = e; });\nPromise.all([]).then(function (v) { empty = v.length; });",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    assert_eq!(
+        ctx.eval_string("all", "test").expect("eval should succeed"),
+        "slow,2,3"
+    );
+    assert_eq!(
+        ctx.eval_string("race", "test")
+            .expect("eval should succeed"),
+        "fast"
+    );
+    assert_eq!(
+        ctx.eval_string("failed", "test")
+            .expect("eval should succeed"),
+        "no"
+    );
+    assert_eq!(
+        ctx.eval_i32("empty", "test").expect("eval should succeed"),
+        0
+    );
+}
+
+#[test]
+fn host_promises_resolve_script_handlers() {
+    let ctx = context();
+    let promise = Promise::new(&ctx).expect("promise should be created");
+    set_global(&ctx, "pending", &promise);
+    ctx.eval("var got; pending.then(function (v) { got = v; });", "test")
+        .expect("eval should succeed");
+    assert!(matches!(promise.state(), PromiseState::Pending));
+
+    promise.resolve("done").expect("resolve should succeed");
+    promise.reject("ignored").expect("reject should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    assert_eq!(
+        ctx.eval_string("got", "test").expect("eval should succeed"),
+        "done"
+    );
+    match promise.state() {
+        PromiseState::Fulfilled(value) => assert_eq!(value.to_string().expect("string"), "done"),
+        state => panic!("unexpected state {state:?}"),
+    }
+}
+
+#[test]
+fn promises_can_be_awaited_from_rust() {
+    let ctx = context();
+    let value = ctx
+        .eval(
+            "Promise.resolve(20).then(function (v) { return v + 1; })",
+            "test",
+        )
+        .expect("eval should succeed");
+    let promise = Promise::from_value(&ctx, value).expect("value should be a promise");
+    let value = block_on(promise).expect("promise should fulfill");
+    assert_eq!(value.to_i32().expect("number"), 21);
+
+    let value = ctx
+        .eval(
+            "new Promise(function (_, reject) { reject(new RangeError('bad')); })",
+            "test",
+        )
+        .expect("eval should succeed");
+    let promise = Promise::from_value(&ctx, value).expect("value should be a promise");
+    match block_on(promise) {
+        Err(JsError::Exception { message, .. }) => assert_eq!(message, "RangeError: bad"),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn microtask_exceptions_are_returned() {
+    let ctx = context();
+    ctx.eval(
+        "var ran = false;\nqueueMicrotask(function () { throw new Error('tick'); });\nqueueMicrotask(function () { ran = true; });",
+        "test",
+    )
+    .expect("eval should succeed");
+    match ctx.run_jobs() {
+        Err(JsError::Exception { message, .. }) => assert_eq!(message, "Error: tick"),
+        other => panic!("unexpected result {other:?}"),
+    }
+    assert_eq!(ctx.run_jobs().expect("jobs should run"), 1);
+    assert!(ctx.eval_bool("ran", "test").expect("eval should succeed"));
+}
+
+#[test]
+fn unreachable_promises_are_collected() {
+    let ctx = context();
+    ctx.eval(
+        "for (var i = 0; i < 50; i++) {\n  new Promise(function () {}).then(function () { return [1, 2, 3]; });\n}",
+        "test",
+    )
+    .expect("eval should succeed");
+    let count = |ctx: &Context| {
+        ctx.memory_stats()
+            .class("Promise")
+            .map_or(0, |class| class.count)
+    };
+    assert_eq!(count(&ctx), 100);
+
+    // The promises returned by `then` stay reachable from the pending
+    // promises' handlers until those are collected.
+    ctx.gc();
+    ctx.gc();
+    assert_eq!(count(&ctx), 0);
+}
+
+#[test]
+fn promises_reachable_only_from_their_handlers_are_collected() {
+    let ctx = context();
+    ctx.gc();
+    let before = ctx.memory_used();
+    ctx.eval(
+        "for (var n = 0; n < 20; n++) {\n  (function () {\n    var big = [];\n    for (var i = 0; i < 100; i++) big.push('item ' + i);\n    var p = new Promise(function () {});\n    p.then(function () { return [p, big]; });\n  })();\n}\nvar settled = Promise.resolve();\nsettled.then(function () { return settled; });",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.eval("settled = null;", "test")
+        .expect("eval should succeed");
+    ctx.run_jobs().expect("jobs should run");
+    ctx.gc();
+    assert!(
+        ctx.memory_used() < before + 16 * 1024,
+        "{} bytes still used after collecting, {before} before",
+        ctx.memory_used()
+    );
+    let count = ctx
+        .memory_stats()
+        .class("Promise")
+        .map_or(0, |class| class.count);
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn from_value_rejects_other_objects() {
+    let ctx = context();
+    let value = ctx
+        .eval("({ then: function () {} })", "test")
+        .expect("eval should succeed");
+    assert!(Promise::from_value(&ctx, value).is_err());
+}
@@ -0,0 +1,72 @@
+use std::sync::{Arc, Mutex};
+
+use mquickjs_rs::{Context, JsError, MemoryQuota, QuotaThreshold};
+
+const FILL: &str = "var items = [];\nfor (var i = 0; ; i++) items.push('item ' + i);";
+
+#[test]
+fn exceeding_the_quota_throws_a_catchable_range_error() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    ctx.set_memory_quota(MemoryQuota::new(128 * 1024));
+    assert_eq!(ctx.memory_quota(), Some(128 * 1024));
+
+    let caught = ctx
+        .eval_string(
+            "(function () {\n  try {\n    var items = [];\n    for (var i = 0; ; i++) items.push('item ' + i);\n  } catch (e) {\n    return (e instanceof RangeError) + ' ' + e.message;\n  }\n})()",
+            "test",
+        )
+        .expect("script should catch the error");
+    assert_eq!(caught, "true memory quota exceeded");
+    assert!(ctx.memory_used() <= 128 * 1024 + 4096);
+}
+
+#[test]
+fn uncaught_quota_errors_are_exceptions() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    ctx.set_memory_quota(MemoryQuota::new(128 * 1024));
+    let err = ctx.eval(FILL, "test").expect_err("script should exceed the quota");
+    match err {
+        JsError::Exception { message, .. } => {
+            assert_eq!(message, "RangeError: memory quota exceeded")
+        }
+        other => panic!("expected an exception, got {other:?}"),
+    }
+
+    ctx.eval("items = null;", "test").expect("eval should succeed");
+    ctx.clear_memory_quota();
+    assert_eq!(ctx.memory_quota(), None);
+    ctx.eval("var more = []; for (var i = 0; i < 10000; i++) more.push('more ' + i);", "test")
+        .expect("eval should succeed without a quota");
+}
+
+#[test]
+fn thresholds_fire_once_until_usage_drops() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let events: Arc<Mutex<Vec<QuotaThreshold>>> = Arc::default();
+    let sink = events.clone();
+    ctx.on_memory_threshold(move |event| sink.lock().unwrap().push(event));
+    ctx.set_memory_quota(MemoryQuota::new(256 * 1024).threshold(80).threshold(50));
+
+    let fill = "var items = [];\ntry {\n  for (var i = 0; ; i++) items.push('item ' + i);\n} catch (e) {\n  items = null;\n}";
+    ctx.eval(fill, "test").expect("script should catch the error");
+    let percents: Vec<u8> = events.lock().unwrap().iter().map(|event| event.percent).collect();
+    assert_eq!(percents, [50, 80]);
+    let last = events.lock().unwrap()[1];
+    assert!(last.used_bytes > 256 * 1024 * 80 / 100);
+    assert_eq!(last.limit_bytes, 256 * 1024);
+
+    ctx.gc();
+    ctx.eval(fill, "test").expect("script should catch the error");
+    assert_eq!(events.lock().unwrap().len(), 4);
+}
+
+#[test]
+fn panicking_threshold_callbacks_do_not_unwind_into_the_engine() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    ctx.on_memory_threshold(|_| panic!("threshold callback panicked"));
+    ctx.set_memory_quota(MemoryQuota::new(256 * 1024).threshold(50));
+
+    let fill = "var items = [];\ntry {\n  for (var i = 0; ; i++) items.push('item ' + i);\n} catch (e) {\n  items = null;\n}";
+    ctx.eval(fill, "test").expect("script should catch the error");
+    assert_eq!(ctx.eval_i32("1 + 1", "test").expect("eval should succeed"), 2);
+}
@@ -0,0 +1,114 @@
+use std::thread;
+
+use mquickjs_rs::{CaptureConsole, Context, ContextBuilder, ManualClock, MemoryQuota, Value};
+
+fn prepared_context() -> Context {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.register_fn("echo", |args: &[Value<'_>]| Ok(args[0]))
+        .expect("register should succeed");
+    ctx.eval(
+        "var config = { name: 'tenant', limits: [1, 2, 3] };\nfunction double(x) { return echo(x) * 2; }\nfunction total() { return config.limits.reduce(function (a, b) { return a + b; }, 0); }\nvar counter = 0;",
+        "prelude",
+    )
+    .expect("prelude should run");
+    ctx
+}
+
+#[test]
+fn snapshot_keeps_globals_and_host_functions() {
+    let ctx = prepared_context();
+    let image = ctx.snapshot().expect("snapshot should succeed");
+    drop(ctx);
+
+    let clone = Context::from_snapshot(&image).expect("clone should initialize");
+    assert_eq!(clone.eval_i32("total()", "test").expect("eval should succeed"), 6);
+    assert_eq!(
+        clone.eval_string("config.name", "test").expect("eval should succeed"),
+        "tenant"
+    );
+    assert_eq!(clone.eval_i32("double(21)", "test").expect("eval should succeed"), 42);
+    clone.eval("config.extra = config.name + '-copy'; var more = [1, 2, 3].map(double);", "test")
+        .expect("eval should succeed");
+    clone.gc();
+    assert_eq!(clone.eval_i32("more[2]", "test").expect("eval should succeed"), 6);
+}
+
+#[test]
+fn snapshot_clones_are_independent() {
+    let ctx = prepared_context();
+    let image = ctx.snapshot().expect("snapshot should succeed");
+    let first = Context::from_snapshot(&image).expect("clone should initialize");
+    let second = Context::from_snapshot(&image).expect("clone should initialize");
+
+    first.eval("counter = 10; config.limits.push(4);", "test").expect("eval should succeed");
+    assert_eq!(first.eval_i32("total()", "test").expect("eval should succeed"), 10);
+    assert_eq!(second.eval_i32("counter", "test").expect("eval should succeed"), 0);
+    assert_eq!(second.eval_i32("total()", "test").expect("eval should succeed"), 6);
+    assert_eq!(ctx.eval_i32("counter", "test").expect("eval should succeed"), 0);
+}
+
+#[test]
+fn snapshot_fails_while_values_are_rooted() {
+    let ctx = prepared_context();
+    let value = ctx.eval("config", "test").expect("eval should succeed");
+    let rooted = ctx.root(value);
+    assert!(ctx.snapshot().is_err());
+    drop(rooted);
+    assert!(ctx.snapshot().is_ok());
+}
+
+#[test]
+fn snapshot_clones_run_on_other_threads() {
+    let image = prepared_context().snapshot().expect("snapshot should succeed");
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let image = image.clone();
+            thread::spawn(move || {
+                let ctx = Context::from_snapshot(&image).expect("clone should initialize");
+                ctx.eval_i32(&format!("counter = {i}; double(counter) + total()"), "test")
+                    .expect("eval should succeed")
+            })
+        })
+        .collect();
+    let results: Vec<i32> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("thread should finish"))
+        .collect();
+    assert_eq!(results, vec![6, 8, 10, 12]);
+}
+
+#[test]
+fn snapshot_clones_keep_the_memory_quota() {
+    let ctx = prepared_context();
+    ctx.set_memory_quota(MemoryQuota::new(128 * 1024));
+    let image = ctx.snapshot().expect("snapshot should succeed");
+
+    let clone = Context::from_snapshot(&image).expect("clone should initialize");
+    assert_eq!(clone.memory_quota(), Some(128 * 1024));
+    let caught = clone
+        .eval_string(
+            "(function () {\n  try {\n    var items = [];\n    for (var i = 0; ; i++) items.push('item ' + i);\n  } catch (e) {\n    items = null;\n    return e.message;\n  }\n})()",
+            "test",
+        )
+        .expect("script should catch the error");
+    assert_eq!(caught, "memory quota exceeded");
+}
+
+#[test]
+fn snapshot_clones_take_callbacks_from_the_builder() {
+    let ctx = prepared_context();
+    let image = ctx.snapshot().expect("snapshot should succeed");
+
+    let clock = ManualClock::new(1000.0);
+    let console = CaptureConsole::new();
+    let clone = ContextBuilder::new()
+        .snapshot(&image)
+        .clock(clock.clone())
+        .console(console.clone())
+        .build()
+        .expect("clone should initialize");
+    assert_eq!(clone.eval_f64("Date.now()", "test").expect("eval should succeed"), 1000.0);
+    clock.advance(5.0);
+    clone.eval("console.log(Date.now(), total())", "test").expect("eval should succeed");
+    assert_eq!(console.lines(), ["1005 6"]);
+}
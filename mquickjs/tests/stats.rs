@@ -0,0 +1,43 @@
+use mquickjs_rs::Context;
+
+#[test]
+fn memory_stats_account_for_the_whole_buffer() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    let stats = ctx.memory_stats();
+    assert!(stats.heap_size > 250 * 1024 && stats.heap_size <= 256 * 1024);
+    assert_eq!(stats.used_bytes + stats.free_bytes, stats.heap_size);
+    assert!(stats.object_count > 0);
+    assert!(stats.object_bytes <= stats.used_bytes);
+    let objects: usize = stats.classes.iter().map(|class| class.count).sum();
+    assert_eq!(objects, stats.object_count);
+}
+
+#[test]
+fn memory_stats_track_allocations_and_collection() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.gc();
+    let before = ctx.memory_stats();
+    let arrays_before = before.class("Array").map_or(0, |class| class.count);
+
+    ctx.eval(
+        "var kept = [];\nfor (var i = 0; i < 50; i++) kept.push([i, 'item ' + i]);\nfunction helper(x) { return x + 1; }",
+        "test",
+    )
+    .expect("eval should succeed");
+    ctx.gc();
+    let after = ctx.memory_stats();
+    let arrays = after.class("Array").expect("arrays should be counted");
+    assert_eq!(arrays.count, arrays_before + 51);
+    assert!(after.string_bytes > before.string_bytes);
+    assert!(after.bytecode_count > before.bytecode_count);
+    assert!(after.free_bytes < before.free_bytes);
+
+    ctx.eval("kept = null;", "test").expect("eval should succeed");
+    ctx.gc();
+    let released = ctx.memory_stats();
+    assert_eq!(
+        released.class("Array").map_or(0, |class| class.count),
+        arrays_before
+    );
+    assert!(released.free_bytes > after.free_bytes);
+}
@@ -0,0 +1,170 @@
+use mquickjs_rs::{Context, FromValue, IntoValue, JsData, JsError, Object, Symbol, ValueKind};
+
+fn context() -> Context {
+    Context::new(256 * 1024).expect("context should initialize")
+}
+
+#[test]
+fn symbols_are_unique_and_keep_their_description() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var a = Symbol('tag');\nvar b = Symbol('tag');\n[typeof a, a === a, a === b, a.description, String(a), Symbol().description, Symbol().toString(), typeof Symbol.iterator, Symbol.iterator === Symbol.iterator].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(
+        value,
+        "symbol,true,false,tag,Symbol(tag),,Symbol(),symbol,true"
+    );
+
+    let err = ctx
+        .eval("new Symbol('tag')", "test")
+        .expect_err("symbols are not constructed with new");
+    assert!(err.to_string().contains("TypeError"), "{err}");
+}
+
+#[test]
+fn symbol_keys_are_distinct_and_not_enumerated() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var key = Symbol('key');\nvar other = Symbol('key');\nvar obj = { key: 'string' };\nobj[key] = 'symbol';\nvar names = [];\nfor (var name in obj) names.push(name);\n[obj[key], obj[other], obj.key, obj.hasOwnProperty(key), Object.keys(obj).join(), names.join(), JSON.stringify(obj)].join(' ')",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "symbol  string true key key {\"key\":\"string\"}");
+}
+
+#[test]
+fn symbols_are_primitives() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var s = Symbol('tag');\n[s instanceof Symbol, s == 'Symbol(tag)', s == s, typeof s, s.toString(), String(s), JSON.stringify([s])].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "false,false,true,symbol,Symbol(tag),Symbol(tag),[null]");
+
+    for script in [
+        "var s = Symbol('tag'); s.x = 1;",
+        "var s = Symbol('tag'); s[Symbol.iterator] = 1;",
+        "Object.keys(Symbol('tag'))",
+        "Object.defineProperty(Symbol('tag'), 'x', { value: 1 })",
+        "'' + Symbol('tag')",
+        "Symbol('tag') + 1",
+        "+Symbol('tag')",
+        "'x' in Symbol('tag')",
+        "for (var x of Symbol('tag')) {}",
+        "var o = { toString: function () { return Symbol('tag'); } }; '' + o",
+    ] {
+        let err = ctx.eval(script, "test").expect_err("script should throw");
+        assert!(err.to_string().contains("TypeError"), "{script}: {err}");
+    }
+
+    let err = ctx
+        .eval("throw Symbol('boom')", "test")
+        .expect_err("script should throw");
+    assert!(err.to_string().contains("Symbol(boom)"), "{err}");
+}
+
+#[test]
+fn for_of_uses_the_iterator_protocol() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "function range(n) {\n  var it = {};\n  it[Symbol.iterator] = function () {\n    var i = 0;\n    return { next: function () { return i < n ? { value: 'x' + i++, done: false } : { done: true }; } };\n  };\n  return it;\n}\nvar seen = [];\nfor (var a of range(3)) {\n  for (var b of range(2)) seen.push(a + b);\n}\nfor (var c of range(10)) { if (c === 'x2') break; seen.push(c); }\nfor (var d of [1, 2]) seen.push(d);\nseen.join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "x0x0,x0x1,x1x0,x1x1,x2x0,x2x1,x0,x1,1,2");
+}
+
+#[test]
+fn iteration_survives_garbage_collection() {
+    let ctx = context();
+    let value = ctx
+        .eval_string(
+            "var it = {};\nit[Symbol.iterator] = function () {\n  var i = 0;\n  return { next: function () {\n    var garbage = [];\n    for (var j = 0; j < 200; j++) garbage.push({ j: j, s: 'g' + j });\n    return { value: { n: i }, done: i++ >= 50 };\n  } };\n};\nvar sum = 0;\nfor (var v of it) sum += v.n;\n'' + sum",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "1225");
+}
+
+#[test]
+fn invalid_iterators_throw_type_errors() {
+    let ctx = context();
+    for script in [
+        "for (var x of {}) {}",
+        "for (var x of 1) {}",
+        "var o = {}; o[Symbol.iterator] = function () { return 1; }; for (var x of o) {}",
+        "var o = {}; o[Symbol.iterator] = function () { return { next: function () { return 1; } }; }; for (var x of o) {}",
+        "var o = {}; o[Symbol.iterator] = function () { return {}; }; for (var x of o) {}",
+        "var o = {}; o[Symbol.iterator] = function () { return { next: 1 }; }; for (var x of o) {}",
+    ] {
+        let err = ctx.eval(script, "test").expect_err("script should throw");
+        assert!(err.to_string().contains("TypeError"), "{script}: {err}");
+    }
+}
+
+#[test]
+fn rust_symbols_convert_from_descriptions() {
+    let ctx = context();
+    let global = ctx.eval("globalThis", "test").expect("eval should succeed");
+    let global = Object::from_value(&ctx, global).expect("global should be an object");
+
+    let symbol = Symbol::from("tag");
+    global.set("a", symbol.clone()).expect("set should succeed");
+    global.set("b", symbol).expect("set should succeed");
+    global
+        .set("c", Symbol::default())
+        .expect("set should succeed");
+
+    let value = ctx
+        .eval_string(
+            "[typeof a, a === b, a.description, b.description, c.toString()].join()",
+            "test",
+        )
+        .expect("eval should succeed");
+    assert_eq!(value, "symbol,false,tag,tag,Symbol()");
+}
+
+#[test]
+fn symbol_values_convert_to_rust() {
+    let ctx = context();
+    let value = ctx
+        .eval("Symbol('tag')", "test")
+        .expect("eval should succeed");
+    assert_eq!(value.kind(), ValueKind::Symbol);
+    assert!(value.is_symbol());
+    assert!(!value.is_object());
+    let symbol = Symbol::from_value(value).expect("symbol should convert");
+    assert_eq!(symbol.description(), Some("tag"));
+
+    let value = Symbol::default()
+        .into_value(&ctx)
+        .expect("symbol should convert");
+    let symbol = Symbol::from_value(value).expect("symbol should convert");
+    assert_eq!(symbol.description(), None);
+
+    let value = ctx
+        .eval("Symbol.iterator", "test")
+        .expect("eval should succeed");
+    let symbol = Symbol::from_value(value).expect("symbol should convert");
+    assert_eq!(symbol.description(), Some("Symbol.iterator"));
+
+    let value = ctx.eval("'tag'", "test").expect("eval should succeed");
+    let err = Symbol::from_value(value).expect_err("strings are not symbols");
+    assert!(
+        matches!(err, JsError::Conversion { found: Some(ref found), .. } if found == "string"),
+        "{err}"
+    );
+
+    let value = ctx
+        .eval("Symbol('tag')", "test")
+        .expect("eval should succeed");
+    let err = JsData::from_value(value).expect_err("symbols are not data");
+    assert!(err.to_string().contains("symbol"), "{err}");
+}
@@ -0,0 +1,127 @@
+use mquickjs_rs::{Context, ContextBuilder, JsError, ManualClock};
+
+fn manual_context() -> (Context, ManualClock) {
+    let clock = ManualClock::new(0.0);
+    let ctx = ContextBuilder::new()
+        .clock(clock.clone())
+        .build()
+        .expect("context should initialize");
+    (ctx, clock)
+}
+
+#[test]
+fn timeouts_run_in_deadline_order_once_due() {
+    let (ctx, clock) = manual_context();
+    ctx.eval(
+        "var log = [];\nsetTimeout(function (a, b) { log.push('b' + a + b); }, 20, 1, 2);\nsetTimeout(function () { log.push('a'); }, 10);",
+        "test",
+    )
+    .expect("eval should succeed");
+    assert_eq!(ctx.next_timer_deadline(), Some(10.0));
+    assert_eq!(ctx.run_timers().expect("timers should run"), 0);
+
+    clock.advance(25.0);
+    assert_eq!(ctx.run_timers().expect("timers should run"), 2);
+    assert_eq!(ctx.eval_string("log.join()", "test").expect("eval should succeed"), "a,b12");
+    assert_eq!(ctx.next_timer_deadline(), None);
+}
+
+#[test]
+fn cleared_timers_do_not_run() {
+    let (ctx, clock) = manual_context();
+    ctx.eval(
+        "var ran = false;\nvar id = setTimeout(function () { ran = true; }, 5);\nclearTimeout(id);",
+        "test",
+    )
+    .expect("eval should succeed");
+    clock.advance(10.0);
+    assert_eq!(ctx.run_timers().expect("timers should run"), 0);
+    assert!(!ctx.eval_bool("ran", "test").expect("eval should succeed"));
+}
+
+#[test]
+fn intervals_repeat_until_cleared() {
+    let (ctx, _clock) = manual_context();
+    ctx.eval(
+        "var ticks = 0;\nvar id = setInterval(function () { if (++ticks === 3) clearInterval(id); }, 100);",
+        "test",
+    )
+    .expect("eval should succeed");
+
+    assert_eq!(ctx.run_until_idle(10).expect("timers should run"), 3);
+    assert_eq!(ctx.eval_i32("ticks", "test").expect("eval should succeed"), 3);
+    assert_eq!(ctx.eval_i32("performance.now()", "test").expect("eval should succeed"), 300);
+}
+
+#[test]
+fn zero_delay_intervals_wait_a_millisecond() {
+    let (ctx, clock) = manual_context();
+    ctx.eval("var ticks = 0; setInterval(function () { ticks++; }, 0);", "test")
+        .expect("eval should succeed");
+    assert_eq!(ctx.next_timer_deadline(), Some(1.0));
+    assert_eq!(ctx.run_timers().expect("timers should run"), 0);
+
+    clock.advance(1.0);
+    assert_eq!(ctx.run_timers().expect("timers should run"), 1);
+    assert_eq!(ctx.eval_i32("ticks", "test").expect("eval should succeed"), 1);
+    assert_eq!(ctx.next_timer_deadline(), Some(2.0));
+}
+
+#[test]
+fn run_until_idle_stops_after_max_callbacks() {
+    let (ctx, _clock) = manual_context();
+    ctx.eval("var ticks = 0; setInterval(function () { ticks++; }, 0);", "test")
+        .expect("eval should succeed");
+    assert_eq!(ctx.run_until_idle(5).expect("timers should run"), 5);
+    assert_eq!(ctx.eval_i32("ticks", "test").expect("eval should succeed"), 5);
+    assert!(ctx.next_timer_deadline().is_some());
+}
+
+#[test]
+fn callback_exceptions_are_returned() {
+    let (ctx, _clock) = manual_context();
+    ctx.eval(
+        "var after = false;\nsetTimeout(function () { throw new Error('boom'); }, 0);\nsetTimeout(function () { after = true; }, 0);",
+        "test",
+    )
+    .expect("eval should succeed");
+
+    let err = ctx.run_timers().expect_err("the first timer throws");
+    match err {
+        JsError::Exception { message, .. } => assert_eq!(message, "Error: boom"),
+        other => panic!("expected an exception, got {other:?}"),
+    }
+    assert_eq!(ctx.run_timers().expect("timers should run"), 1);
+    assert!(ctx.eval_bool("after", "test").expect("eval should succeed"));
+}
+
+#[test]
+fn set_timeout_requires_a_function() {
+    let (ctx, _clock) = manual_context();
+    let caught = ctx
+        .eval_string(
+            "(function () {\n  try {\n    setTimeout(1, 0);\n  } catch (e) {\n    return (e instanceof TypeError) + ' ' + e.message;\n  }\n})()",
+            "test",
+        )
+        .expect("script should catch the error");
+    assert_eq!(caught, "true not a function");
+}
+
+#[test]
+fn reset_drops_pending_timers() {
+    let (mut ctx, clock) = manual_context();
+    ctx.eval("setTimeout(function () {}, 10);", "test").expect("eval should succeed");
+    ctx.reset().expect("reset should succeed");
+    assert_eq!(ctx.next_timer_deadline(), None);
+    clock.advance(20.0);
+    assert_eq!(ctx.run_timers().expect("timers should run"), 0);
+}
+
+#[test]
+fn system_clock_timers_run_until_idle() {
+    let ctx = Context::new(256 * 1024).expect("context should initialize");
+    ctx.eval("var done = false; setTimeout(function () { done = true; }, 5);", "test")
+        .expect("eval should succeed");
+    assert_eq!(ctx.run_until_idle(10).expect("timers should run"), 1);
+    assert!(ctx.eval_bool("done", "test").expect("eval should succeed"));
+}
@@ -0,0 +1,62 @@
+use mquickjs_rs::{Context, ValueKind};
+
+#[test]
+fn kind_distinguishes_primitives() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let cases = [
+        ("undefined", ValueKind::Undefined),
+        ("null", ValueKind::Null),
+        ("true", ValueKind::Bool),
+        ("42", ValueKind::Int),
+        ("3.5", ValueKind::Float),
+        ("1e300", ValueKind::Float),
+        ("'a'", ValueKind::String),
+        ("'hello'", ValueKind::String),
+    ];
+    for (source, expected) in cases {
+        let value = ctx.eval(source, "test").expect("eval should succeed");
+        assert_eq!(value.kind(), expected, "kind of {source}");
+    }
+}
+
+#[test]
+fn kind_maps_object_classes() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let cases = [
+        ("({a: 1})", ValueKind::Object),
+        ("[1, 2]", ValueKind::Array),
+        ("(function () {})", ValueKind::Function),
+        ("Math.max", ValueKind::Function),
+        ("new TypeError('x')", ValueKind::Error),
+        ("/a+/", ValueKind::RegExp),
+        ("new ArrayBuffer(4)", ValueKind::ArrayBuffer),
+        ("new Uint8Array(4)", ValueKind::Uint8Array),
+        ("new Float64Array(2)", ValueKind::Float64Array),
+    ];
+    for (source, expected) in cases {
+        let value = ctx.eval(source, "test").expect("eval should succeed");
+        assert_eq!(value.kind(), expected, "kind of {source}");
+    }
+}
+
+#[test]
+fn predicates_follow_kind() {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+
+    let value = ctx.eval("[1]", "test").expect("eval should succeed");
+    assert!(value.is_array());
+    assert!(value.is_object());
+    assert!(!value.is_function());
+
+    let value = ctx.eval("2.5", "test").expect("eval should succeed");
+    assert!(value.is_number());
+    assert!(!value.is_object());
+
+    let value = ctx.eval("new Error('boom')", "test").expect("eval should succeed");
+    assert!(value.is_error());
+
+    assert!(ValueKind::Int32Array.is_typed_array());
+    assert!(!ValueKind::ArrayBuffer.is_typed_array());
+    assert_eq!(ValueKind::Float.to_string(), "number");
+    assert!(!ValueKind::Internal.is_object());
+}
@@ -0,0 +1,120 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use mquickjs_rs::{ClonedValue, Context, FromValue, JsError, Worker};
+
+fn message(ctx: &Context, source: &str) -> ClonedValue {
+    let value = ctx.eval(source, "test").expect("eval should succeed");
+    ClonedValue::from_value(value).expect("message should clone")
+}
+
+fn read<T: for<'ctx> FromValue<'ctx>>(message: &ClonedValue) -> T {
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = message.to_value(&ctx).expect("message should materialize");
+    T::from_value(value).expect("message should convert")
+}
+
+#[test]
+fn worker_echoes_messages() {
+    let worker = Worker::new(
+        "onmessage = function (event) {\n\
+           postMessage({ doubled: event.data.n * 2, tags: event.data.tags });\n\
+         };",
+    )
+    .expect("worker should spawn");
+
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    worker
+        .post_message(message(&ctx, "({ n: 21, tags: ['a'] })"))
+        .expect("post should succeed");
+
+    let reply = worker.recv().expect("reply should arrive");
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    let value = reply.to_value(&ctx).expect("reply should materialize");
+    let object = mquickjs_rs::Object::from_value(&ctx, value).expect("reply should be an object");
+    assert_eq!(object.get::<i32>("doubled").expect("doubled should read"), 42);
+    assert_eq!(
+        object.get::<Vec<String>>("tags").expect("tags should read"),
+        vec!["a".to_string()]
+    );
+}
+
+#[test]
+fn worker_script_can_post_on_startup() {
+    let worker = Worker::new("postMessage('ready');").expect("worker should spawn");
+    let reply = worker.recv().expect("message should arrive");
+    assert_eq!(read::<String>(&reply), "ready");
+}
+
+#[test]
+fn on_message_handler_receives_worker_messages() {
+    let worker = Worker::new(
+        "onmessage = function (event) { postMessage(event.data + 1); };",
+    )
+    .expect("worker should spawn");
+    let (tx, rx) = mpsc::channel();
+    worker.on_message(move |message| {
+        tx.send(message).expect("send should succeed");
+    });
+
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    worker.post_message(message(&ctx, "41")).expect("post should succeed");
+    let reply = rx.recv_timeout(Duration::from_secs(5)).expect("handler should run");
+    assert_eq!(read::<i32>(&reply), 42);
+}
+
+#[test]
+fn panicking_handlers_stay_installed() {
+    let worker = Worker::new(
+        "onmessage = function (event) { postMessage(event.data); };",
+    )
+    .expect("worker should spawn");
+    let (tx, rx) = mpsc::channel();
+    worker.on_message(move |message| {
+        let value = read::<i32>(&message);
+        assert_ne!(value, 1, "handler panicked");
+        tx.send(value).expect("send should succeed");
+    });
+
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    worker.post_message(message(&ctx, "1")).expect("post should succeed");
+    let err = worker.recv().expect_err("the panic should surface as an error");
+    assert!(err.to_string().contains("callback panicked"), "{err}");
+
+    worker.post_message(message(&ctx, "2")).expect("post should succeed");
+    let value = rx.recv_timeout(Duration::from_secs(5)).expect("handler should run");
+    assert_eq!(value, 2);
+
+    let (tx, rx) = mpsc::channel();
+    worker.on_message(move |message| tx.send(read::<i32>(&message)).expect("send should succeed"));
+    worker.post_message(message(&ctx, "3")).expect("post should succeed");
+    let value = rx.recv_timeout(Duration::from_secs(5)).expect("new handler should run");
+    assert_eq!(value, 3);
+}
+
+#[test]
+fn worker_errors_are_reported() {
+    let worker = Worker::new(
+        "onmessage = function (event) { throw new Error('bad ' + event.data); };",
+    )
+    .expect("worker should spawn");
+    let ctx = Context::new(1024 * 1024).expect("context should initialize");
+    worker.post_message(message(&ctx, "'input'")).expect("post should succeed");
+
+    let err = worker.recv().expect_err("error should arrive");
+    assert!(matches!(err, JsError::Exception { ref message, .. } if message.contains("bad input")));
+
+    let worker = Worker::new("throw new TypeError('startup');").expect("worker should spawn");
+    let err = worker.recv().expect_err("error should arrive");
+    assert!(matches!(err, JsError::Exception { ref message, .. } if message.contains("startup")));
+    let err = worker.recv().expect_err("worker should have exited");
+    assert!(matches!(err, JsError::Runtime { .. }));
+}
+
+#[test]
+fn terminate_interrupts_running_script() {
+    let worker = Worker::new("postMessage('started'); for (;;) {}").expect("worker should spawn");
+    let started = worker.recv().expect("worker should start");
+    assert_eq!(read::<String>(&started), "started");
+    worker.terminate();
+}